@@ -114,6 +114,14 @@ export const SOL_PRICE_FEED = new PublicKey(
 );
 const POOL_SEED = Buffer.from("pool");
 const BET_SEED = Buffer.from("bet");
+const LEDGER_SEED = Buffer.from("ledger");
+const EXPOSURE_SEED = Buffer.from("exposure");
+const LP_SEED = Buffer.from("lp");
+const SHARE_MINT_SEED = Buffer.from("share-mint");
+// The demo holds one ticket at a time, so it only ever uses the first Bet PDA.
+const BET_ID = 0;
+const ROUND_FEE_BPS = 0;
+const MAX_PRICE_AGE_SECONDS = 300;
 
 class KeypairWallet implements anchor.Wallet {
   constructor(readonly payer: Keypair) {}
@@ -204,8 +212,39 @@ export function poolPda(programId: PublicKey, mint: PublicKey): PublicKey {
   return pda([POOL_SEED, mint.toBuffer()], programId);
 }
 
-export function betPda(programId: PublicKey, user: PublicKey): PublicKey {
-  return pda([BET_SEED, user.toBuffer()], programId);
+export function betPda(
+  programId: PublicKey,
+  user: PublicKey,
+  betId: number = BET_ID,
+): PublicKey {
+  return pda(
+    [BET_SEED, user.toBuffer(), new BN(betId).toArrayLike(Buffer, "le", 8)],
+    programId,
+  );
+}
+
+export function ledgerPda(programId: PublicKey, pool: PublicKey): PublicKey {
+  return pda([LEDGER_SEED, pool.toBuffer()], programId);
+}
+
+export function exposurePda(
+  programId: PublicKey,
+  pool: PublicKey,
+  user: PublicKey,
+): PublicKey {
+  return pda([EXPOSURE_SEED, pool.toBuffer(), user.toBuffer()], programId);
+}
+
+export function lpPositionPda(
+  programId: PublicKey,
+  pool: PublicKey,
+  owner: PublicKey,
+): PublicKey {
+  return pda([LP_SEED, pool.toBuffer(), owner.toBuffer()], programId);
+}
+
+export function shareMintPda(programId: PublicKey, pool: PublicKey): PublicKey {
+  return pda([SHARE_MINT_SEED, pool.toBuffer()], programId);
 }
 
 export function eata(owner: PublicKey, mint: PublicKey): PublicKey {
@@ -501,6 +540,7 @@ export async function bootstrapMarket(
     admin.publicKey,
   );
   const pool = poolPda(program.programId, mint);
+  const ledger = ledgerPda(program.programId, pool);
   const poolAta = getAssociatedTokenAddressSync(mint, pool, true);
   const poolEata = eata(pool, mint);
   const vaultPda = vault(mint);
@@ -539,6 +579,13 @@ export async function bootstrapMarket(
       new BN(options.durationSeconds),
       new BN(options.minStake),
       new BN(options.payoutBps),
+      new BN(ROUND_FEE_BPS),
+      { maxUserStake: new BN(0), maxDirectionStake: new BN(0) },
+      {
+        kind: { pyth: {} },
+        maxAgeSeconds: new BN(MAX_PRICE_AGE_SECONDS),
+        maxConfidenceBps: new BN(0),
+      },
     )
     .preInstructions([
       ComputeBudgetProgram.setComputeUnitLimit({ units: 1_000_000 }),
@@ -547,6 +594,9 @@ export async function bootstrapMarket(
       admin: admin.publicKey,
       mint,
       pool,
+      ledger,
+      adminLpPosition: lpPositionPda(program.programId, pool, admin.publicKey),
+      shareMint: shareMintPda(program.programId, pool),
       poolTokenAccount: poolAta,
       adminTokenAccount: adminAta,
       poolEphemeralAta: poolEata,
@@ -577,10 +627,56 @@ export async function bootstrapMarket(
     .transaction();
   await sendSignedTransaction(base.connection, initializeTx, admin);
 
+  const delegateLedgerTx = await (program as any).methods
+    .delegateLedger()
+    .accountsPartial({
+      payer: admin.publicKey,
+      mint,
+      pool,
+      ledger,
+    })
+    .remainingAccounts([
+      { pubkey: VALIDATOR, isSigner: false, isWritable: false },
+    ])
+    .transaction();
+  await sendSignedTransaction(base.connection, delegateLedgerTx, admin);
+
   options.onLog({ tone: "info", message: "Preparing user bet account" });
+  const exposure = exposurePda(program.programId, pool, user.publicKey);
+  const initializeExposureTx = await (program as any).methods
+    .initializeExposure()
+    .accountsPartial({
+      payer: admin.publicKey,
+      user: user.publicKey,
+      mint,
+      pool,
+      exposure,
+      systemProgram: SystemProgram.programId,
+    })
+    .transaction();
+  await sendSignedTransaction(base.connection, initializeExposureTx, admin);
+
+  const delegateExposureTx = await (program as any).methods
+    .delegateExposure()
+    .accountsPartial({
+      payer: admin.publicKey,
+      user: user.publicKey,
+      mint,
+      pool,
+      exposure,
+    })
+    .remainingAccounts([
+      { pubkey: VALIDATOR, isSigner: false, isWritable: false },
+    ])
+    .signers([user])
+    .transaction();
+  await sendSignedTransaction(base.connection, delegateExposureTx, admin, [
+    user,
+  ]);
+
   const bet = betPda(program.programId, user.publicKey);
   const initializeBetTx = await (program as any).methods
-    .initializeBet()
+    .initializeBet(new BN(BET_ID))
     .accountsPartial({
       payer: admin.publicKey,
       user: user.publicKey,
@@ -591,7 +687,7 @@ export async function bootstrapMarket(
   await sendSignedTransaction(base.connection, initializeBetTx, admin);
 
   const delegateBetTx = await (program as any).methods
-    .delegateBet()
+    .delegateBet(new BN(BET_ID))
     .accountsPartial({
       payer: admin.publicKey,
       user: user.publicKey,
@@ -643,6 +739,8 @@ export async function placeBet(
   const mint = new PublicKey(market.mint);
   const pool = poolPda(erProgram.programId, mint);
   const bet = betPda(erProgram.programId, user.publicKey);
+  const ledger = ledgerPda(erProgram.programId, pool);
+  const exposure = exposurePda(erProgram.programId, pool, user.publicKey);
   const poolTokenAccount = new PublicKey(market.poolAta);
   const userTokenAccount = new PublicKey(market.userAta);
 
@@ -653,6 +751,8 @@ export async function placeBet(
       user: user.publicKey,
       mint,
       pool,
+      ledger,
+      exposure,
       bet,
       userTokenAccount,
       poolTokenAccount,
@@ -690,6 +790,8 @@ export async function settleBet(
       user: user.publicKey,
       mint,
       pool,
+      ledger: ledgerPda(erProgram.programId, pool),
+      exposure: exposurePda(erProgram.programId, pool, user.publicKey),
       bet,
       userTokenAccount: new PublicKey(market.userAta),
       poolTokenAccount: new PublicKey(market.poolAta),
@@ -38,4 +38,34 @@ pub enum ErrorCode {
     MathOverflow,
     #[msg("tie has no directional outcome")]
     TieHasNoDirection,
+    #[msg("account is not one of the user's bet PDAs")]
+    InvalidBetAccount,
+    #[msg("bet belongs to a parimutuel round")]
+    RoundBet,
+    #[msg("bet does not belong to this round")]
+    WrongRound,
+    #[msg("round is not accepting bets")]
+    RoundNotOpen,
+    #[msg("round has not reached its lock time")]
+    RoundNotLockable,
+    #[msg("round is not locked")]
+    RoundNotLocked,
+    #[msg("round has not reached its resolve time")]
+    RoundNotResolvable,
+    #[msg("round is not resolved")]
+    RoundNotResolved,
+    #[msg("bet would exceed the user's open stake limit")]
+    UserStakeLimit,
+    #[msg("bet would exceed the direction's open stake limit")]
+    DirectionStakeLimit,
+    #[msg("not enough LP shares")]
+    InsufficientShares,
+    #[msg("oracle price is older than the pool allows")]
+    StalePrice,
+    #[msg("oracle confidence interval is wider than the pool allows")]
+    PriceTooUncertain,
+    #[msg("bet was placed against a different pool")]
+    WrongPool,
+    #[msg("oracle has no price near the round's timestamp yet")]
+    RoundPriceNotReady,
 }
@@ -7,17 +7,28 @@ use ephemeral_rollups_sdk::ephem::MagicIntentBundleBuilder;
 use session_keys::{session_auth_or, Session, SessionError, SessionTokenV2};
 
 mod error;
+mod oracle;
 mod state;
 mod utils;
 
 use error::ErrorCode;
-use state::{Bet, Direction, Pool};
+use oracle::{read_price, read_price_at};
+use state::{
+    Bet, Direction, Exposure, LpPosition, ManualOracle, OracleConfig, Pool, PoolLedger, RiskLimits,
+    Round, RoundStatus,
+};
 use utils::*;
 
 declare_id!("7HHiv8th2wY24iZp2ReF7QkJyFJHwHWCgZWg7CWrQnnm");
 
 pub const POOL_SEED: &[u8] = b"pool";
 pub const BET_SEED: &[u8] = b"bet";
+pub const ROUND_SEED: &[u8] = b"round";
+pub const LEDGER_SEED: &[u8] = b"ledger";
+pub const EXPOSURE_SEED: &[u8] = b"exposure";
+pub const LP_SEED: &[u8] = b"lp";
+pub const MANUAL_ORACLE_SEED: &[u8] = b"manual-oracle";
+pub const SHARE_MINT_SEED: &[u8] = b"share-mint";
 
 pub const EPHEMERAL_SPL_TOKEN_PROGRAM_ID: Pubkey =
     pubkey!("SPLxh1LVZzEkX99H6rqYizhytLWPZVV296zyYDPagv2");
@@ -27,8 +38,10 @@ pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
 pub const MAGIC_PROGRAM_ID: Pubkey = pubkey!("Magic11111111111111111111111111111111111111");
 pub const MAGIC_CONTEXT_ID: Pubkey = pubkey!("MagicContext1111111111111111111111111111111");
 
-pub const MAX_PRICE_AGE_SECONDS: u64 = 300;
 pub const BASIS_POINTS_DENOMINATOR: u64 = 10_000;
+/// How far from a round's lock or resolve time the snapshotted price may have
+/// been published.
+pub const ROUND_PRICE_TOLERANCE_SECONDS: i64 = 2;
 
 #[ephemeral]
 #[program]
@@ -37,7 +50,12 @@ pub mod binary_prediction {
 
     /// Creates the prediction pool and moves its starting liquidity into ER custody.
     /// The Pool PDA stores market config and owns the pool token account that is
-    /// deposited into an EATA and delegated to the ER.
+    /// deposited into an EATA and delegated to the ER. The pool's liability
+    /// ledger is created alongside it; `delegate_ledger` moves it to the ER.
+    /// The seed liquidity is credited to the admin as the pool's first LP
+    /// shares, and the pool's LP share mint is created for `mint_shares`.
+    /// `oracle` picks the adapter used to read `price_feed` and the pool's
+    /// staleness and confidence thresholds.
     pub fn initialize(
         ctx: Context<Initialize>,
         price_feed: Pubkey,
@@ -46,6 +64,9 @@ pub mod binary_prediction {
         bet_duration_seconds: i64,
         min_stake: u64,
         payout_bps: u64,
+        round_fee_bps: u64,
+        limits: RiskLimits,
+        oracle: OracleConfig,
     ) -> Result<()> {
         require!(seed_amount > 0, ErrorCode::InvalidAmount);
         require!(bet_duration_seconds > 0, ErrorCode::InvalidPoolConfig);
@@ -54,6 +75,23 @@ pub mod binary_prediction {
             payout_bps >= BASIS_POINTS_DENOMINATOR,
             ErrorCode::InvalidPoolConfig
         );
+        require!(
+            round_fee_bps <= BASIS_POINTS_DENOMINATOR,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            limits.max_user_stake == 0 || limits.max_user_stake >= min_stake,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            limits.max_direction_stake == 0 || limits.max_direction_stake >= min_stake,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(oracle.max_age_seconds > 0, ErrorCode::InvalidPoolConfig);
+        require!(
+            oracle.max_confidence_bps <= BASIS_POINTS_DENOMINATOR,
+            ErrorCode::InvalidPoolConfig
+        );
 
         let pool_key = ctx.accounts.pool.key();
         let pool = &mut ctx.accounts.pool;
@@ -65,6 +103,20 @@ pub mod binary_prediction {
         pool.min_stake = min_stake;
         pool.payout_bps = payout_bps;
         pool.bump = ctx.bumps.pool;
+        pool.round_fee_bps = round_fee_bps;
+        pool.limits = limits;
+        pool.oracle = oracle;
+
+        let ledger = &mut ctx.accounts.ledger;
+        ledger.pool = pool_key;
+        ledger.bump = ctx.bumps.ledger;
+        ledger.total_shares = seed_amount;
+
+        let admin_lp_position = &mut ctx.accounts.admin_lp_position;
+        admin_lp_position.pool = pool_key;
+        admin_lp_position.owner = ctx.accounts.admin.key();
+        admin_lp_position.shares = seed_amount;
+        admin_lp_position.bump = ctx.bumps.admin_lp_position;
 
         let cpi_accounts = SplTransfer {
             from: ctx.accounts.admin_token_account.to_account_info(),
@@ -145,27 +197,75 @@ pub mod binary_prediction {
         Ok(())
     }
 
-    /// Creates the user's Bet PDA on the base layer.
-    /// Each user has one reusable Bet account; `settle` clears it so the same PDA
-    /// can be used again.
-    pub fn initialize_bet(ctx: Context<InitializeBet>) -> Result<()> {
+    /// Delegates the pool's liability ledger to the ER, where bets and
+    /// settlements update it.
+    pub fn delegate_ledger(ctx: Context<DelegateLedger>) -> Result<()> {
+        let validator = ctx.remaining_accounts.first().map(|account| account.key());
+        ctx.accounts.delegate_ledger(
+            &ctx.accounts.payer,
+            &[LEDGER_SEED, ctx.accounts.pool.key().as_ref()],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Creates the user's Exposure PDA on the base layer. Every bet the user
+    /// places is counted against it, so it has to exist (and be delegated with
+    /// `delegate_exposure`) before their first bet.
+    pub fn initialize_exposure(ctx: Context<InitializeExposure>) -> Result<()> {
+        let exposure = &mut ctx.accounts.exposure;
+        exposure.pool = ctx.accounts.pool.key();
+        exposure.user = ctx.accounts.user.key();
+        exposure.bump = ctx.bumps.exposure;
+        Ok(())
+    }
+
+    /// Delegates the user's Exposure PDA to the ER.
+    pub fn delegate_exposure(ctx: Context<DelegateExposure>) -> Result<()> {
+        let validator = ctx.remaining_accounts.first().map(|account| account.key());
+        ctx.accounts.delegate_exposure(
+            &ctx.accounts.payer,
+            &[
+                EXPOSURE_SEED,
+                ctx.accounts.pool.key().as_ref(),
+                ctx.accounts.user.key().as_ref(),
+            ],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Creates one of the user's Bet PDAs on the base layer.
+    /// Users pick their own `bet_id`s and can hold one open position per id, so
+    /// positions across expiries overlap. `settle` clears a position so the same
+    /// PDA can be used again.
+    pub fn initialize_bet(ctx: Context<InitializeBet>, bet_id: u64) -> Result<()> {
         let bet = &mut ctx.accounts.bet;
         require!(!bet.is_open, ErrorCode::BetAlreadyOpen);
-        bet.is_open = false;
-        bet.stake = 0;
-        bet.open_price = 0;
-        bet.expiry_ts = 0;
+        bet.bet_id = bet_id;
+        bet.bump = ctx.bumps.bet;
+        bet.reset();
         Ok(())
     }
 
-    /// Delegates the user's Bet PDA to the ER.
+    /// Delegates one of the user's Bet PDAs to the ER.
     /// After delegation, `place_bet` and `settle` can update the Bet account with
     /// low latency on the ephemeral runtime.
-    pub fn delegate_bet(ctx: Context<DelegateBet>) -> Result<()> {
+    pub fn delegate_bet(ctx: Context<DelegateBet>, bet_id: u64) -> Result<()> {
         let validator = ctx.remaining_accounts.first().map(|account| account.key());
         ctx.accounts.delegate_bet(
             &ctx.accounts.payer,
-            &[BET_SEED, ctx.accounts.user.key().as_ref()],
+            &[
+                BET_SEED,
+                ctx.accounts.user.key().as_ref(),
+                &bet_id.to_le_bytes(),
+            ],
             DelegateConfig {
                 validator,
                 ..Default::default()
@@ -174,10 +274,10 @@ pub mod binary_prediction {
         Ok(())
     }
 
-    /// Commits the user's Bet PDA back to the base layer and undelegates it.
-    /// This is optional for normal play; users only need it when they want the
-    /// account back, for example before closing it to reclaim rent.
-    pub fn undelegate_bet(ctx: Context<UndelegateBet>) -> Result<()> {
+    /// Commits one of the user's Bet PDAs back to the base layer and undelegates
+    /// it. This is optional for normal play; users only need it when they want
+    /// the account back, for example before closing it to reclaim rent.
+    pub fn undelegate_bet(ctx: Context<UndelegateBet>, _bet_id: u64) -> Result<()> {
         MagicIntentBundleBuilder::new(
             ctx.accounts.payer.to_account_info(),
             ctx.accounts.magic_context.to_account_info(),
@@ -188,7 +288,7 @@ pub mod binary_prediction {
         Ok(())
     }
 
-    /// Opens one UP or DOWN prediction on the ER.
+    /// Opens one UP or DOWN prediction on the ER, in the Bet PDA passed in.
     /// The payer spends from the user's token account as either the user signer
     /// or an approved session delegate, records the current oracle price, and
     /// sets the earliest settlement time.
@@ -216,11 +316,20 @@ pub mod binary_prediction {
             )?;
         }
 
-        let open_price = read_price(&ctx.accounts.price_update, &ctx.accounts.pool.price_feed_id)?;
+        let open_price = read_price(&ctx.accounts.price_update, &ctx.accounts.pool)?;
         let now = Clock::get()?.unix_timestamp;
         let required_payout = checked_payout(stake, ctx.accounts.pool.payout_bps)?;
+        let limits = ctx.accounts.pool.limits;
+        ctx.accounts.exposure.add(stake, limits.max_user_stake)?;
+        ctx.accounts.ledger.open_bet(
+            direction,
+            stake,
+            required_payout,
+            limits.max_direction_stake,
+        )?;
         // Solvency is checked against the balance *after* the stake lands, since
-        // the stake is transferred into the pool below and backs the payout.
+        // the stake is transferred into the pool below. It has to cover the
+        // worst case across every open position, this one included.
         let available_after_stake = ctx
             .accounts
             .pool_token_account
@@ -228,7 +337,7 @@ pub mod binary_prediction {
             .checked_add(stake)
             .ok_or(ErrorCode::MathOverflow)?;
         require!(
-            available_after_stake >= required_payout,
+            available_after_stake >= ctx.accounts.ledger.worst_case_liability()?,
             ErrorCode::InsufficientLiquidity
         );
 
@@ -248,15 +357,22 @@ pub mod binary_prediction {
         bet.direction = direction;
         bet.stake = stake;
         bet.is_open = true;
+        bet.pool = ctx.accounts.pool.key();
 
         Ok(())
     }
 
-    /// Settles an expired bet using the latest oracle price.
+    /// Settles one expired position using the latest oracle price.
     /// Winners receive the configured basis-point payout, ties refund the stake,
     /// and losses pay nothing. The Bet account is cleared afterward.
     pub fn settle(ctx: Context<Settle>) -> Result<()> {
         require!(ctx.accounts.bet.is_open, ErrorCode::BetNotOpen);
+        require!(!ctx.accounts.bet.is_round_bet(), ErrorCode::RoundBet);
+        require_keys_eq!(
+            ctx.accounts.bet.pool,
+            ctx.accounts.pool.key(),
+            ErrorCode::WrongPool
+        );
         require_keys_eq!(
             ctx.accounts.price_update.key(),
             ctx.accounts.pool.price_feed,
@@ -266,17 +382,12 @@ pub mod binary_prediction {
         let now = Clock::get()?.unix_timestamp;
         require!(now >= ctx.accounts.bet.expiry_ts, ErrorCode::BetNotExpired);
 
-        let settle_price =
-            read_price(&ctx.accounts.price_update, &ctx.accounts.pool.price_feed_id)?;
-        let win_payout = checked_payout(ctx.accounts.bet.stake, ctx.accounts.pool.payout_bps)?;
-        let payout = if settle_price == ctx.accounts.bet.open_price {
-            ctx.accounts.bet.stake
-        } else if ctx.accounts.bet.direction == outcome(settle_price, ctx.accounts.bet.open_price)?
-        {
-            win_payout
-        } else {
-            0
-        };
+        let settle_price = read_price(&ctx.accounts.price_update, &ctx.accounts.pool)?;
+        let payout = bet_payout(
+            &ctx.accounts.bet,
+            settle_price,
+            ctx.accounts.pool.payout_bps,
+        )?;
 
         if payout > 0 {
             pool_signed_transfer(
@@ -290,220 +401,1199 @@ pub mod binary_prediction {
             )?;
         }
 
+        let liability = checked_payout(ctx.accounts.bet.stake, ctx.accounts.pool.payout_bps)?;
         let bet = &mut ctx.accounts.bet;
-        bet.is_open = false;
-        bet.stake = 0;
-        bet.open_price = 0;
-        bet.expiry_ts = 0;
+        ctx.accounts
+            .ledger
+            .close_bet(bet.direction, bet.stake, liability);
+        ctx.accounts.exposure.release(bet.stake);
+        bet.reset();
 
         Ok(())
     }
-}
 
-/// Accounts for pool creation and one-time liquidity delegation.
-/// The Pool PDA owns the pool token account and its EATA is delegated to the ER.
-#[derive(Accounts)]
-#[instruction(
-    price_feed: Pubkey,
-    price_feed_id: [u8; 32],
-    seed_amount: u64,
-    bet_duration_seconds: i64,
-    min_stake: u64,
-    payout_bps: u64
-)]
-pub struct Initialize<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub mint: Account<'info, Mint>,
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + Pool::LEN,
-        seeds = [POOL_SEED, mint.key().as_ref()],
-        bump
-    )]
-    pub pool: Account<'info, Pool>,
-    #[account(
-        init_if_needed,
-        payer = admin,
-        associated_token::mint = mint,
-        associated_token::authority = pool
-    )]
-    pub pool_token_account: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        constraint = admin_token_account.owner == admin.key() @ ErrorCode::InvalidTokenOwner,
-        constraint = admin_token_account.mint == mint.key() @ ErrorCode::MintMismatch
-    )]
-    pub admin_token_account: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        constraint = pool_ephemeral_ata.key() == ephemeral_ata_pda(&pool.key(), &mint.key()) @ ErrorCode::InvalidEphemeralAta
-    )]
-    /// CHECK: created and delegated by the Ephemeral SPL Token program.
-    pub pool_ephemeral_ata: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = vault.key() == vault_pda(&mint.key()) @ ErrorCode::InvalidVault
-    )]
-    /// CHECK: created by the Ephemeral SPL Token program.
-    pub vault: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = vault_ephemeral_ata.key() == ephemeral_ata_pda(&vault.key(), &mint.key()) @ ErrorCode::InvalidEphemeralAta
-    )]
-    /// CHECK: created and delegated by the Ephemeral SPL Token program.
-    pub vault_ephemeral_ata: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = vault_token_account.key() == associated_token_pda(&vault.key(), &mint.key()) @ ErrorCode::InvalidVaultAta
-    )]
-    /// CHECK: token account created idempotently for the e-token vault.
-    pub vault_token_account: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = pool_eata_buffer.key() == eata_buffer_address(&pool_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
-    )]
-    /// CHECK: delegation buffer for the pool EATA.
-    pub pool_eata_buffer: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = pool_eata_record.key() == record_pda(&pool_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
-    )]
-    /// CHECK: delegation record for the pool EATA.
-    pub pool_eata_record: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = pool_eata_metadata.key() == metadata_pda(&pool_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
-    )]
-    /// CHECK: delegation metadata for the pool EATA.
-    pub pool_eata_metadata: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = vault_eata_buffer.key() == eata_buffer_address(&vault_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
-    )]
-    /// CHECK: delegation buffer for the vault EATA.
-    pub vault_eata_buffer: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = vault_eata_record.key() == record_pda(&vault_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
-    )]
-    /// CHECK: delegation record for the vault EATA.
-    pub vault_eata_record: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        constraint = vault_eata_metadata.key() == metadata_pda(&vault_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
-    )]
-    /// CHECK: delegation metadata for the vault EATA.
-    pub vault_eata_metadata: UncheckedAccount<'info>,
-    #[account(address = EPHEMERAL_SPL_TOKEN_PROGRAM_ID)]
-    /// CHECK: fixed Ephemeral SPL Token program id.
-    pub ephemeral_token_program: UncheckedAccount<'info>,
-    #[account(address = DELEGATION_PROGRAM_ID)]
-    /// CHECK: fixed Delegation program id.
-    pub delegation_program: UncheckedAccount<'info>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-}
+    /// Settles every expired open position among the user's Bet PDAs passed as
+    /// remaining accounts, at one oracle price and with a single payout
+    /// transfer. Positions that are closed, not yet expired, or in a
+    /// parimutuel round are skipped, so a client can pass all of a user's bets.
+    pub fn settle_expired(ctx: Context<SettleExpired>) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.price_update.key(),
+            ctx.accounts.pool.price_feed,
+            ErrorCode::InvalidPriceFeed
+        );
 
-#[derive(Accounts)]
-pub struct InitializeBet<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    /// CHECK: user authority for the bet PDA.
-    pub user: UncheckedAccount<'info>,
-    #[account(
-        init_if_needed,
-        payer = payer,
-        space = 8 + Bet::LEN,
-        seeds = [BET_SEED, user.key().as_ref()],
-        bump
-    )]
-    pub bet: Account<'info, Bet>,
-    pub system_program: Program<'info, System>,
-}
+        let now = Clock::get()?.unix_timestamp;
+        let settle_price = read_price(&ctx.accounts.price_update, &ctx.accounts.pool)?;
+        let user_key = ctx.accounts.user.key();
+        let mut total_payout: u64 = 0;
+        let mut settled = 0;
 
-/// Accounts for delegating a Bet PDA to the ER.
-#[delegate]
-#[derive(Accounts)]
-pub struct DelegateBet<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub user: Signer<'info>,
-    #[account(mut, del, seeds = [BET_SEED, user.key().as_ref()], bump)]
-    /// CHECK: deserialized by delegated instructions after delegation.
-    pub bet: UncheckedAccount<'info>,
-}
+        for bet_info in ctx.remaining_accounts {
+            require!(bet_info.is_writable, ErrorCode::InvalidBetAccount);
+            let mut bet = Account::<Bet>::try_from(bet_info)?;
+            let expected_bet = Pubkey::create_program_address(
+                &[
+                    BET_SEED,
+                    user_key.as_ref(),
+                    &bet.bet_id.to_le_bytes(),
+                    &[bet.bump],
+                ],
+                ctx.program_id,
+            )
+            .map_err(|_| ErrorCode::InvalidBetAccount)?;
+            require_keys_eq!(bet_info.key(), expected_bet, ErrorCode::InvalidBetAccount);
+            if !bet.is_open || bet.is_round_bet() || now < bet.expiry_ts {
+                continue;
+            }
+            require_keys_eq!(bet.pool, ctx.accounts.pool.key(), ErrorCode::WrongPool);
 
-/// Accounts for returning a delegated Bet PDA to the base layer.
-#[commit]
-#[derive(Accounts)]
-pub struct UndelegateBet<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub user: Signer<'info>,
-    #[account(mut, seeds = [BET_SEED, user.key().as_ref()], bump)]
-    pub bet: Account<'info, Bet>,
-}
+            let payout = bet_payout(&bet, settle_price, ctx.accounts.pool.payout_bps)?;
+            total_payout = total_payout
+                .checked_add(payout)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let liability = checked_payout(bet.stake, ctx.accounts.pool.payout_bps)?;
+            ctx.accounts
+                .ledger
+                .close_bet(bet.direction, bet.stake, liability);
+            ctx.accounts.exposure.release(bet.stake);
+            bet.reset();
+            bet.exit(ctx.program_id)?;
+            settled += 1;
+        }
 
-/// Accounts for opening a prediction on the ER.
-/// A session payer must be approved as delegate for the user's token account.
-#[derive(Accounts, Session)]
-pub struct PlaceBet<'info> {
-    pub payer: Signer<'info>,
-    /// CHECK: user authority for the bet and session token.
-    pub user: UncheckedAccount<'info>,
-    pub mint: Account<'info, Mint>,
-    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
-    pub pool: Account<'info, Pool>,
-    #[account(mut, seeds = [BET_SEED, user.key().as_ref()], bump)]
-    pub bet: Account<'info, Bet>,
-    #[account(
-        mut,
-        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
-        constraint = user_token_account.mint == pool.mint @ ErrorCode::MintMismatch
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        constraint = pool_token_account.key() == associated_token_pda(&pool.key(), &pool.mint) @ ErrorCode::InvalidTokenOwner,
-        constraint = pool_token_account.owner == pool.key() @ ErrorCode::InvalidTokenOwner,
-        constraint = pool_token_account.mint == pool.mint @ ErrorCode::MintMismatch
-    )]
-    pub pool_token_account: Account<'info, TokenAccount>,
-    /// CHECK: external ephemeral-oracle PriceUpdateV2 account; key checked against Pool.price_feed.
-    pub price_update: UncheckedAccount<'info>,
-    pub token_program: Program<'info, Token>,
-    #[session(signer = payer, authority = user.key())]
-    pub session_token: Option<Account<'info, SessionTokenV2>>,
-}
+        if total_payout > 0 {
+            pool_signed_transfer(
+                ctx.accounts.pool_token_account.to_account_info(),
+                ctx.accounts.user_token_account.to_account_info(),
+                ctx.accounts.pool.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                total_payout,
+                ctx.accounts.pool.mint,
+                ctx.accounts.pool.bump,
+            )?;
+        }
+        msg!("Settled {} bets, paid {}", settled, total_payout);
 
-#[derive(Accounts)]
-pub struct Settle<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    /// CHECK: user authority for the bet.
-    pub user: UncheckedAccount<'info>,
-    pub mint: Account<'info, Mint>,
-    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
-    pub pool: Account<'info, Pool>,
-    #[account(mut, seeds = [BET_SEED, user.key().as_ref()], bump)]
-    pub bet: Account<'info, Bet>,
-    #[account(
-        mut,
-        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
-        constraint = user_token_account.mint == pool.mint @ ErrorCode::MintMismatch
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        constraint = pool_token_account.key() == associated_token_pda(&pool.key(), &pool.mint) @ ErrorCode::InvalidTokenOwner,
-        constraint = pool_token_account.owner == pool.key() @ ErrorCode::InvalidTokenOwner,
-        constraint = pool_token_account.mint == pool.mint @ ErrorCode::MintMismatch
-    )]
-    pub pool_token_account: Account<'info, TokenAccount>,
-    /// CHECK: external ephemeral-oracle PriceUpdateV2 account; key checked against Pool.price_feed.
-    pub price_update: UncheckedAccount<'info>,
+        Ok(())
+    }
+
+    /// Creates a parimutuel round on the base layer. Anyone can open one; it
+    /// takes bets until `lock_ts` and resolves `bet_duration_seconds` later.
+    pub fn open_round(ctx: Context<OpenRound>, round_id: u64, lock_ts: i64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(lock_ts > now, ErrorCode::InvalidPoolConfig);
+
+        let round = &mut ctx.accounts.round;
+        round.pool = ctx.accounts.pool.key();
+        round.round_id = round_id;
+        round.open_ts = now;
+        round.lock_ts = lock_ts;
+        round.resolve_ts = lock_ts
+            .checked_add(ctx.accounts.pool.bet_duration_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        round.status = RoundStatus::Open;
+        round.bump = ctx.bumps.round;
+        Ok(())
+    }
+
+    /// Delegates a Round PDA to the ER, where bets, lock and resolve run.
+    pub fn delegate_round(ctx: Context<DelegateRound>, round_id: u64) -> Result<()> {
+        let validator = ctx.remaining_accounts.first().map(|account| account.key());
+        ctx.accounts.delegate_round(
+            &ctx.accounts.payer,
+            &[
+                ROUND_SEED,
+                ctx.accounts.pool.key().as_ref(),
+                &round_id.to_le_bytes(),
+            ],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Stakes on UP or DOWN in an open parimutuel round, using one of the
+    /// user's Bet PDAs as the position. The stake joins the round's side total
+    /// and is held on the pool ledger until claimed; unlike `place_bet` no
+    /// liquidity check is needed, since payouts only ever come out of the
+    /// round's own stakes. Counts towards the user's open stake limit.
+    #[session_auth_or(
+        ctx.accounts.user.key() == ctx.accounts.payer.key(),
+        SessionError::InvalidToken
+    )]
+    pub fn place_round_bet(
+        ctx: Context<PlaceRoundBet>,
+        direction: Direction,
+        stake: u64,
+    ) -> Result<()> {
+        require!(
+            stake >= ctx.accounts.pool.min_stake,
+            ErrorCode::StakeTooSmall
+        );
+        require!(!ctx.accounts.bet.is_open, ErrorCode::BetAlreadyOpen);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            ctx.accounts.round.status == RoundStatus::Open && now < ctx.accounts.round.lock_ts,
+            ErrorCode::RoundNotOpen
+        );
+        ctx.accounts
+            .exposure
+            .add(stake, ctx.accounts.pool.limits.max_user_stake)?;
+        let ledger = &mut ctx.accounts.ledger;
+        ledger.round_liability = ledger
+            .round_liability
+            .checked_add(stake)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if ctx.accounts.payer.key() != ctx.accounts.user.key() {
+            require_token_delegate(
+                &ctx.accounts.user_token_account,
+                ctx.accounts.payer.key(),
+                stake,
+            )?;
+        }
+
+        signer_transfer(
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.pool_token_account.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            stake,
+        )?;
+
+        let round_key = ctx.accounts.round.key();
+        let round = &mut ctx.accounts.round;
+        let side_total = match direction {
+            Direction::Up => &mut round.up_total,
+            Direction::Down => &mut round.down_total,
+        };
+        *side_total = side_total
+            .checked_add(stake)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let bet = &mut ctx.accounts.bet;
+        bet.open_price = 0;
+        bet.expiry_ts = round.resolve_ts;
+        bet.direction = direction;
+        bet.stake = stake;
+        bet.is_open = true;
+        bet.round = round_key;
+        bet.pool = ctx.accounts.pool.key();
+
+        Ok(())
+    }
+
+    /// Closes betting on a round and snapshots the lock price. Permissionless
+    /// crank, callable once `lock_ts` has passed. Only a price published within
+    /// `ROUND_PRICE_TOLERANCE_SECONDS` of `lock_ts` is used, so whoever cranks
+    /// can't pick a favourable later tick; once the feed has moved past that
+    /// window, the round refunds instead.
+    pub fn lock_round(ctx: Context<CrankRound>) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.price_update.key(),
+            ctx.accounts.pool.price_feed,
+            ErrorCode::InvalidPriceFeed
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let round = &mut ctx.accounts.round;
+        require!(round.status == RoundStatus::Open, ErrorCode::RoundNotOpen);
+        require!(now >= round.lock_ts, ErrorCode::RoundNotLockable);
+        let Some(lock_price) = read_price_at(
+            &ctx.accounts.price_update,
+            &ctx.accounts.pool,
+            round.lock_ts,
+        )?
+        else {
+            round.refund_all();
+            msg!(
+                "Round {} has no price at lock, refunding every stake",
+                round.round_id
+            );
+            return Ok(());
+        };
+
+        round.lock_price = lock_price;
+        round.status = RoundStatus::Locked;
+        Ok(())
+    }
+
+    /// Snapshots the resolve price and fixes the round's outcome. A flat price,
+    /// or a round where nobody backed the winning side, refunds every stake;
+    /// otherwise the pool keeps `round_fee_bps` of the losing side and the rest
+    /// is split among the winners by `claim_round_bet`. Permissionless crank;
+    /// like `lock_round`, it refunds when no price near `resolve_ts` is left.
+    pub fn resolve_round(ctx: Context<CrankRound>) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.price_update.key(),
+            ctx.accounts.pool.price_feed,
+            ErrorCode::InvalidPriceFeed
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let round = &mut ctx.accounts.round;
+        require!(
+            round.status == RoundStatus::Locked,
+            ErrorCode::RoundNotLocked
+        );
+        require!(now >= round.resolve_ts, ErrorCode::RoundNotResolvable);
+        let Some(resolve_price) = read_price_at(
+            &ctx.accounts.price_update,
+            &ctx.accounts.pool,
+            round.resolve_ts,
+        )?
+        else {
+            round.refund_all();
+            msg!(
+                "Round {} has no price at resolve, refunding every stake",
+                round.round_id
+            );
+            return Ok(());
+        };
+
+        round.resolve_price = resolve_price;
+        let winner = if round.resolve_price == round.lock_price {
+            None
+        } else {
+            Some(outcome(round.resolve_price, round.lock_price)?)
+        };
+        round.winner = winner.filter(|winner| round.total(*winner) > 0);
+        let fee = match round.winner {
+            Some(winner) => checked_payout(
+                round.total(winner.opposite()),
+                ctx.accounts.pool.round_fee_bps,
+            )?,
+            None => 0,
+        };
+        round.distributable = match round.winner {
+            Some(winner) => round
+                .total(winner.opposite())
+                .checked_sub(fee)
+                .ok_or(ErrorCode::MathOverflow)?,
+            None => 0,
+        };
+        // The fee is the pool's from here on; the rest stays held until claimed.
+        let ledger = &mut ctx.accounts.ledger;
+        ledger.round_liability = ledger.round_liability.saturating_sub(fee);
+        round.status = RoundStatus::Resolved;
+        Ok(())
+    }
+
+    /// Pays out one position in a resolved round and clears the Bet PDA. The
+    /// last winning claim also releases the rounding dust left over from the
+    /// pro-rata split, which the pool keeps.
+    pub fn claim_round_bet(ctx: Context<ClaimRoundBet>) -> Result<()> {
+        require!(ctx.accounts.bet.is_open, ErrorCode::BetNotOpen);
+        require_keys_eq!(
+            ctx.accounts.bet.round,
+            ctx.accounts.round.key(),
+            ErrorCode::WrongRound
+        );
+        require_keys_eq!(
+            ctx.accounts.bet.pool,
+            ctx.accounts.pool.key(),
+            ErrorCode::WrongPool
+        );
+        require!(
+            ctx.accounts.round.status == RoundStatus::Resolved,
+            ErrorCode::RoundNotResolved
+        );
+
+        let payout = round_payout(&ctx.accounts.round, &ctx.accounts.bet)?;
+        let bet = &ctx.accounts.bet;
+        let round = &mut ctx.accounts.round;
+        let dust = if round.winner == Some(bet.direction) {
+            let share = payout
+                .checked_sub(bet.stake)
+                .ok_or(ErrorCode::MathOverflow)?;
+            round.record_win(bet.stake, share)?
+        } else {
+            0
+        };
+        let released = payout.checked_add(dust).ok_or(ErrorCode::MathOverflow)?;
+        let ledger = &mut ctx.accounts.ledger;
+        ledger.round_liability = ledger.round_liability.saturating_sub(released);
+        ctx.accounts.exposure.release(ctx.accounts.bet.stake);
+        if payout > 0 {
+            pool_signed_transfer(
+                ctx.accounts.pool_token_account.to_account_info(),
+                ctx.accounts.user_token_account.to_account_info(),
+                ctx.accounts.pool.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                payout,
+                ctx.accounts.pool.mint,
+                ctx.accounts.pool.bump,
+            )?;
+        }
+
+        ctx.accounts.bet.reset();
+
+        Ok(())
+    }
+
+    /// Creates a liquidity provider's LpPosition PDA on the base layer.
+    pub fn initialize_lp_position(ctx: Context<InitializeLpPosition>) -> Result<()> {
+        let lp_position = &mut ctx.accounts.lp_position;
+        lp_position.pool = ctx.accounts.pool.key();
+        lp_position.owner = ctx.accounts.owner.key();
+        lp_position.bump = ctx.bumps.lp_position;
+        Ok(())
+    }
+
+    /// Delegates an LpPosition PDA to the ER, where deposits and withdrawals run.
+    pub fn delegate_lp_position(ctx: Context<DelegateLpPosition>) -> Result<()> {
+        let validator = ctx.remaining_accounts.first().map(|account| account.key());
+        ctx.accounts.delegate_lp_position(
+            &ctx.accounts.payer,
+            &[
+                LP_SEED,
+                ctx.accounts.pool.key().as_ref(),
+                ctx.accounts.owner.key().as_ref(),
+            ],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Adds liquidity on the ER and credits LP shares priced at the pool's
+    /// deposit assets, so house PnL so far stays with the existing LPs and
+    /// open bets don't let a new LP buy in at a discount.
+    pub fn deposit_liquidity(ctx: Context<ManageLiquidity>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        let deposit_assets =
+            deposit_assets(ctx.accounts.pool_token_account.amount, &ctx.accounts.ledger);
+        let shares = shares_for_deposit(amount, deposit_assets, ctx.accounts.ledger.total_shares)?;
+        require!(shares > 0, ErrorCode::InvalidAmount);
+
+        signer_transfer(
+            ctx.accounts.owner_token_account.to_account_info(),
+            ctx.accounts.pool_token_account.to_account_info(),
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            amount,
+        )?;
+
+        let lp_position = &mut ctx.accounts.lp_position;
+        lp_position.shares = lp_position
+            .shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let ledger = &mut ctx.accounts.ledger;
+        ledger.total_shares = ledger
+            .total_shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        msg!("Deposited {} for {} shares", amount, shares);
+        Ok(())
+    }
+
+    /// Redeems LP shares for their slice of the pool's net assets. Net assets
+    /// already exclude the worst-case payout on open positions, so a withdrawal
+    /// can never touch liquidity backing open bets.
+    pub fn withdraw_liquidity(ctx: Context<ManageLiquidity>, shares: u64) -> Result<()> {
+        require!(shares > 0, ErrorCode::InvalidAmount);
+        require!(
+            shares <= ctx.accounts.lp_position.shares,
+            ErrorCode::InsufficientShares
+        );
+        let net_assets = net_assets(ctx.accounts.pool_token_account.amount, &ctx.accounts.ledger)?;
+        let amount = withdrawal_amount(shares, net_assets, ctx.accounts.ledger.total_shares)?;
+
+        ctx.accounts.lp_position.shares -= shares;
+        ctx.accounts.ledger.total_shares -= shares;
+
+        if amount > 0 {
+            pool_signed_transfer(
+                ctx.accounts.pool_token_account.to_account_info(),
+                ctx.accounts.owner_token_account.to_account_info(),
+                ctx.accounts.pool.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                amount,
+                ctx.accounts.pool.mint,
+                ctx.accounts.pool.bump,
+            )?;
+        }
+        msg!("Withdrew {} for {} shares", amount, shares);
+        Ok(())
+    }
+
+    /// Commits an LpPosition back to the base layer and undelegates it, so its
+    /// shares can be minted as share tokens with `mint_shares`.
+    pub fn undelegate_lp_position(ctx: Context<UndelegateLpPosition>) -> Result<()> {
+        MagicIntentBundleBuilder::new(
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.magic_context.to_account_info(),
+            ctx.accounts.magic_program.to_account_info(),
+        )
+        .commit_and_undelegate(&[ctx.accounts.lp_position.to_account_info()])
+        .build_and_invoke()?;
+        Ok(())
+    }
+
+    /// Mints LP shares out of an undelegated LpPosition as SPL share tokens,
+    /// which can be held and transferred like any other token. The shares stay
+    /// in `PoolLedger::total_shares`; they only move from the position to the
+    /// owner's share token account.
+    pub fn mint_shares(ctx: Context<ConvertShares>, shares: u64) -> Result<()> {
+        require!(shares > 0, ErrorCode::InvalidAmount);
+        require!(
+            shares <= ctx.accounts.lp_position.shares,
+            ErrorCode::InsufficientShares
+        );
+        ctx.accounts.lp_position.shares -= shares;
+
+        pool_signed_mint_to(
+            ctx.accounts.share_mint.to_account_info(),
+            ctx.accounts.owner_share_account.to_account_info(),
+            ctx.accounts.pool.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            shares,
+            ctx.accounts.pool.mint,
+            ctx.accounts.pool.bump,
+        )?;
+        msg!("Minted {} share tokens", shares);
+        Ok(())
+    }
+
+    /// Burns share tokens back into the owner's LpPosition. Once the position
+    /// is delegated again, `withdraw_liquidity` can redeem them.
+    pub fn redeem_shares(ctx: Context<ConvertShares>, shares: u64) -> Result<()> {
+        require!(shares > 0, ErrorCode::InvalidAmount);
+        signer_burn(
+            ctx.accounts.share_mint.to_account_info(),
+            ctx.accounts.owner_share_account.to_account_info(),
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            shares,
+        )?;
+
+        let lp_position = &mut ctx.accounts.lp_position;
+        lp_position.shares = lp_position
+            .shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        msg!("Redeemed {} share tokens", shares);
+        Ok(())
+    }
+
+    /// Creates a program-owned price feed for tests and local runs. A pool
+    /// reads it when initialized with `OracleKind::Manual` and this account as
+    /// its `price_feed`.
+    pub fn create_manual_oracle(
+        ctx: Context<CreateManualOracle>,
+        oracle_id: u64,
+        price: i64,
+        conf: u64,
+    ) -> Result<()> {
+        let manual_oracle = &mut ctx.accounts.manual_oracle;
+        manual_oracle.authority = ctx.accounts.authority.key();
+        manual_oracle.oracle_id = oracle_id;
+        manual_oracle.price = price;
+        manual_oracle.conf = conf;
+        manual_oracle.publish_time = Clock::get()?.unix_timestamp;
+        manual_oracle.bump = ctx.bumps.manual_oracle;
+        Ok(())
+    }
+
+    /// Delegates a ManualOracle to the ER so prices can be published next to
+    /// the bets that read them.
+    pub fn delegate_manual_oracle(
+        ctx: Context<DelegateManualOracle>,
+        oracle_id: u64,
+    ) -> Result<()> {
+        let validator = ctx.remaining_accounts.first().map(|account| account.key());
+        ctx.accounts.delegate_manual_oracle(
+            &ctx.accounts.payer,
+            &[
+                MANUAL_ORACLE_SEED,
+                ctx.accounts.authority.key().as_ref(),
+                &oracle_id.to_le_bytes(),
+            ],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Publishes a new manual price, stamped with the current clock.
+    pub fn set_manual_price(ctx: Context<SetManualPrice>, price: i64, conf: u64) -> Result<()> {
+        let manual_oracle = &mut ctx.accounts.manual_oracle;
+        manual_oracle.price = price;
+        manual_oracle.conf = conf;
+        manual_oracle.publish_time = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+}
+
+/// Accounts for pool creation and one-time liquidity delegation.
+/// The Pool PDA owns the pool token account and its EATA is delegated to the ER.
+#[derive(Accounts)]
+#[instruction(
+    price_feed: Pubkey,
+    price_feed_id: [u8; 32],
+    seed_amount: u64,
+    bet_duration_seconds: i64,
+    min_stake: u64,
+    payout_bps: u64,
+    round_fee_bps: u64,
+    limits: RiskLimits,
+    oracle: OracleConfig
+)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Pool::LEN,
+        seeds = [POOL_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PoolLedger::LEN,
+        seeds = [LEDGER_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub ledger: Account<'info, PoolLedger>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + LpPosition::LEN,
+        seeds = [LP_SEED, pool.key().as_ref(), admin.key().as_ref()],
+        bump
+    )]
+    pub admin_lp_position: Account<'info, LpPosition>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [SHARE_MINT_SEED, pool.key().as_ref()],
+        bump,
+        mint::decimals = mint.decimals,
+        mint::authority = pool
+    )]
+    pub share_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = pool
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = admin_token_account.owner == admin.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = admin_token_account.mint == mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub admin_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_ephemeral_ata.key() == ephemeral_ata_pda(&pool.key(), &mint.key()) @ ErrorCode::InvalidEphemeralAta
+    )]
+    /// CHECK: created and delegated by the Ephemeral SPL Token program.
+    pub pool_ephemeral_ata: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = vault.key() == vault_pda(&mint.key()) @ ErrorCode::InvalidVault
+    )]
+    /// CHECK: created by the Ephemeral SPL Token program.
+    pub vault: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = vault_ephemeral_ata.key() == ephemeral_ata_pda(&vault.key(), &mint.key()) @ ErrorCode::InvalidEphemeralAta
+    )]
+    /// CHECK: created and delegated by the Ephemeral SPL Token program.
+    pub vault_ephemeral_ata: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = vault_token_account.key() == associated_token_pda(&vault.key(), &mint.key()) @ ErrorCode::InvalidVaultAta
+    )]
+    /// CHECK: token account created idempotently for the e-token vault.
+    pub vault_token_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = pool_eata_buffer.key() == eata_buffer_address(&pool_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
+    )]
+    /// CHECK: delegation buffer for the pool EATA.
+    pub pool_eata_buffer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = pool_eata_record.key() == record_pda(&pool_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
+    )]
+    /// CHECK: delegation record for the pool EATA.
+    pub pool_eata_record: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = pool_eata_metadata.key() == metadata_pda(&pool_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
+    )]
+    /// CHECK: delegation metadata for the pool EATA.
+    pub pool_eata_metadata: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = vault_eata_buffer.key() == eata_buffer_address(&vault_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
+    )]
+    /// CHECK: delegation buffer for the vault EATA.
+    pub vault_eata_buffer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = vault_eata_record.key() == record_pda(&vault_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
+    )]
+    /// CHECK: delegation record for the vault EATA.
+    pub vault_eata_record: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = vault_eata_metadata.key() == metadata_pda(&vault_ephemeral_ata.key()) @ ErrorCode::InvalidDelegationPda
+    )]
+    /// CHECK: delegation metadata for the vault EATA.
+    pub vault_eata_metadata: UncheckedAccount<'info>,
+    #[account(address = EPHEMERAL_SPL_TOKEN_PROGRAM_ID)]
+    /// CHECK: fixed Ephemeral SPL Token program id.
+    pub ephemeral_token_program: UncheckedAccount<'info>,
+    #[account(address = DELEGATION_PROGRAM_ID)]
+    /// CHECK: fixed Delegation program id.
+    pub delegation_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(bet_id: u64)]
+pub struct InitializeBet<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: user authority for the bet PDA.
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Bet::LEN,
+        seeds = [BET_SEED, user.key().as_ref(), &bet_id.to_le_bytes()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for delegating a Bet PDA to the ER.
+#[delegate]
+#[derive(Accounts)]
+#[instruction(bet_id: u64)]
+pub struct DelegateBet<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub user: Signer<'info>,
+    #[account(mut, del, seeds = [BET_SEED, user.key().as_ref(), &bet_id.to_le_bytes()], bump)]
+    /// CHECK: deserialized by delegated instructions after delegation.
+    pub bet: UncheckedAccount<'info>,
+}
+
+/// Accounts for returning a delegated Bet PDA to the base layer.
+#[commit]
+#[derive(Accounts)]
+#[instruction(bet_id: u64)]
+pub struct UndelegateBet<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub user: Signer<'info>,
+    #[account(mut, seeds = [BET_SEED, user.key().as_ref(), &bet_id.to_le_bytes()], bump)]
+    pub bet: Account<'info, Bet>,
+}
+
+/// Accounts for opening a prediction on the ER.
+/// A session payer must be approved as delegate for the user's token account.
+#[derive(Accounts, Session)]
+pub struct PlaceBet<'info> {
+    pub payer: Signer<'info>,
+    /// CHECK: user authority for the bet and session token.
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, seeds = [LEDGER_SEED, pool.key().as_ref()], bump = ledger.bump)]
+    pub ledger: Account<'info, PoolLedger>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = exposure.bump
+    )]
+    pub exposure: Account<'info, Exposure>,
+    #[account(
+        mut,
+        seeds = [BET_SEED, user.key().as_ref(), &bet.bet_id.to_le_bytes()],
+        bump = bet.bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = user_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == associated_token_pda(&pool.key(), &pool.mint) @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.owner == pool.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+    /// CHECK: pool oracle account read through Pool.oracle; key checked against Pool.price_feed.
+    pub price_update: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    #[session(signer = payer, authority = user.key())]
+    pub session_token: Option<Account<'info, SessionTokenV2>>,
+}
+
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: user authority for the bet.
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, seeds = [LEDGER_SEED, pool.key().as_ref()], bump = ledger.bump)]
+    pub ledger: Account<'info, PoolLedger>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = exposure.bump
+    )]
+    pub exposure: Account<'info, Exposure>,
+    #[account(
+        mut,
+        seeds = [BET_SEED, user.key().as_ref(), &bet.bet_id.to_le_bytes()],
+        bump = bet.bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = user_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == associated_token_pda(&pool.key(), &pool.mint) @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.owner == pool.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+    /// CHECK: pool oracle account read through Pool.oracle; key checked against Pool.price_feed.
+    pub price_update: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for settling all of a user's expired bets at once.
+/// The Bet PDAs are passed as writable remaining accounts.
+#[derive(Accounts)]
+pub struct SettleExpired<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: user authority for the bets.
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, seeds = [LEDGER_SEED, pool.key().as_ref()], bump = ledger.bump)]
+    pub ledger: Account<'info, PoolLedger>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = exposure.bump
+    )]
+    pub exposure: Account<'info, Exposure>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = user_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == associated_token_pda(&pool.key(), &pool.mint) @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.owner == pool.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+    /// CHECK: pool oracle account read through Pool.oracle; key checked against Pool.price_feed.
+    pub price_update: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for creating a parimutuel round on the base layer.
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct OpenRound<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Round::LEN,
+        seeds = [ROUND_SEED, pool.key().as_ref(), &round_id.to_le_bytes()],
+        bump
+    )]
+    pub round: Account<'info, Round>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for delegating a Round PDA to the ER.
+#[delegate]
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct DelegateRound<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, del, seeds = [ROUND_SEED, pool.key().as_ref(), &round_id.to_le_bytes()], bump)]
+    /// CHECK: deserialized by delegated instructions after delegation.
+    pub round: UncheckedAccount<'info>,
+}
+
+/// Accounts for staking in a parimutuel round on the ER.
+#[derive(Accounts, Session)]
+pub struct PlaceRoundBet<'info> {
+    pub payer: Signer<'info>,
+    /// CHECK: user authority for the bet and session token.
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, seeds = [LEDGER_SEED, pool.key().as_ref()], bump = ledger.bump)]
+    pub ledger: Account<'info, PoolLedger>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = exposure.bump
+    )]
+    pub exposure: Account<'info, Exposure>,
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, pool.key().as_ref(), &round.round_id.to_le_bytes()],
+        bump = round.bump
+    )]
+    pub round: Account<'info, Round>,
+    #[account(
+        mut,
+        seeds = [BET_SEED, user.key().as_ref(), &bet.bet_id.to_le_bytes()],
+        bump = bet.bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = user_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == associated_token_pda(&pool.key(), &pool.mint) @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.owner == pool.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    #[session(signer = payer, authority = user.key())]
+    pub session_token: Option<Account<'info, SessionTokenV2>>,
+}
+
+/// Accounts for the permissionless `lock_round` / `resolve_round` cranks.
+/// Only `resolve_round` writes the ledger, releasing the pool's fee.
+#[derive(Accounts)]
+pub struct CrankRound<'info> {
+    pub payer: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, seeds = [LEDGER_SEED, pool.key().as_ref()], bump = ledger.bump)]
+    pub ledger: Account<'info, PoolLedger>,
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, pool.key().as_ref(), &round.round_id.to_le_bytes()],
+        bump = round.bump
+    )]
+    pub round: Account<'info, Round>,
+    /// CHECK: pool oracle account read through Pool.oracle; key checked against Pool.price_feed.
+    pub price_update: UncheckedAccount<'info>,
+}
+
+/// Accounts for paying out one position in a resolved round.
+#[derive(Accounts)]
+pub struct ClaimRoundBet<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: user authority for the bet.
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, seeds = [LEDGER_SEED, pool.key().as_ref()], bump = ledger.bump)]
+    pub ledger: Account<'info, PoolLedger>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump = exposure.bump
+    )]
+    pub exposure: Account<'info, Exposure>,
+    #[account(
+        mut,
+        seeds = [ROUND_SEED, pool.key().as_ref(), &round.round_id.to_le_bytes()],
+        bump = round.bump
+    )]
+    pub round: Account<'info, Round>,
+    #[account(
+        mut,
+        seeds = [BET_SEED, user.key().as_ref(), &bet.bet_id.to_le_bytes()],
+        bump = bet.bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = user_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == associated_token_pda(&pool.key(), &pool.mint) @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.owner == pool.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for delegating the pool's liability ledger to the ER.
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateLedger<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, del, seeds = [LEDGER_SEED, pool.key().as_ref()], bump)]
+    /// CHECK: deserialized by delegated instructions after delegation.
+    pub ledger: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeExposure<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: user whose open stake is tracked.
+    pub user: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Exposure::LEN,
+        seeds = [EXPOSURE_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub exposure: Account<'info, Exposure>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for delegating a user's Exposure PDA to the ER.
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateExposure<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub user: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        mut,
+        del,
+        seeds = [EXPOSURE_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    /// CHECK: deserialized by delegated instructions after delegation.
+    pub exposure: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLpPosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: liquidity provider that owns the position.
+    pub owner: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + LpPosition::LEN,
+        seeds = [LP_SEED, pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for delegating an LpPosition PDA to the ER.
+#[delegate]
+#[derive(Accounts)]
+pub struct DelegateLpPosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub owner: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        mut,
+        del,
+        seeds = [LP_SEED, pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    /// CHECK: deserialized by delegated instructions after delegation.
+    pub lp_position: UncheckedAccount<'info>,
+}
+
+/// Accounts for `deposit_liquidity` / `withdraw_liquidity` on the ER.
+#[derive(Accounts)]
+pub struct ManageLiquidity<'info> {
+    pub owner: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut, seeds = [LEDGER_SEED, pool.key().as_ref()], bump = ledger.bump)]
+    pub ledger: Account<'info, PoolLedger>,
+    #[account(
+        mut,
+        seeds = [LP_SEED, pool.key().as_ref(), owner.key().as_ref()],
+        bump = lp_position.bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = owner_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == associated_token_pda(&pool.key(), &pool.mint) @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.owner == pool.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = pool_token_account.mint == pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for returning a delegated LpPosition PDA to the base layer.
+#[commit]
+#[derive(Accounts)]
+pub struct UndelegateLpPosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub owner: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        mut,
+        seeds = [LP_SEED, pool.key().as_ref(), owner.key().as_ref()],
+        bump = lp_position.bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+}
+
+/// Accounts for `mint_shares` / `redeem_shares` on the base layer. The
+/// LpPosition has to be undelegated first.
+#[derive(Accounts)]
+pub struct ConvertShares<'info> {
+    pub owner: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(seeds = [POOL_SEED, mint.key().as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        mut,
+        seeds = [LP_SEED, pool.key().as_ref(), owner.key().as_ref()],
+        bump = lp_position.bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+    #[account(mut, seeds = [SHARE_MINT_SEED, pool.key().as_ref()], bump)]
+    pub share_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        constraint = owner_share_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = owner_share_account.mint == share_mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub owner_share_account: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
 }
+
+#[derive(Accounts)]
+#[instruction(oracle_id: u64)]
+pub struct CreateManualOracle<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ManualOracle::LEN,
+        seeds = [MANUAL_ORACLE_SEED, authority.key().as_ref(), &oracle_id.to_le_bytes()],
+        bump
+    )]
+    pub manual_oracle: Account<'info, ManualOracle>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for delegating a ManualOracle to the ER.
+#[delegate]
+#[derive(Accounts)]
+#[instruction(oracle_id: u64)]
+pub struct DelegateManualOracle<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        del,
+        seeds = [MANUAL_ORACLE_SEED, authority.key().as_ref(), &oracle_id.to_le_bytes()],
+        bump
+    )]
+    /// CHECK: deserialized by delegated instructions after delegation.
+    pub manual_oracle: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetManualPrice<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [
+            MANUAL_ORACLE_SEED,
+            authority.key().as_ref(),
+            &manual_oracle.oracle_id.to_le_bytes()
+        ],
+        bump = manual_oracle.bump
+    )]
+    pub manual_oracle: Account<'info, ManualOracle>,
+}
@@ -0,0 +1,136 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::error::ErrorCode;
+use crate::state::{ManualOracle, OracleConfig, OracleKind, Pool};
+use crate::utils::{past_price_window, price_near};
+use crate::BASIS_POINTS_DENOMINATOR;
+
+/// Anchor discriminator of Switchboard on-demand's `PullFeedAccountData`.
+const SWITCHBOARD_PULL_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+/// Byte offsets into a Switchboard pull feed account, discriminator included,
+/// following the `#[repr(C)]` layout of `PullFeedAccountData`.
+const SWITCHBOARD_LAST_UPDATE_OFFSET: usize = 2216;
+const SWITCHBOARD_VALUE_OFFSET: usize = 2264;
+const SWITCHBOARD_STD_DEV_OFFSET: usize = 2280;
+/// Switchboard results carry 18 decimals; prices are scaled down to 8 to fit an i64.
+const SWITCHBOARD_SCALE_DOWN: i128 = 10_000_000_000;
+
+/// One oracle reading, before the pool's freshness and confidence checks.
+struct OraclePrice {
+    price: i64,
+    conf: u64,
+    publish_time: i64,
+}
+
+/// Reads the pool's price feed through the adapter selected by `Pool.oracle`.
+/// Prices older than `max_age_seconds` or with a confidence interval wider
+/// than `max_confidence_bps` are rejected.
+pub(crate) fn read_price(price_account: &UncheckedAccount, pool: &Pool) -> Result<i64> {
+    Ok(read_oracle(price_account, pool)?.price)
+}
+
+/// Reads the price at `ts` for a round crank: one published within
+/// `ROUND_PRICE_TOLERANCE_SECONDS` of `ts`. Returns `None` once no such price
+/// can be read any more, because the feed has published past the window or
+/// the window has aged out, so the round refunds.
+pub(crate) fn read_price_at(
+    price_account: &UncheckedAccount,
+    pool: &Pool,
+    ts: i64,
+) -> Result<Option<i64>> {
+    let now = Clock::get()?.unix_timestamp;
+    if past_price_window(ts, now, pool.oracle.max_age_seconds) {
+        return Ok(None);
+    }
+    let price = read_oracle(price_account, pool)?;
+    Ok(price_near(ts, price.publish_time)?.then_some(price.price))
+}
+
+fn read_oracle(price_account: &UncheckedAccount, pool: &Pool) -> Result<OraclePrice> {
+    let price_info = price_account.to_account_info();
+    let data_ref = price_info.data.borrow();
+    let clock = Clock::get()?;
+    let oracle = pool.oracle;
+    let price = match oracle.kind {
+        OracleKind::Pyth => read_pyth(
+            &data_ref,
+            &pool.price_feed_id,
+            &clock,
+            oracle.max_age_seconds,
+        )?,
+        OracleKind::SwitchboardOnDemand => read_switchboard(&data_ref)?,
+        OracleKind::Manual => {
+            require_keys_eq!(*price_info.owner, crate::ID, ErrorCode::InvalidPriceFeed);
+            read_manual(&data_ref)?
+        }
+    };
+    check_price(&price, &oracle, clock.unix_timestamp)?;
+    Ok(price)
+}
+
+/// Pyth receiver `PriceUpdateV2`, as posted by the ephemeral oracle on the ER.
+/// The SDK also checks the feed id and the update's verification level.
+fn read_pyth(
+    mut data: &[u8],
+    feed_id: &[u8; 32],
+    clock: &Clock,
+    max_age_seconds: u64,
+) -> Result<OraclePrice> {
+    let price_update =
+        PriceUpdateV2::try_deserialize_unchecked(&mut data).map_err(Into::<Error>::into)?;
+    let price = price_update.get_price_no_older_than(clock, max_age_seconds, feed_id)?;
+    Ok(OraclePrice {
+        price: price.price,
+        conf: price.conf,
+        publish_time: price.publish_time,
+    })
+}
+
+/// Switchboard on-demand pull feed. Only the latest result and its update time
+/// are read; the standard deviation across oracle samples stands in for Pyth's
+/// confidence interval.
+fn read_switchboard(data: &[u8]) -> Result<OraclePrice> {
+    require!(
+        data.len() >= SWITCHBOARD_STD_DEV_OFFSET + 16
+            && data[..8] == SWITCHBOARD_PULL_FEED_DISCRIMINATOR,
+        ErrorCode::InvalidPriceFeed
+    );
+    let value = i128::from_le_bytes(le_bytes(data, SWITCHBOARD_VALUE_OFFSET));
+    let std_dev = i128::from_le_bytes(le_bytes(data, SWITCHBOARD_STD_DEV_OFFSET));
+    Ok(OraclePrice {
+        price: i64::try_from(value / SWITCHBOARD_SCALE_DOWN)
+            .map_err(|_| ErrorCode::MathOverflow)?,
+        conf: u64::try_from(std_dev.unsigned_abs() / SWITCHBOARD_SCALE_DOWN as u128)
+            .map_err(|_| ErrorCode::MathOverflow)?,
+        publish_time: i64::from_le_bytes(le_bytes(data, SWITCHBOARD_LAST_UPDATE_OFFSET)),
+    })
+}
+
+/// Program-owned `ManualOracle`, published by its authority with `set_manual_price`.
+fn read_manual(mut data: &[u8]) -> Result<OraclePrice> {
+    let manual_oracle = ManualOracle::try_deserialize(&mut data)?;
+    Ok(OraclePrice {
+        price: manual_oracle.price,
+        conf: manual_oracle.conf,
+        publish_time: manual_oracle.publish_time,
+    })
+}
+
+/// Applies the pool's staleness and confidence thresholds to any oracle reading.
+fn check_price(price: &OraclePrice, config: &OracleConfig, now: i64) -> Result<()> {
+    let age = now.saturating_sub(price.publish_time).max(0) as u64;
+    require!(age <= config.max_age_seconds, ErrorCode::StalePrice);
+    if config.max_confidence_bps > 0 {
+        let conf = (price.conf as u128) * BASIS_POINTS_DENOMINATOR as u128;
+        let limit = (price.price.unsigned_abs() as u128) * config.max_confidence_bps as u128;
+        require!(conf <= limit, ErrorCode::PriceTooUncertain);
+    }
+    Ok(())
+}
+
+fn le_bytes<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    bytes.copy_from_slice(&data[offset..offset + N]);
+    bytes
+}
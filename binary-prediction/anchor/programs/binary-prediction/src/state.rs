@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::error::ErrorCode;
+
 /// Pool configuration for this prediction market.
 /// The Pool PDA owns pool token custody and signs payout transfers.
 #[account]
@@ -9,7 +11,8 @@ pub struct Pool {
     pub authority: Pubkey,
     /// Oracle account accepted by betting and settlement instructions.
     pub price_feed: Pubkey,
-    /// Pyth feed id validated inside the oracle price update account.
+    /// Pyth feed id validated inside the oracle price update account. Unused by
+    /// the other oracle kinds, which are pinned by `price_feed` alone.
     pub price_feed_id: [u8; 32],
     /// How long a bet must stay open before it can be settled.
     pub bet_duration_seconds: i64,
@@ -17,26 +20,302 @@ pub struct Pool {
     /// Payout multiplier in basis points.
     pub payout_bps: u64,
     pub bump: u8,
+    /// Share of a parimutuel round's losing side kept by the pool, in basis points.
+    pub round_fee_bps: u64,
+    pub limits: RiskLimits,
+    pub oracle: OracleConfig,
 }
 
 impl Pool {
-    pub const LEN: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1;
+    pub const LEN: usize =
+        32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 8 + RiskLimits::LEN + OracleConfig::LEN;
+}
+
+/// Which oracle adapter a pool reads prices through, and how fresh and tight
+/// those prices must be.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct OracleConfig {
+    pub kind: OracleKind,
+    /// Oldest price, in seconds, that betting and settlement accept.
+    pub max_age_seconds: u64,
+    /// Widest confidence interval accepted, in basis points of the price.
+    /// Zero disables the check.
+    pub max_confidence_bps: u64,
+}
+
+impl OracleConfig {
+    pub const LEN: usize = 1 + 8 + 8;
+}
+
+/// Price feed formats the program can read.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum OracleKind {
+    /// Pyth receiver `PriceUpdateV2`, including MagicBlock's ephemeral oracle.
+    Pyth,
+    /// Switchboard on-demand pull feed.
+    SwitchboardOnDemand,
+    /// Program-owned `ManualOracle`, for tests and local validators.
+    Manual,
+}
+
+/// Price feed published directly by its authority. Meant for tests and local
+/// runs where no real oracle is available; never point a live pool at one.
+#[account]
+pub struct ManualOracle {
+    pub authority: Pubkey,
+    pub oracle_id: u64,
+    pub price: i64,
+    pub conf: u64,
+    pub publish_time: i64,
+    pub bump: u8,
+}
+
+impl ManualOracle {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 1;
+}
+
+/// Optional caps on open stake, set at pool creation. Zero disables a cap.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct RiskLimits {
+    /// Most open stake one user can hold across all of their bets.
+    pub max_user_stake: u64,
+    /// Most open fixed-payout stake the pool takes on each direction.
+    pub max_direction_stake: u64,
+}
+
+impl RiskLimits {
+    pub const LEN: usize = 8 + 8;
+}
+
+/// Outstanding liability for a pool's open positions.
+/// The Pool is only read on the ER, so these running totals live in their own
+/// PDA, delegated next to the pool's token custody and updated by every bet and
+/// settlement.
+#[account]
+pub struct PoolLedger {
+    pub pool: Pubkey,
+    /// Open fixed-payout stake per direction.
+    pub up_stake: u64,
+    pub down_stake: u64,
+    /// Payouts owed if every open fixed-payout bet on that direction wins.
+    pub up_liability: u64,
+    pub down_liability: u64,
+    /// Parimutuel round stake (net of resolved fees) not yet claimed.
+    pub round_liability: u64,
+    pub bump: u8,
+    /// LP shares outstanding, whether held in an `LpPosition` or minted out as
+    /// share tokens.
+    pub total_shares: u64,
+}
+
+impl PoolLedger {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + 1 + 8;
+
+    /// Most the pool can owe at once: every open bet on both sides winning,
+    /// plus everything still held for rounds. Bets opened at different prices
+    /// can win on both sides at once, so the two directions are summed.
+    pub fn worst_case_liability(&self) -> Result<u64> {
+        self.up_liability
+            .checked_add(self.down_liability)
+            .and_then(|liability| liability.checked_add(self.round_liability))
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    /// Books a new fixed-payout bet, enforcing `max_direction_stake`.
+    pub fn open_bet(
+        &mut self,
+        direction: Direction,
+        stake: u64,
+        liability: u64,
+        max_direction_stake: u64,
+    ) -> Result<()> {
+        let (side_stake, side_liability) = self.side_mut(direction);
+        *side_stake = side_stake
+            .checked_add(stake)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            max_direction_stake == 0 || *side_stake <= max_direction_stake,
+            ErrorCode::DirectionStakeLimit
+        );
+        *side_liability = side_liability
+            .checked_add(liability)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Releases a settled fixed-payout bet.
+    pub fn close_bet(&mut self, direction: Direction, stake: u64, liability: u64) {
+        let (side_stake, side_liability) = self.side_mut(direction);
+        *side_stake = side_stake.saturating_sub(stake);
+        *side_liability = side_liability.saturating_sub(liability);
+    }
+
+    fn side_mut(&mut self, direction: Direction) -> (&mut u64, &mut u64) {
+        match direction {
+            Direction::Up => (&mut self.up_stake, &mut self.up_liability),
+            Direction::Down => (&mut self.down_stake, &mut self.down_liability),
+        }
+    }
+}
+
+/// A liquidity provider's claim on the pool, in shares of `PoolLedger::total_shares`.
+/// Delegated to the ER, where the pool's token custody lives; undelegated,
+/// its shares can be minted out as share tokens.
+#[account]
+pub struct LpPosition {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub bump: u8,
+}
+
+impl LpPosition {
+    pub const LEN: usize = 32 + 32 + 8 + 1;
 }
 
-/// Per-user prediction state.
-/// This account is delegated to the ER while the user is playing. It holds one
-/// open bet at a time and is reset after settlement.
+/// A user's open stake across all of their Bet PDAs, checked against
+/// `RiskLimits::max_user_stake`. Delegated to the ER alongside the bets.
+#[account]
+pub struct Exposure {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub open_stake: u64,
+    pub bump: u8,
+}
+
+impl Exposure {
+    pub const LEN: usize = 32 + 32 + 8 + 1;
+
+    pub fn add(&mut self, stake: u64, max_user_stake: u64) -> Result<()> {
+        self.open_stake = self
+            .open_stake
+            .checked_add(stake)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            max_user_stake == 0 || self.open_stake <= max_user_stake,
+            ErrorCode::UserStakeLimit
+        );
+        Ok(())
+    }
+
+    pub fn release(&mut self, stake: u64) {
+        self.open_stake = self.open_stake.saturating_sub(stake);
+    }
+}
+
+/// One prediction position, keyed by a user-chosen `bet_id`.
+/// Each Bet PDA is delegated to the ER on its own, so a user can hold as many
+/// overlapping positions as they have bet ids. A position holds one open bet at
+/// a time and is reset after settlement.
 #[account]
 pub struct Bet {
+    pub bet_id: u64,
     pub open_price: i64,
     pub expiry_ts: i64,
     pub direction: Direction,
     pub stake: u64,
     pub is_open: bool,
+    pub bump: u8,
+    /// Parimutuel round this position is in; the default key for a
+    /// fixed-payout bet.
+    pub round: Pubkey,
+    /// Pool the open position was placed against. Settlement and claims
+    /// only pay out from this pool.
+    pub pool: Pubkey,
 }
 
 impl Bet {
-    pub const LEN: usize = 8 + 8 + 1 + 8 + 1;
+    pub const LEN: usize = 8 + 8 + 8 + 1 + 8 + 1 + 1 + 32 + 32;
+
+    /// Clears a settled position so its PDA can be reused.
+    pub fn reset(&mut self) {
+        self.is_open = false;
+        self.stake = 0;
+        self.open_price = 0;
+        self.expiry_ts = 0;
+        self.round = Pubkey::default();
+        self.pool = Pubkey::default();
+    }
+
+    pub fn is_round_bet(&self) -> bool {
+        self.round != Pubkey::default()
+    }
+}
+
+/// One parimutuel round: bets are taken until `lock_ts`, the price is
+/// snapshotted at lock and again at `resolve_ts`, and winners split the losing
+/// side pro rata minus the pool's `round_fee_bps`. The pool takes no directional
+/// risk. Created on the base layer and delegated to the ER.
+#[account]
+pub struct Round {
+    pub pool: Pubkey,
+    pub round_id: u64,
+    pub open_ts: i64,
+    pub lock_ts: i64,
+    pub resolve_ts: i64,
+    pub lock_price: i64,
+    pub resolve_price: i64,
+    pub up_total: u64,
+    pub down_total: u64,
+    pub status: RoundStatus,
+    /// Winning side once resolved; `None` refunds every stake (a flat price, or
+    /// nobody on the winning side).
+    pub winner: Option<Direction>,
+    /// Losing-side stake shared among the winners, after the pool fee.
+    pub distributable: u64,
+    pub bump: u8,
+    /// Winning-side stake claimed so far.
+    pub claimed_stake: u64,
+    /// Part of `distributable` paid out so far.
+    pub claimed_share: u64,
+}
+
+impl Round {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + (1 + 1) + 8 + 1 + 8 + 8;
+
+    /// Total staked on `direction`.
+    pub fn total(&self, direction: Direction) -> u64 {
+        match direction {
+            Direction::Up => self.up_total,
+            Direction::Down => self.down_total,
+        }
+    }
+
+    /// Books a winning claim of `stake` that was paid `share` of
+    /// `distributable`. Once every winning stake has claimed, returns the
+    /// rounding dust nobody was paid, so the caller can release it.
+    pub fn record_win(&mut self, stake: u64, share: u64) -> Result<u64> {
+        let Some(winner) = self.winner else {
+            return Ok(0);
+        };
+        self.claimed_stake = self
+            .claimed_stake
+            .checked_add(stake)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.claimed_share = self
+            .claimed_share
+            .checked_add(share)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if self.claimed_stake < self.total(winner) {
+            return Ok(0);
+        }
+        Ok(self.distributable.saturating_sub(self.claimed_share))
+    }
+
+    /// Resolves the round with no winner, so every position claims its stake back.
+    pub fn refund_all(&mut self) {
+        self.winner = None;
+        self.distributable = 0;
+        self.status = RoundStatus::Resolved;
+    }
+}
+
+/// Lifecycle of a parimutuel round.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RoundStatus {
+    Open,
+    Locked,
+    Resolved,
 }
 
 /// Direction the user predicts the price will move.
@@ -45,3 +324,12 @@ pub enum Direction {
     Up,
     Down,
 }
+
+impl Direction {
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+}
@@ -5,31 +5,15 @@ use anchor_lang::solana_program::{
     program_option::COption,
 };
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer as SplTransfer};
-use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount, Transfer as SplTransfer};
 
 use crate::error::ErrorCode;
-use crate::state::Direction;
+use crate::state::{Bet, Direction, PoolLedger, Round};
 use crate::{
     ASSOCIATED_TOKEN_PROGRAM_ID, BASIS_POINTS_DENOMINATOR, DELEGATION_PROGRAM_ID,
-    EPHEMERAL_SPL_TOKEN_PROGRAM_ID, MAX_PRICE_AGE_SECONDS, POOL_SEED,
+    EPHEMERAL_SPL_TOKEN_PROGRAM_ID, POOL_SEED, ROUND_PRICE_TOLERANCE_SECONDS,
 };
 
-/// Reads a fresh price from the Pyth receiver account.
-/// This example rejects stale prices with a fixed max age.
-pub(crate) fn read_price(
-    price_update_account: &UncheckedAccount,
-    feed_id: &[u8; 32],
-) -> Result<i64> {
-    let price_update_info = price_update_account.to_account_info();
-    let data_ref = price_update_info.data.borrow();
-    let price_update = PriceUpdateV2::try_deserialize_unchecked(&mut data_ref.as_ref())
-        .map_err(Into::<Error>::into)?;
-    let price =
-        price_update.get_price_no_older_than(&Clock::get()?, MAX_PRICE_AGE_SECONDS, feed_id)?;
-    Ok(price.price)
-}
-
 /// Confirms an SPL token account delegated enough allowance to the Pool PDA.
 pub(crate) fn require_token_delegate(
     token_account: &Account<TokenAccount>,
@@ -61,6 +45,97 @@ pub(crate) fn checked_payout(stake: u64, payout_bps: u64) -> Result<u64> {
         .ok_or(ErrorCode::MathOverflow.into())
 }
 
+/// Amount owed on an expired bet at `settle_price`: the configured payout for a
+/// correct call, the stake back on a tie, nothing on a loss.
+pub(crate) fn bet_payout(bet: &Bet, settle_price: i64, payout_bps: u64) -> Result<u64> {
+    if settle_price == bet.open_price {
+        Ok(bet.stake)
+    } else if bet.direction == outcome(settle_price, bet.open_price)? {
+        checked_payout(bet.stake, payout_bps)
+    } else {
+        Ok(0)
+    }
+}
+
+/// Amount owed on a position in a resolved parimutuel round: its stake back plus
+/// a pro-rata share of the distributable losing side if it picked the winner,
+/// nothing if it lost, and the stake back if the round refunds.
+pub(crate) fn round_payout(round: &Round, bet: &Bet) -> Result<u64> {
+    let Some(winner) = round.winner else {
+        return Ok(bet.stake);
+    };
+    if bet.direction != winner {
+        return Ok(0);
+    }
+    let share = (bet.stake as u128)
+        .checked_mul(round.distributable as u128)
+        .and_then(|value| value.checked_div(round.total(winner) as u128))
+        .ok_or(ErrorCode::MathOverflow)?;
+    bet.stake
+        .checked_add(u64::try_from(share).map_err(|_| ErrorCode::MathOverflow)?)
+        .ok_or(ErrorCode::MathOverflow.into())
+}
+
+/// Pool value that belongs to LPs: the pool balance minus the worst-case
+/// payout on every open position. House PnL lands here as bets settle.
+pub(crate) fn net_assets(pool_balance: u64, ledger: &PoolLedger) -> Result<u64> {
+    Ok(pool_balance.saturating_sub(ledger.worst_case_liability()?))
+}
+
+/// Pool value deposits are priced at: the pool balance minus round stake held
+/// for bettors. Unlike `net_assets`, open fixed-payout bets aren't marked at
+/// their worst case, which would sell new shares below what they're worth.
+pub(crate) fn deposit_assets(pool_balance: u64, ledger: &PoolLedger) -> u64 {
+    pool_balance.saturating_sub(ledger.round_liability)
+}
+
+/// Shares minted for depositing `amount` into a pool worth `pool_assets`.
+/// The first deposit into an empty pool mints one share per token.
+pub(crate) fn shares_for_deposit(amount: u64, pool_assets: u64, total_shares: u64) -> Result<u64> {
+    if total_shares == 0 {
+        return Ok(amount);
+    }
+    require!(pool_assets > 0, ErrorCode::InsufficientLiquidity);
+    mul_div(amount, total_shares, pool_assets)
+}
+
+/// Tokens paid out for redeeming `shares` from a pool worth `net_assets`.
+pub(crate) fn withdrawal_amount(shares: u64, net_assets: u64, total_shares: u64) -> Result<u64> {
+    require!(
+        shares <= total_shares && total_shares > 0,
+        ErrorCode::InsufficientShares
+    );
+    mul_div(shares, net_assets, total_shares)
+}
+
+/// `value * numerator / denominator`, rounded down.
+fn mul_div(value: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let result = (value as u128)
+        .checked_mul(numerator as u128)
+        .and_then(|value| value.checked_div(denominator as u128))
+        .ok_or(ErrorCode::MathOverflow)?;
+    u64::try_from(result).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Whether `now` is more than `max_age_seconds` past the end of the price
+/// window around `ts`, so no price the oracle accepts now can stand in for the
+/// price at `ts`.
+pub(crate) fn past_price_window(ts: i64, now: i64, max_age_seconds: u64) -> bool {
+    let window_end = ts.saturating_add(ROUND_PRICE_TOLERANCE_SECONDS);
+    now.saturating_sub(window_end).max(0) as u64 > max_age_seconds
+}
+
+/// Whether a price published at `publish_time` can stand in for the price at
+/// `ts`. One from before the window is an error rather than `false`: the oracle
+/// just hasn't caught up yet, so the crank can be retried.
+pub(crate) fn price_near(ts: i64, publish_time: i64) -> Result<bool> {
+    require!(
+        publish_time >= ts.saturating_sub(ROUND_PRICE_TOLERANCE_SECONDS),
+        ErrorCode::RoundPriceNotReady
+    );
+    Ok(publish_time <= ts.saturating_add(ROUND_PRICE_TOLERANCE_SECONDS))
+}
+
 /// Returns the price direction between opening and settlement.
 pub(crate) fn outcome(settle_price: i64, open_price: i64) -> Result<Direction> {
     if settle_price > open_price {
@@ -94,6 +169,46 @@ pub(crate) fn pool_signed_transfer<'info>(
     Ok(())
 }
 
+/// Mints pool share tokens with the Pool PDA as mint authority.
+pub(crate) fn pool_signed_mint_to<'info>(
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    pool: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+    pool_mint: Pubkey,
+    pool_bump: u8,
+) -> Result<()> {
+    let pool_bump_seed = [pool_bump];
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED, pool_mint.as_ref(), &pool_bump_seed]];
+    let cpi_accounts = MintTo {
+        mint,
+        to,
+        authority: pool,
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.key(), cpi_accounts, signer_seeds);
+    token::mint_to(cpi_ctx, amount)?;
+    Ok(())
+}
+
+/// Burns tokens using the transaction signer as SPL owner.
+pub(crate) fn signer_burn<'info>(
+    mint: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = Burn {
+        mint,
+        from,
+        authority,
+    };
+    let cpi_ctx = CpiContext::new(token_program.key(), cpi_accounts);
+    token::burn(cpi_ctx, amount)?;
+    Ok(())
+}
+
 /// Transfers tokens using the transaction signer as SPL owner or delegate.
 pub(crate) fn signer_transfer<'info>(
     from: AccountInfo<'info>,
@@ -365,3 +480,169 @@ pub(crate) fn metadata_pda(delegated_account: &Pubkey) -> Pubkey {
     )
     .0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::RoundStatus;
+
+    fn ledger(up_liability: u64, down_liability: u64, round_liability: u64) -> PoolLedger {
+        PoolLedger {
+            pool: Pubkey::default(),
+            up_stake: 0,
+            down_stake: 0,
+            up_liability,
+            down_liability,
+            round_liability,
+            bump: 0,
+            total_shares: 0,
+        }
+    }
+
+    fn round(up_total: u64, down_total: u64, winner: Direction, distributable: u64) -> Round {
+        Round {
+            pool: Pubkey::default(),
+            round_id: 0,
+            open_ts: 0,
+            lock_ts: 0,
+            resolve_ts: 0,
+            lock_price: 0,
+            resolve_price: 0,
+            up_total,
+            down_total,
+            status: RoundStatus::Resolved,
+            winner: Some(winner),
+            distributable,
+            bump: 0,
+            claimed_stake: 0,
+            claimed_share: 0,
+        }
+    }
+
+    fn bet(direction: Direction, stake: u64) -> Bet {
+        Bet {
+            bet_id: 0,
+            open_price: 0,
+            expiry_ts: 0,
+            direction,
+            stake,
+            is_open: true,
+            bump: 0,
+            round: Pubkey::default(),
+            pool: Pubkey::default(),
+        }
+    }
+
+    #[test]
+    fn round_price_must_be_published_near_the_target_time() {
+        let tolerance = ROUND_PRICE_TOLERANCE_SECONDS;
+        assert!(price_near(100, 100).unwrap());
+        assert!(price_near(100, 100 - tolerance).unwrap());
+        assert!(price_near(100, 100 + tolerance).unwrap());
+        // A later tick can't be cherry-picked: the round refunds instead.
+        assert!(!price_near(100, 101 + tolerance).unwrap());
+        // An older one means the oracle hasn't caught up; the crank retries.
+        assert!(price_near(100, 99 - tolerance).is_err());
+    }
+
+    #[test]
+    fn round_price_window_closes_once_it_ages_out() {
+        let window_end = 100 + ROUND_PRICE_TOLERANCE_SECONDS;
+        assert!(!past_price_window(100, 100, 60));
+        assert!(!past_price_window(100, window_end + 60, 60));
+        assert!(past_price_window(100, window_end + 61, 60));
+    }
+
+    #[test]
+    fn last_winning_claim_releases_rounding_dust() {
+        // Winners staked 10 and 20 and split 10 from the losing side.
+        let mut round = round(30, 10, Direction::Up, 10);
+        let small = bet(Direction::Up, 10);
+        let large = bet(Direction::Up, 20);
+        assert_eq!(round_payout(&round, &small).unwrap(), 13);
+        assert_eq!(round_payout(&round, &large).unwrap(), 26);
+        assert_eq!(round_payout(&round, &bet(Direction::Down, 10)).unwrap(), 0);
+
+        assert_eq!(round.record_win(20, 6).unwrap(), 0);
+        // 3 + 6 of the 10 was paid out; the last claim frees the other 1.
+        assert_eq!(round.record_win(10, 3).unwrap(), 1);
+    }
+
+    #[test]
+    fn first_deposit_mints_one_share_per_token() {
+        assert_eq!(shares_for_deposit(500, 0, 0).unwrap(), 500);
+    }
+
+    #[test]
+    fn deposit_mints_shares_pro_rata_rounded_down() {
+        // 1_000 shares over 2_000 tokens: half a share per token.
+        assert_eq!(shares_for_deposit(300, 2_000, 1_000).unwrap(), 150);
+        assert_eq!(shares_for_deposit(3, 2_000, 1_000).unwrap(), 1);
+    }
+
+    #[test]
+    fn deposit_into_worthless_pool_is_rejected() {
+        assert!(shares_for_deposit(100, 0, 1_000).is_err());
+    }
+
+    #[test]
+    fn withdrawal_pays_pro_rata_rounded_down() {
+        assert_eq!(withdrawal_amount(250, 2_000, 1_000).unwrap(), 500);
+        assert_eq!(withdrawal_amount(1, 999, 1_000).unwrap(), 0);
+        assert_eq!(withdrawal_amount(1_000, 2_000, 1_000).unwrap(), 2_000);
+        assert!(withdrawal_amount(1_001, 2_000, 1_000).is_err());
+        assert!(withdrawal_amount(1, 2_000, 0).is_err());
+    }
+
+    #[test]
+    fn deposits_ignore_open_bet_liability_but_withdrawals_do_not() {
+        // 1_000 tokens: 200 held for a round, 300 owed on UP and 100 on DOWN.
+        let ledger = ledger(300, 100, 200);
+        assert_eq!(deposit_assets(1_000, &ledger), 800);
+        assert_eq!(net_assets(1_000, &ledger).unwrap(), 400);
+
+        // A deposit buys in at the 800 valuation, not the worst-case 400.
+        assert_eq!(shares_for_deposit(400, 800, 800).unwrap(), 400);
+        assert_eq!(withdrawal_amount(400, 400, 800).unwrap(), 200);
+    }
+
+    #[test]
+    fn pool_assets_never_go_negative() {
+        let ledger = ledger(0, 0, 2_000);
+        assert_eq!(deposit_assets(1_000, &ledger), 0);
+        assert_eq!(net_assets(1_000, &ledger).unwrap(), 0);
+    }
+
+    /// Deposits `amount` and immediately withdraws the shares it bought.
+    fn deposit_then_withdraw(
+        pool_balance: u64,
+        total_shares: u64,
+        ledger: &PoolLedger,
+        amount: u64,
+    ) -> u64 {
+        let shares =
+            shares_for_deposit(amount, deposit_assets(pool_balance, ledger), total_shares).unwrap();
+        let net_assets = net_assets(pool_balance + amount, ledger).unwrap();
+        withdrawal_amount(shares, net_assets, total_shares + shares).unwrap()
+    }
+
+    #[test]
+    fn deposit_then_withdraw_never_returns_more_than_deposited() {
+        // With no open bets, or only round stake held, the round trip is exact.
+        assert_eq!(
+            deposit_then_withdraw(1_000, 1_000, &ledger(0, 0, 0), 100),
+            100
+        );
+        assert_eq!(
+            deposit_then_withdraw(1_000, 1_000, &ledger(0, 0, 200), 100),
+            100
+        );
+
+        // Open fixed-payout bets are marked at their worst case on the way out,
+        // so the new shares give up part of their value to the existing LPs.
+        assert_eq!(
+            deposit_then_withdraw(1_000, 1_000, &ledger(300, 0, 0), 100),
+            72
+        );
+    }
+}
@@ -28,6 +28,12 @@ const ORACLE_PROGRAM_ID = new web3.PublicKey(
 );
 const POOL_SEED = Buffer.from("pool");
 const BET_SEED = Buffer.from("bet");
+const ROUND_SEED = Buffer.from("round");
+const LEDGER_SEED = Buffer.from("ledger");
+const EXPOSURE_SEED = Buffer.from("exposure");
+const LP_SEED = Buffer.from("lp");
+const SHARE_MINT_SEED = Buffer.from("share-mint");
+const MANUAL_ORACLE_SEED = Buffer.from("manual-oracle");
 const PRICE_FEED_SEED = Buffer.from("price_feed");
 const ORACLE_PROVIDER = "pyth-lazer";
 const ORACLE_SYMBOL = "6";
@@ -37,6 +43,18 @@ const POOL_SEED_AMOUNT = new BN(10_000);
 const BET_DURATION_SECONDS = new BN(5);
 const MIN_STAKE = new BN(10);
 const PAYOUT_BPS = new BN(19_000);
+const ROUND_FEE_BPS = new BN(0);
+const NO_LIMITS = { maxUserStake: new BN(0), maxDirectionStake: new BN(0) };
+const PYTH_ORACLE = {
+  kind: { pyth: {} },
+  maxAgeSeconds: new BN(300),
+  maxConfidenceBps: new BN(0),
+};
+const MANUAL_ORACLE = {
+  kind: { manual: {} },
+  maxAgeSeconds: new BN(5),
+  maxConfidenceBps: new BN(0),
+};
 const BET_DURATION_MS = 6_000;
 
 const INITIALIZE_PRICE_FEED_DISCRIMINATOR = Buffer.from([
@@ -124,11 +142,65 @@ function priceFeed(): web3.PublicKey {
   );
 }
 
+function u64Seed(value: number): Buffer {
+  return new BN(value).toArrayLike(Buffer, "le", 8);
+}
+
 function betPda(
   programId: web3.PublicKey,
   user: web3.PublicKey,
+  betId: number,
+): web3.PublicKey {
+  return pda([BET_SEED, user.toBuffer(), u64Seed(betId)], programId);
+}
+
+function roundPda(
+  programId: web3.PublicKey,
+  pool: web3.PublicKey,
+  roundId: number,
+): web3.PublicKey {
+  return pda([ROUND_SEED, pool.toBuffer(), u64Seed(roundId)], programId);
+}
+
+function ledgerPda(
+  programId: web3.PublicKey,
+  pool: web3.PublicKey,
+): web3.PublicKey {
+  return pda([LEDGER_SEED, pool.toBuffer()], programId);
+}
+
+function exposurePda(
+  programId: web3.PublicKey,
+  pool: web3.PublicKey,
+  user: web3.PublicKey,
+): web3.PublicKey {
+  return pda([EXPOSURE_SEED, pool.toBuffer(), user.toBuffer()], programId);
+}
+
+function lpPositionPda(
+  programId: web3.PublicKey,
+  pool: web3.PublicKey,
+  owner: web3.PublicKey,
+): web3.PublicKey {
+  return pda([LP_SEED, pool.toBuffer(), owner.toBuffer()], programId);
+}
+
+function manualOraclePda(
+  programId: web3.PublicKey,
+  authority: web3.PublicKey,
+  oracleId: number,
+): web3.PublicKey {
+  return pda(
+    [MANUAL_ORACLE_SEED, authority.toBuffer(), u64Seed(oracleId)],
+    programId,
+  );
+}
+
+function shareMintPda(
+  programId: web3.PublicKey,
+  pool: web3.PublicKey,
 ): web3.PublicKey {
-  return pda([BET_SEED, user.toBuffer()], programId);
+  return pda([SHARE_MINT_SEED, pool.toBuffer()], programId);
 }
 
 function encodeInstruction(
@@ -247,6 +319,10 @@ async function sleep(ms: number): Promise<void> {
   await new Promise((resolve) => setTimeout(resolve, ms));
 }
 
+async function sleepUntil(unixSeconds: number): Promise<void> {
+  await sleep(Math.max(0, unixSeconds * 1_000 - Date.now()));
+}
+
 async function sendLocalTransaction(
   connection: web3.Connection,
   transaction: web3.Transaction,
@@ -348,6 +424,14 @@ async function getCommitmentSignatureWithLogs(
   );
 }
 
+type Market = {
+  mint: web3.PublicKey;
+  pool: web3.PublicKey;
+  ledger: web3.PublicKey;
+  poolAta: web3.PublicKey;
+  priceFeed: web3.PublicKey;
+};
+
 describe("binary-prediction", () => {
   const provider = process.env.PROVIDER_ENDPOINT
     ? new anchor.AnchorProvider(
@@ -380,43 +464,43 @@ describe("binary-prediction", () => {
   const sessionKeypair = web3.Keypair.generate();
   const feed = priceFeed();
   const feedId = Array.from(feed.toBytes());
-  const userBet = betPda(program.programId, user.publicKey);
+  const userBet = betPda(program.programId, user.publicKey, 0);
+  const validator = new web3.PublicKey(
+    process.env.VALIDATOR ?? "mAGicPQYBMvcYveUZA5F5UNNwyHvfYh5xkLS2Fr1mev",
+  );
 
   let pool: web3.PublicKey;
+  let ledger: web3.PublicKey;
   let mint: web3.PublicKey;
   let userAta: web3.PublicKey;
   let poolAta: web3.PublicKey;
-  let poolEata: web3.PublicKey;
-  let vaultPda: web3.PublicKey;
-  let vaultEata: web3.PublicKey;
-  let vaultAta: web3.PublicKey;
   let sessionTokenPda: web3.PublicKey;
 
-  it("runs initialize -> bet -> settle -> user withdraw", async () => {
-    mint = await createMint(
+  function mainMarket(): Market {
+    return { mint, pool, ledger, poolAta, priceFeed: feed };
+  }
+
+  async function createMarket(
+    priceFeed: web3.PublicKey,
+    oracle: Parameters<typeof program.methods.initialize>[8],
+    limits = NO_LIMITS,
+    seedAmount = POOL_SEED_AMOUNT,
+  ): Promise<Market> {
+    const mint = await createMint(
       provider.connection,
       admin,
       admin.publicKey,
       null,
       0,
     );
-    userAta = await createAssociatedTokenAccount(
-      provider.connection,
-      admin,
-      mint,
-      user.publicKey,
-    );
-    pool = web3.PublicKey.findProgramAddressSync(
-      [POOL_SEED, mint.toBuffer()],
-      program.programId,
-    )[0];
-    poolAta = getAssociatedTokenAddressSync(mint, pool, true);
-    poolEata = eata(pool, mint);
-    vaultPda = vault(mint);
-    vaultEata = eata(vaultPda, mint);
-    vaultAta = getAssociatedTokenAddressSync(mint, vaultPda, true);
+    const pool = pda([POOL_SEED, mint.toBuffer()], program.programId);
+    const ledger = ledgerPda(program.programId, pool);
+    const poolAta = getAssociatedTokenAddressSync(mint, pool, true);
+    const poolEata = eata(pool, mint);
+    const vaultPda = vault(mint);
+    const vaultEata = eata(vaultPda, mint);
+    const vaultAta = getAssociatedTokenAddressSync(mint, vaultPda, true);
 
-    await mintTo(provider.connection, admin, mint, userAta, admin, 1_000n);
     const adminAta = await createAssociatedTokenAccount(
       provider.connection,
       admin,
@@ -429,46 +513,20 @@ describe("binary-prediction", () => {
       mint,
       adminAta,
       admin,
-      BigInt(POOL_SEED_AMOUNT.toString()),
-    );
-
-    const feedAccount = await provider.connection.getAccountInfo(
-      feed,
-      "confirmed",
-    );
-    const feedIsDelegated = Boolean(
-      feedAccount?.owner.equals(DELEGATION_PROGRAM_ID),
-    );
-    if (!feedAccount) {
-      await sendLocalTransaction(
-        provider.connection,
-        new anchor.web3.Transaction().add(
-          initializePriceFeedIx(admin.publicKey, feed),
-        ),
-        admin,
-      );
-    }
-
-    await sendLocalTransaction(
-      feedIsDelegated ? erProvider.connection : provider.connection,
-      new anchor.web3.Transaction().add(
-        updatePriceFeedIx(admin.publicKey, feed, 100),
-      ),
-      admin,
-    );
-
-    const validator = new web3.PublicKey(
-      process.env.VALIDATOR ?? "mAGicPQYBMvcYveUZA5F5UNNwyHvfYh5xkLS2Fr1mev",
+      BigInt(seedAmount.toString()),
     );
 
     const initializeTx = await program.methods
       .initialize(
-        feed,
-        feedId,
-        POOL_SEED_AMOUNT,
+        priceFeed,
+        Array.from(priceFeed.toBytes()),
+        seedAmount,
         BET_DURATION_SECONDS,
         MIN_STAKE,
         PAYOUT_BPS,
+        ROUND_FEE_BPS,
+        limits,
+        oracle,
       )
       .preInstructions([
         web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_000_000 }),
@@ -477,6 +535,13 @@ describe("binary-prediction", () => {
         admin: admin.publicKey,
         mint,
         pool,
+        ledger,
+        adminLpPosition: lpPositionPda(
+          program.programId,
+          pool,
+          admin.publicKey,
+        ),
+        shareMint: shareMintPda(program.programId, pool),
         poolTokenAccount: poolAta,
         adminTokenAccount: adminAta,
         poolEphemeralAta: poolEata,
@@ -507,6 +572,359 @@ describe("binary-prediction", () => {
       .transaction();
     await sendLocalTransaction(provider.connection, initializeTx, admin);
 
+    const delegateLedgerTx = await program.methods
+      .delegateLedger()
+      .accountsPartial({
+        payer: admin.publicKey,
+        mint,
+        pool,
+        ledger,
+      })
+      .remainingAccounts([
+        { pubkey: validator, isSigner: false, isWritable: false },
+      ])
+      .transaction();
+    await sendLocalTransaction(provider.connection, delegateLedgerTx, admin);
+
+    return { mint, pool, ledger, poolAta, priceFeed };
+  }
+
+  async function setPrice(price: number): Promise<void> {
+    await sendLocalTransaction(
+      erProvider.connection,
+      new anchor.web3.Transaction().add(
+        updatePriceFeedIx(admin.publicKey, feed, price),
+      ),
+      admin,
+    );
+  }
+
+  async function setupExposure(
+    bettor: web3.Keypair,
+    { mint, pool } = mainMarket(),
+  ): Promise<void> {
+    const exposure = exposurePda(program.programId, pool, bettor.publicKey);
+    const initializeExposureTx = await program.methods
+      .initializeExposure()
+      .accountsPartial({
+        payer: admin.publicKey,
+        user: bettor.publicKey,
+        mint,
+        pool,
+        exposure,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .transaction();
+    await sendLocalTransaction(
+      provider.connection,
+      initializeExposureTx,
+      admin,
+    );
+
+    const delegateExposureTx = await program.methods
+      .delegateExposure()
+      .accountsPartial({
+        payer: admin.publicKey,
+        user: bettor.publicKey,
+        mint,
+        pool,
+        exposure,
+      })
+      .remainingAccounts([
+        { pubkey: validator, isSigner: false, isWritable: false },
+      ])
+      .transaction();
+    await sendLocalTransaction(provider.connection, delegateExposureTx, admin, [
+      bettor,
+    ]);
+  }
+
+  async function setupBet(
+    bettor: web3.Keypair,
+    betId: number,
+  ): Promise<web3.PublicKey> {
+    const bet = betPda(program.programId, bettor.publicKey, betId);
+    const initializeBetTx = await program.methods
+      .initializeBet(new BN(betId))
+      .accountsPartial({
+        payer: admin.publicKey,
+        user: bettor.publicKey,
+        bet,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .transaction();
+    await sendLocalTransaction(provider.connection, initializeBetTx, admin);
+
+    const delegateBetTx = await program.methods
+      .delegateBet(new BN(betId))
+      .accountsPartial({
+        payer: admin.publicKey,
+        user: bettor.publicKey,
+        bet,
+      })
+      .remainingAccounts([
+        { pubkey: validator, isSigner: false, isWritable: false },
+      ])
+      .transaction();
+    await sendLocalTransaction(provider.connection, delegateBetTx, admin, [
+      bettor,
+    ]);
+
+    return bet;
+  }
+
+  async function fundOnEr(
+    owner: web3.Keypair,
+    amount: bigint,
+    mint: web3.PublicKey,
+  ): Promise<web3.PublicKey> {
+    const ownerAta = await createAssociatedTokenAccount(
+      provider.connection,
+      admin,
+      mint,
+      owner.publicKey,
+    );
+    await mintTo(provider.connection, admin, mint, ownerAta, admin, amount);
+
+    const delegateOwnerIxs = await delegateSpl(owner.publicKey, mint, amount, {
+      validator,
+      idempotent: false,
+      initVaultIfMissing: false,
+      payer: admin.publicKey,
+    });
+    await sendLocalTransaction(
+      provider.connection,
+      new anchor.web3.Transaction().add(...delegateOwnerIxs),
+      admin,
+      [owner],
+    );
+
+    return ownerAta;
+  }
+
+  async function setupBettor(
+    amount: bigint,
+    market = mainMarket(),
+  ): Promise<{ bettor: web3.Keypair; bettorAta: web3.PublicKey }> {
+    const bettor = web3.Keypair.generate();
+    const bettorAta = await fundOnEr(bettor, amount, market.mint);
+    await setupExposure(bettor, market);
+
+    return { bettor, bettorAta };
+  }
+
+  async function placeBet(
+    bettor: web3.Keypair,
+    bettorAta: web3.PublicKey,
+    bet: web3.PublicKey,
+    direction: { up: {} } | { down: {} },
+    stake: number,
+    { mint, pool, ledger, poolAta, priceFeed } = mainMarket(),
+  ): Promise<void> {
+    const placeBetTx = await erProgram.methods
+      .placeBet(direction, new BN(stake))
+      .accountsPartial({
+        payer: bettor.publicKey,
+        user: bettor.publicKey,
+        mint,
+        pool,
+        ledger,
+        exposure: exposurePda(program.programId, pool, bettor.publicKey),
+        bet,
+        userTokenAccount: bettorAta,
+        poolTokenAccount: poolAta,
+        priceUpdate: priceFeed,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        sessionToken: null,
+      })
+      .transaction();
+    await sendLocalTransaction(erProvider.connection, placeBetTx, admin, [
+      bettor,
+    ]);
+  }
+
+  async function manageLiquidity(
+    method: "depositLiquidity" | "withdrawLiquidity",
+    owner: web3.Keypair,
+    ownerAta: web3.PublicKey,
+    amount: number,
+    { mint, pool, ledger, poolAta } = mainMarket(),
+  ): Promise<void> {
+    const manageLiquidityTx = await erProgram.methods[method](new BN(amount))
+      .accountsPartial({
+        owner: owner.publicKey,
+        mint,
+        pool,
+        ledger,
+        lpPosition: lpPositionPda(program.programId, pool, owner.publicKey),
+        ownerTokenAccount: ownerAta,
+        poolTokenAccount: poolAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .transaction();
+    await sendLocalTransaction(
+      erProvider.connection,
+      manageLiquidityTx,
+      admin,
+      [owner],
+    );
+  }
+
+  async function expectProgramError(
+    action: Promise<unknown>,
+    name: string,
+  ): Promise<void> {
+    const code = program.idl.errors.find((error) => error.name === name)?.code;
+    let message = "";
+    try {
+      await action;
+    } catch (err) {
+      message = String(err);
+    }
+    expect(message).to.include(`"Custom":${code}`);
+  }
+
+  async function setupRound(
+    roundId: number,
+    lockTs: number,
+  ): Promise<web3.PublicKey> {
+    const round = roundPda(program.programId, pool, roundId);
+    const openRoundTx = await program.methods
+      .openRound(new BN(roundId), new BN(lockTs))
+      .accountsPartial({
+        payer: admin.publicKey,
+        mint,
+        pool,
+        round,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .transaction();
+    await sendLocalTransaction(provider.connection, openRoundTx, admin);
+
+    const delegateRoundTx = await program.methods
+      .delegateRound(new BN(roundId))
+      .accountsPartial({
+        payer: admin.publicKey,
+        mint,
+        pool,
+        round,
+      })
+      .remainingAccounts([
+        { pubkey: validator, isSigner: false, isWritable: false },
+      ])
+      .transaction();
+    await sendLocalTransaction(provider.connection, delegateRoundTx, admin);
+
+    return round;
+  }
+
+  async function placeRoundBet(
+    bettor: web3.Keypair,
+    bettorAta: web3.PublicKey,
+    round: web3.PublicKey,
+    bet: web3.PublicKey,
+    direction: { up: {} } | { down: {} },
+    stake: number,
+  ): Promise<void> {
+    const placeRoundBetTx = await erProgram.methods
+      .placeRoundBet(direction, new BN(stake))
+      .accountsPartial({
+        payer: bettor.publicKey,
+        user: bettor.publicKey,
+        mint,
+        pool,
+        ledger,
+        exposure: exposurePda(program.programId, pool, bettor.publicKey),
+        round,
+        bet,
+        userTokenAccount: bettorAta,
+        poolTokenAccount: poolAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        sessionToken: null,
+      })
+      .transaction();
+    await sendLocalTransaction(erProvider.connection, placeRoundBetTx, admin, [
+      bettor,
+    ]);
+  }
+
+  async function crankRound(
+    crank: "lockRound" | "resolveRound",
+    round: web3.PublicKey,
+  ): Promise<void> {
+    const crankTx = await erProgram.methods[crank]()
+      .accountsPartial({
+        payer: admin.publicKey,
+        mint,
+        pool,
+        ledger,
+        round,
+        priceUpdate: feed,
+      })
+      .transaction();
+    await sendLocalTransaction(erProvider.connection, crankTx, admin);
+  }
+
+  async function claimRoundBet(
+    bettor: web3.Keypair,
+    bettorAta: web3.PublicKey,
+    round: web3.PublicKey,
+    bet: web3.PublicKey,
+  ): Promise<void> {
+    const claimTx = await erProgram.methods
+      .claimRoundBet()
+      .accountsPartial({
+        payer: admin.publicKey,
+        user: bettor.publicKey,
+        mint,
+        pool,
+        ledger,
+        exposure: exposurePda(program.programId, pool, bettor.publicKey),
+        round,
+        bet,
+        userTokenAccount: bettorAta,
+        poolTokenAccount: poolAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .transaction();
+    await sendLocalTransaction(erProvider.connection, claimTx, admin);
+  }
+
+  it("runs initialize -> bet -> settle -> user withdraw", async () => {
+    const feedAccount = await provider.connection.getAccountInfo(
+      feed,
+      "confirmed",
+    );
+    const feedIsDelegated = Boolean(
+      feedAccount?.owner.equals(DELEGATION_PROGRAM_ID),
+    );
+    if (!feedAccount) {
+      await sendLocalTransaction(
+        provider.connection,
+        new anchor.web3.Transaction().add(
+          initializePriceFeedIx(admin.publicKey, feed),
+        ),
+        admin,
+      );
+    }
+
+    await sendLocalTransaction(
+      feedIsDelegated ? erProvider.connection : provider.connection,
+      new anchor.web3.Transaction().add(
+        updatePriceFeedIx(admin.publicKey, feed, 100),
+      ),
+      admin,
+    );
+
+    ({ mint, pool, ledger, poolAta } = await createMarket(feed, PYTH_ORACLE));
+    userAta = await createAssociatedTokenAccount(
+      provider.connection,
+      admin,
+      mint,
+      user.publicKey,
+    );
+    await mintTo(provider.connection, admin, mint, userAta, admin, 1_000n);
+
     const poolState = await program.account.pool.fetch(pool);
     expect(poolState.betDurationSeconds.toNumber()).to.equal(
       BET_DURATION_SECONDS.toNumber(),
@@ -520,17 +938,6 @@ describe("binary-prediction", () => {
       0n,
     );
 
-    const initializeBetTx = await program.methods
-      .initializeBet()
-      .accountsPartial({
-        payer: admin.publicKey,
-        user: user.publicKey,
-        bet: userBet,
-        systemProgram: web3.SystemProgram.programId,
-      })
-      .transaction();
-    await sendLocalTransaction(provider.connection, initializeBetTx, admin);
-
     if (!feedIsDelegated) {
       await sendLocalTransaction(
         provider.connection,
@@ -541,20 +948,8 @@ describe("binary-prediction", () => {
       );
     }
 
-    const delegateBetTx = await program.methods
-      .delegateBet()
-      .accountsPartial({
-        payer: admin.publicKey,
-        user: user.publicKey,
-        bet: userBet,
-      })
-      .remainingAccounts([
-        { pubkey: validator, isSigner: false, isWritable: false },
-      ])
-      .transaction();
-    await sendLocalTransaction(provider.connection, delegateBetTx, admin, [
-      user,
-    ]);
+    await setupExposure(user);
+    await setupBet(user, 0);
 
     const delegateUserIxs = await delegateSpl(
       user.publicKey,
@@ -583,6 +978,8 @@ describe("binary-prediction", () => {
         user: user.publicKey,
         mint,
         pool,
+        ledger,
+        exposure: exposurePda(program.programId, pool, user.publicKey),
         bet: userBet,
         userTokenAccount: userAta,
         poolTokenAccount: poolAta,
@@ -616,6 +1013,8 @@ describe("binary-prediction", () => {
         user: user.publicKey,
         mint,
         pool,
+        ledger,
+        exposure: exposurePda(program.programId, pool, user.publicKey),
         bet: userBet,
         userTokenAccount: userAta,
         poolTokenAccount: poolAta,
@@ -680,6 +1079,8 @@ describe("binary-prediction", () => {
         user: user.publicKey,
         mint,
         pool,
+        ledger,
+        exposure: exposurePda(program.programId, pool, user.publicKey),
         bet: userBet,
         userTokenAccount: userAta,
         poolTokenAccount: poolAta,
@@ -711,6 +1112,8 @@ describe("binary-prediction", () => {
         user: user.publicKey,
         mint,
         pool,
+        ledger,
+        exposure: exposurePda(program.programId, pool, user.publicKey),
         bet: userBet,
         userTokenAccount: userAta,
         poolTokenAccount: poolAta,
@@ -764,4 +1167,452 @@ describe("binary-prediction", () => {
       0n,
     );
   });
+
+  it("keeps overlapping bets open and settles them in one batch", async () => {
+    const { bettor, bettorAta } = await setupBettor(USER_DELEGATION);
+    const exposure = exposurePda(program.programId, pool, bettor.publicKey);
+    const upBet = await setupBet(bettor, 1);
+    const downBet = await setupBet(bettor, 2);
+    await sleep(3_000);
+    await setPrice(100);
+
+    for (const [betId, bet, direction] of [
+      [1, upBet, { up: {} }],
+      [2, downBet, { down: {} }],
+    ] as const) {
+      const placeBetTx = await erProgram.methods
+        .placeBet(direction, STAKE)
+        .accountsPartial({
+          payer: bettor.publicKey,
+          user: bettor.publicKey,
+          mint,
+          pool,
+          ledger,
+          exposure,
+          bet,
+          userTokenAccount: bettorAta,
+          poolTokenAccount: poolAta,
+          priceUpdate: feed,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          sessionToken: null,
+        })
+        .transaction();
+      await sendLocalTransaction(erProvider.connection, placeBetTx, admin, [
+        bettor,
+      ]);
+      expect(
+        (await erProgram.account.bet.fetch(bet)).betId.toNumber(),
+      ).to.equal(betId);
+    }
+
+    const erPoolBalance = (await getAccount(erProvider.connection, poolAta))
+      .amount;
+    let ledgerState = await erProgram.account.poolLedger.fetch(ledger);
+    expect(ledgerState.upStake.toNumber()).to.equal(STAKE.toNumber());
+    expect(ledgerState.downStake.toNumber()).to.equal(STAKE.toNumber());
+    expect(
+      (await erProgram.account.exposure.fetch(exposure)).openStake.toNumber(),
+    ).to.equal(2 * STAKE.toNumber());
+
+    await setPrice(110);
+    await sleep(BET_DURATION_MS);
+
+    const settleExpiredTx = await erProgram.methods
+      .settleExpired()
+      .accountsPartial({
+        payer: admin.publicKey,
+        user: bettor.publicKey,
+        mint,
+        pool,
+        ledger,
+        exposure,
+        userTokenAccount: bettorAta,
+        poolTokenAccount: poolAta,
+        priceUpdate: feed,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts([
+        { pubkey: upBet, isSigner: false, isWritable: true },
+        { pubkey: downBet, isSigner: false, isWritable: true },
+      ])
+      .transaction();
+    await sendLocalTransaction(erProvider.connection, settleExpiredTx, admin);
+
+    expect((await erProgram.account.bet.fetch(upBet)).isOpen).to.equal(false);
+    expect((await erProgram.account.bet.fetch(downBet)).isOpen).to.equal(false);
+    ledgerState = await erProgram.account.poolLedger.fetch(ledger);
+    expect(ledgerState.upStake.toNumber()).to.equal(0);
+    expect(ledgerState.downStake.toNumber()).to.equal(0);
+    expect(ledgerState.upLiability.toNumber()).to.equal(0);
+    expect(ledgerState.downLiability.toNumber()).to.equal(0);
+    expect(
+      (await erProgram.account.exposure.fetch(exposure)).openStake.toNumber(),
+    ).to.equal(0);
+
+    // The UP bet pays 190 and the DOWN bet loses its 100.
+    expect(
+      (await getAccount(erProvider.connection, bettorAta)).amount,
+    ).to.equal(290n);
+    expect((await getAccount(erProvider.connection, poolAta)).amount).to.equal(
+      erPoolBalance - 190n,
+    );
+  });
+
+  it("resolves a round and releases the claim dust", async () => {
+    const { bettor, bettorAta } = await setupBettor(USER_DELEGATION);
+    const bets = [
+      await setupBet(bettor, 3),
+      await setupBet(bettor, 4),
+      await setupBet(bettor, 5),
+    ];
+    const lockTs = Math.floor(Date.now() / 1_000) + 12;
+    const round = await setupRound(1, lockTs);
+    await sleep(3_000);
+
+    const roundLiability = (await erProgram.account.poolLedger.fetch(ledger))
+      .roundLiability;
+    const erPoolBalance = (await getAccount(erProvider.connection, poolAta))
+      .amount;
+    await placeRoundBet(bettor, bettorAta, round, bets[0], { up: {} }, 10);
+    await placeRoundBet(bettor, bettorAta, round, bets[1], { up: {} }, 20);
+    await placeRoundBet(bettor, bettorAta, round, bets[2], { down: {} }, 10);
+
+    let roundState = await erProgram.account.round.fetch(round);
+    expect(roundState.upTotal.toNumber()).to.equal(30);
+    expect(roundState.downTotal.toNumber()).to.equal(10);
+    let ledgerState = await erProgram.account.poolLedger.fetch(ledger);
+    expect(ledgerState.roundLiability.sub(roundLiability).toNumber()).to.equal(
+      40,
+    );
+
+    await sleepUntil(lockTs);
+    await setPrice(100);
+    await sleepUntil(lockTs + 1);
+    await crankRound("lockRound", round);
+    roundState = await erProgram.account.round.fetch(round);
+    expect(roundState.status).to.deep.equal({ locked: {} });
+    expect(roundState.lockPrice.toNumber()).to.equal(100);
+
+    const resolveTs = roundState.resolveTs.toNumber();
+    await sleepUntil(resolveTs);
+    await setPrice(110);
+    await sleepUntil(resolveTs + 1);
+    await crankRound("resolveRound", round);
+    roundState = await erProgram.account.round.fetch(round);
+    expect(roundState.winner).to.deep.equal({ up: {} });
+    expect(roundState.distributable.toNumber()).to.equal(10);
+
+    for (const bet of bets) {
+      await claimRoundBet(bettor, bettorAta, round, bet);
+      expect((await erProgram.account.bet.fetch(bet)).isOpen).to.equal(false);
+    }
+
+    // The UP stakes claim 13 and 26 of the 40 held; the last claim releases
+    // the 1 left over.
+    ledgerState = await erProgram.account.poolLedger.fetch(ledger);
+    expect(ledgerState.roundLiability.toNumber()).to.equal(
+      roundLiability.toNumber(),
+    );
+    expect(
+      (await getAccount(erProvider.connection, bettorAta)).amount,
+    ).to.equal(299n);
+    expect((await getAccount(erProvider.connection, poolAta)).amount).to.equal(
+      erPoolBalance + 1n,
+    );
+  });
+
+  it("refunds a round with no price near its lock time", async () => {
+    const { bettor, bettorAta } = await setupBettor(USER_DELEGATION);
+    const bet = await setupBet(bettor, 6);
+    const lockTs = Math.floor(Date.now() / 1_000) + 10;
+    const round = await setupRound(2, lockTs);
+    await sleep(3_000);
+
+    await placeRoundBet(bettor, bettorAta, round, bet, { up: {} }, 10);
+    await sleepUntil(lockTs + 4);
+    await setPrice(100);
+    await crankRound("lockRound", round);
+
+    const roundState = await erProgram.account.round.fetch(round);
+    expect(roundState.status).to.deep.equal({ resolved: {} });
+    expect(roundState.winner).to.equal(null);
+
+    await claimRoundBet(bettor, bettorAta, round, bet);
+    expect(
+      (await getAccount(erProvider.connection, bettorAta)).amount,
+    ).to.equal(USER_DELEGATION);
+  });
+
+  it("enforces stake caps and sums both sides for solvency", async () => {
+    const market = await createMarket(
+      feed,
+      PYTH_ORACLE,
+      { maxUserStake: new BN(150), maxDirectionStake: new BN(120) },
+      new BN(100),
+    );
+    const first = await setupBettor(USER_DELEGATION, market);
+    const second = await setupBettor(USER_DELEGATION, market);
+    const firstBets = [
+      await setupBet(first.bettor, 1),
+      await setupBet(first.bettor, 2),
+    ];
+    const secondBet = await setupBet(second.bettor, 1);
+    await sleep(3_000);
+    await setPrice(100);
+
+    await placeBet(
+      first.bettor,
+      first.bettorAta,
+      firstBets[0],
+      { up: {} },
+      100,
+      market,
+    );
+    await expectProgramError(
+      placeBet(
+        first.bettor,
+        first.bettorAta,
+        firstBets[1],
+        { down: {} },
+        60,
+        market,
+      ),
+      "userStakeLimit",
+    );
+    await expectProgramError(
+      placeBet(
+        second.bettor,
+        second.bettorAta,
+        secondBet,
+        { up: {} },
+        30,
+        market,
+      ),
+      "directionStakeLimit",
+    );
+
+    // 300 in the pool can't cover a 190 UP and a 190 DOWN payout together.
+    await expectProgramError(
+      placeBet(
+        second.bettor,
+        second.bettorAta,
+        secondBet,
+        { down: {} },
+        100,
+        market,
+      ),
+      "insufficientLiquidity",
+    );
+    await placeBet(
+      second.bettor,
+      second.bettorAta,
+      secondBet,
+      { down: {} },
+      10,
+      market,
+    );
+
+    const ledgerState = await erProgram.account.poolLedger.fetch(market.ledger);
+    expect(ledgerState.upLiability.toNumber()).to.equal(190);
+    expect(ledgerState.downLiability.toNumber()).to.equal(19);
+  });
+
+  it("round-trips LP deposits and mints shares as tokens", async () => {
+    const market = await createMarket(feed, PYTH_ORACLE);
+    const { mint, pool } = market;
+    const lp = web3.Keypair.generate();
+    const lpAta = await fundOnEr(lp, 1_000n, mint);
+    const lpPosition = lpPositionPda(program.programId, pool, lp.publicKey);
+    const initializeLpPositionTx = await program.methods
+      .initializeLpPosition()
+      .accountsPartial({
+        payer: admin.publicKey,
+        owner: lp.publicKey,
+        mint,
+        pool,
+        lpPosition,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .transaction();
+    await sendLocalTransaction(
+      provider.connection,
+      initializeLpPositionTx,
+      admin,
+    );
+    const delegateLpPositionTx = await program.methods
+      .delegateLpPosition()
+      .accountsPartial({
+        payer: admin.publicKey,
+        owner: lp.publicKey,
+        mint,
+        pool,
+        lpPosition,
+      })
+      .remainingAccounts([
+        { pubkey: validator, isSigner: false, isWritable: false },
+      ])
+      .transaction();
+    await sendLocalTransaction(
+      provider.connection,
+      delegateLpPositionTx,
+      admin,
+      [lp],
+    );
+    await sleep(3_000);
+
+    // No bets are open, so withdrawing right after a deposit returns all of it.
+    await manageLiquidity("depositLiquidity", lp, lpAta, 100, market);
+    expect(
+      (await erProgram.account.lpPosition.fetch(lpPosition)).shares.toNumber(),
+    ).to.equal(100);
+    await manageLiquidity("withdrawLiquidity", lp, lpAta, 100, market);
+    expect((await getAccount(erProvider.connection, lpAta)).amount).to.equal(
+      1_000n,
+    );
+
+    await manageLiquidity("depositLiquidity", lp, lpAta, 100, market);
+    const undelegateLpPositionTx = await erProgram.methods
+      .undelegateLpPosition()
+      .accountsPartial({
+        payer: admin.publicKey,
+        owner: lp.publicKey,
+        mint,
+        pool,
+        lpPosition,
+      })
+      .transaction();
+    const undelegateSig = await sendLocalTransaction(
+      erProvider.connection,
+      undelegateLpPositionTx,
+      admin,
+      [lp],
+    );
+    await provider.connection.confirmTransaction(
+      await getCommitmentSignatureWithLogs(
+        "lp position undelegate",
+        undelegateSig,
+        erProvider.connection,
+      ),
+      "confirmed",
+    );
+
+    const shareMint = shareMintPda(program.programId, pool);
+    const lpShareAta = await createAssociatedTokenAccount(
+      provider.connection,
+      admin,
+      shareMint,
+      lp.publicKey,
+    );
+    for (const method of ["mintShares", "redeemShares"] as const) {
+      const convertSharesTx = await program.methods[method](new BN(100))
+        .accountsPartial({
+          owner: lp.publicKey,
+          mint,
+          pool,
+          lpPosition,
+          shareMint,
+          ownerShareAccount: lpShareAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .transaction();
+      await sendLocalTransaction(provider.connection, convertSharesTx, admin, [
+        lp,
+      ]);
+
+      const minted = method === "mintShares";
+      expect(
+        (await getAccount(provider.connection, lpShareAta)).amount,
+      ).to.equal(minted ? 100n : 0n);
+      expect(
+        (await program.account.lpPosition.fetch(lpPosition)).shares.toNumber(),
+      ).to.equal(minted ? 0 : 100);
+    }
+  });
+
+  it("reads a manual oracle and rejects foreign or stale prices", async () => {
+    const authority = web3.Keypair.generate();
+    const impostor = web3.Keypair.generate();
+    const manualOracle = manualOraclePda(
+      program.programId,
+      authority.publicKey,
+      0,
+    );
+    const createManualOracleTx = await program.methods
+      .createManualOracle(new BN(0), new BN(100), new BN(0))
+      .accountsPartial({
+        payer: admin.publicKey,
+        authority: authority.publicKey,
+        manualOracle,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .transaction();
+    await sendLocalTransaction(
+      provider.connection,
+      createManualOracleTx,
+      admin,
+      [authority],
+    );
+    const delegateManualOracleTx = await program.methods
+      .delegateManualOracle(new BN(0))
+      .accountsPartial({
+        payer: admin.publicKey,
+        authority: authority.publicKey,
+        manualOracle,
+      })
+      .remainingAccounts([
+        { pubkey: validator, isSigner: false, isWritable: false },
+      ])
+      .transaction();
+    await sendLocalTransaction(
+      provider.connection,
+      delegateManualOracleTx,
+      admin,
+      [authority],
+    );
+
+    const market = await createMarket(manualOracle, MANUAL_ORACLE);
+    const { bettor, bettorAta } = await setupBettor(USER_DELEGATION, market);
+    const freshBet = await setupBet(bettor, 1);
+    const staleBet = await setupBet(bettor, 2);
+    await sleep(3_000);
+
+    async function setManualPrice(
+      signer: web3.Keypair,
+      price: number,
+    ): Promise<void> {
+      const setManualPriceTx = await erProgram.methods
+        .setManualPrice(new BN(price), new BN(0))
+        .accountsPartial({ authority: signer.publicKey, manualOracle })
+        .transaction();
+      await sendLocalTransaction(
+        erProvider.connection,
+        setManualPriceTx,
+        admin,
+        [signer],
+      );
+    }
+
+    await setManualPrice(authority, 105);
+    await placeBet(bettor, bettorAta, freshBet, { up: {} }, 100, market);
+    expect(
+      (await erProgram.account.bet.fetch(freshBet)).openPrice.toNumber(),
+    ).to.equal(105);
+
+    let rejected = false;
+    try {
+      await setManualPrice(impostor, 1);
+    } catch {
+      rejected = true;
+    }
+    expect(rejected).to.equal(true);
+    const oracleState =
+      await erProgram.account.manualOracle.fetch(manualOracle);
+    expect(oracleState.price.toNumber()).to.equal(105);
+
+    await sleep((MANUAL_ORACLE.maxAgeSeconds.toNumber() + 2) * 1_000);
+    await expectProgramError(
+      placeBet(bettor, bettorAta, staleBet, { up: {} }, 100, market),
+      "stalePrice",
+    );
+  });
 });
@@ -1,21 +1,65 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
 use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer as TokenTransfer};
 use ephemeral_rollups_sdk::access_control::instructions::{
     CreateEphemeralPermissionCpi, UpdateEphemeralPermissionCpi,
 };
 use ephemeral_rollups_sdk::access_control::structs::{
     EphemeralMembersArgs, EphemeralPermission, Member, AUTHORITY_FLAG, TX_LOGS_FLAG,
 };
-use ephemeral_rollups_sdk::anchor::{commit, delegate, ephemeral};
+use ephemeral_rollups_sdk::anchor::{action, commit, delegate, ephemeral};
 use ephemeral_rollups_sdk::consts::{EPHEMERAL_VAULT_ID, MAGIC_PROGRAM_ID, PERMISSION_PROGRAM_ID};
 use ephemeral_rollups_sdk::cpi::DelegateConfig;
-use ephemeral_rollups_sdk::ephem::MagicIntentBundleBuilder;
+use ephemeral_rollups_sdk::ephem::{CallHandler, MagicIntentBundleBuilder};
+use ephemeral_rollups_sdk::{ActionArgs, ShortAccountMeta};
+
+pub mod tournament;
+pub use tournament::*;
 
 declare_id!("4AEU8Dhg5dRXyfPCdPUibbhQvQVrqs9ZGPvft6wyDBvE");
 
 pub const PLAYER_CHOICE_SEED: &[u8] = b"player_choice";
 pub const GAME_SEED: &[u8] = b"game";
 pub const VAULT_SEED: &[u8] = b"vault";
+pub const LOBBY_SEED: &[u8] = b"lobby";
+pub const PLAYER_STATS_SEED: &[u8] = b"player_stats";
+pub const LEADERBOARD_SEED: &[u8] = b"leaderboard";
+pub const RULE_TABLE_SEED: &[u8] = b"rule_table";
+pub const REMATCH_SEED: &[u8] = b"rematch";
+pub const CONFIG_SEED: &[u8] = b"config";
+/// Highest house fee the config accepts (10%).
+pub const MAX_FEE_BPS: u16 = 1_000;
+/// Most moves a custom rule table can have (one bit per move in `beats`).
+pub const MAX_RULE_MOVES: usize = 16;
+/// Classic rules: `beats[i]` has bit `j` set when move `i` beats move `j`.
+/// Rock (0) beats Scissors, Paper (1) beats Rock, Scissors (2) beats Paper.
+pub const CLASSIC_RULES: [u16; 3] = [0b100, 0b001, 0b010];
+/// Rock-Paper-Scissors-Lizard-Spock, moves 0–4 in `Choice` order.
+pub const RPSLS_RULES: [u16; 5] = [
+    0b01100, // Rock crushes Scissors and Lizard
+    0b10001, // Paper covers Rock, disproves Spock
+    0b01010, // Scissors cut Paper, decapitate Lizard
+    0b10010, // Lizard eats Paper, poisons Spock
+    0b00101, // Spock vaporizes Rock, smashes Scissors
+];
+/// Players tracked by the global leaderboard, highest rating first.
+pub const LEADERBOARD_SIZE: usize = 10;
+/// Rating a player starts from on their first settled match.
+pub const INITIAL_RATING: u32 = 1200;
+/// Elo K-factor: the most rating a single match can move.
+pub const ELO_K: u32 = 32;
+/// Open games a single lobby (stake tier) can queue at once.
+pub const LOBBY_CAPACITY: usize = 16;
+/// Seconds a listed game stays matchable before `quick_join` skips it and
+/// `prune_lobby` may drop it.
+pub const LOBBY_ENTRY_TTL_SECS: i64 = 600;
+/// Default seconds each player has to `make_choice` once a round starts before
+/// `claim_forfeit` can settle it; `create_game` can pick a longer window.
+pub const MOVE_TIMEOUT_SECS: u32 = 120;
+/// Spectators a game's permission can carry on top of the two players.
+pub const MAX_SPECTATORS: usize = 4;
 
 #[ephemeral]
 #[program]
@@ -25,8 +69,8 @@ pub mod anchor_rock_paper_scissor {
 
     // 1️⃣ Create and auto-join as Player 1.
     // Pre-funds the Game PDA with enough rent for its ephemeral permission (room
-    // for [p1, p2] members) and the PlayerChoice PDA with rent for its own (1
-    // member). After delegation those lamports flow with the PDAs onto the ER,
+    // for [p1, p2] plus MAX_SPECTATORS members) and the PlayerChoice PDA with
+    // rent for its own (1 member). After delegation those lamports flow with the PDAs onto the ER,
     // where each PDA PDA-signs its CreateEphemeralPermission CPI and pays its
     // own rent — no external account needs lamports on the ER.
     pub fn create_game(
@@ -34,8 +78,16 @@ pub mod anchor_rock_paper_scissor {
         game_id: u64,
         stake: u64,
         target_wins: u8,
+        move_timeout_secs: u32,
     ) -> Result<()> {
-        // Rent for the game's ephemeral permission (2 members: p1 + p2)
+        // Tagged ids belong to tournament brackets (`spawn_match`).
+        require!(
+            game_id & BRACKET_GAME_ID_FLAG == 0,
+            GameError::ReservedGameId
+        );
+
+        // Rent for the game's ephemeral permission (p1 + p2, plus every
+        // spectator `add_spectator` may add later, even on the ER)
         transfer(
             CpiContext::new(
                 ctx.accounts.system_program.key(),
@@ -44,7 +96,9 @@ pub mod anchor_rock_paper_scissor {
                     to: ctx.accounts.game.to_account_info(),
                 },
             ),
-            ephemeral_rollups_sdk::ephemeral_accounts::rent(EphemeralPermission::size_of(2) as u32),
+            ephemeral_rollups_sdk::ephemeral_accounts::rent(
+                EphemeralPermission::size_of(2 + MAX_SPECTATORS) as u32,
+            ),
         )?;
         // Rent for player1's PlayerChoice ephemeral permission (1 member: p1)
         transfer(
@@ -58,19 +112,26 @@ pub mod anchor_rock_paper_scissor {
             ephemeral_rollups_sdk::ephemeral_accounts::rent(EphemeralPermission::size_of(1) as u32),
         )?;
 
-        // Player 1's wager into the game vault — a base-layer SOL escrow PDA that
-        // is never delegated, so the pot stays put while the game runs on the ER.
+        // Passing a stake mint makes this a token game; without one the wager
+        // is in lamports, exactly as before.
+        ctx.accounts.game.stake_mint = ctx.accounts.stake_mint.as_ref().map(|mint| mint.key());
+
+        // Player 1's wager into the game vault — a base-layer escrow PDA (or its
+        // token account) that is never delegated, so the pot stays put while the
+        // game runs on the ER.
         if stake > 0 {
-            transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.key(),
-                    Transfer {
-                        from: ctx.accounts.player1.to_account_info(),
-                        to: ctx.accounts.vault.to_account_info(),
-                    },
-                ),
-                stake,
+            let vault = StakeVault::new(
+                &ctx.accounts.game,
+                &ctx.accounts.vault,
+                ctx.accounts.vault_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &ctx.accounts.system_program,
             )?;
+            let from = vault.account_of(
+                ctx.accounts.player1.to_account_info(),
+                ctx.accounts.player_token_account.as_ref(),
+            )?;
+            vault.take(from, ctx.accounts.player1.to_account_info(), stake)?;
         }
 
         let game = &mut ctx.accounts.game;
@@ -86,11 +147,30 @@ pub mod anchor_rock_paper_scissor {
         game.target_wins = target_wins.max(1);
         game.player1_wins = 0;
         game.player2_wins = 0;
+        game.tied_rounds = 0;
+        game.tournament = None;
+        game.variant = GameVariant::Classic;
+        game.match_number = 1;
+        game.rematch_ready = false;
+        game.spectators = Vec::new();
         game.round = 1;
+        // The move clock only starts once an opponent joins, and runs from the
+        // base layer — the window has to cover delegating onto the ER too
+        // (0 = `MOVE_TIMEOUT_SECS`).
+        game.round_deadline_ts = 0;
+        game.move_timeout_secs = match move_timeout_secs {
+            0 => MOVE_TIMEOUT_SECS,
+            secs => secs,
+        };
+        game.abandoned = false;
+        game.listed = false;
 
         msg!("Game ID: {}", game_id);
         msg!("Player 1 PDA: {}", player1);
-        msg!("Stake per player: {} lamports", stake);
+        match game.stake_mint {
+            Some(mint) => msg!("Stake per player: {} of mint {}", stake, mint),
+            None => msg!("Stake per player: {} lamports", stake),
+        }
         msg!("Match: first to {} round-wins", game.target_wins);
 
         // initialize PlayerChoice for player 1
@@ -106,48 +186,222 @@ pub mod anchor_rock_paper_scissor {
 
     // 2️⃣ Player 2 joins the game.
     // Pre-funds player2's PlayerChoice PDA with rent for its ephemeral permission.
-    pub fn join_game(ctx: Context<JoinGame>, game_id: u64) -> Result<()> {
-        transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.key(),
-                Transfer {
-                    from: ctx.accounts.player.to_account_info(),
-                    to: ctx.accounts.player_choice.to_account_info(),
-                },
-            ),
-            ephemeral_rollups_sdk::ephemeral_accounts::rent(EphemeralPermission::size_of(1) as u32),
-        )?;
+    pub fn join_game(ctx: Context<JoinGame>, _game_id: u64) -> Result<()> {
+        // Listed games are matched through the lobby so its queue stays accurate
+        // — but once the entry has expired out of the queue the game is open
+        // to a direct join again.
+        if ctx.accounts.game.listed {
+            let lobby = ctx.accounts.lobby.as_mut().ok_or(GameError::MissingLobby)?;
+            lobby.prune_expired(Clock::get()?.unix_timestamp);
+            require!(
+                !lobby.contains(ctx.accounts.game.game_id),
+                GameError::GameListed
+            );
+            ctx.accounts.game.listed = false;
+        }
 
-        let game = &mut ctx.accounts.game;
-        let player = ctx.accounts.player.key();
+        let vault = if ctx.accounts.game.stake > 0 {
+            Some(StakeVault::new(
+                &ctx.accounts.game,
+                &ctx.accounts.vault,
+                ctx.accounts.vault_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &ctx.accounts.system_program,
+            )?)
+        } else {
+            None
+        };
+        seat_player2(
+            &mut ctx.accounts.game,
+            &mut ctx.accounts.player_choice,
+            &ctx.accounts.player,
+            ctx.accounts.player_token_account.as_ref(),
+            vault,
+            &ctx.accounts.system_program,
+        )
+    }
 
+    // Pick the rule table for a game before anyone joins: classic RPS (the
+    // default), Rock-Paper-Scissors-Lizard-Spock, or a custom `RuleTable`.
+    pub fn set_variant(ctx: Context<SetVariant>, variant: GameVariant) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(
+            game.player1 == Some(ctx.accounts.player1.key()),
+            GameError::WrongPlayerAccount
+        );
+        require!(game.player2.is_none(), GameError::GameFull);
         require!(!game.paid, GameError::GameSettled);
-        require!(game.player1 != Some(player), GameError::CannotJoinOwnGame);
+        // Lobby entries are matched on the variant they were listed with.
+        require!(!game.listed, GameError::GameListed);
+
+        // Resolving the rules checks a custom table was passed and matches.
+        variant.rules(ctx.accounts.rule_table.as_ref())?;
+        game.variant = variant;
+        msg!("Game {} variant: {:?}", game.game_id, game.variant);
+        Ok(())
+    }
+
+    // Publish a custom N-move rule table that games can select with
+    // `set_variant`. `beats[i]` has bit `j` set when move `i` beats move `j`;
+    // every pair of distinct moves must have exactly one winner.
+    pub fn create_rule_table(
+        ctx: Context<CreateRuleTable>,
+        table_id: u64,
+        beats: Vec<u16>,
+    ) -> Result<()> {
+        validate_rules(&beats)?;
+        let rule_table = &mut ctx.accounts.rule_table;
+        rule_table.authority = ctx.accounts.authority.key();
+        rule_table.table_id = table_id;
+        rule_table.beats = beats;
+        msg!(
+            "Rule table {} created with {} moves",
+            rule_table.key(),
+            rule_table.beats.len()
+        );
+        Ok(())
+    }
+
+    // Let `spectator` follow the game: read access (TX_LOGS_FLAG) to the Game
+    // permission only — never to the PlayerChoice permissions, which stay
+    // player-only until the reveal. Creator-only; works on the base layer
+    // before delegation or on the ER mid-match. `create_game` already put the
+    // rent for MAX_SPECTATORS extra permission members on the Game PDA, so
+    // nothing is transferred here. While a round is in progress (permission
+    // private) the member list is updated right away; otherwise `next_round`
+    // picks the spectator up when it re-privatizes.
+    pub fn add_spectator(ctx: Context<ManageSpectators>, spectator: Pubkey) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(
+            game.player1 == Some(ctx.accounts.player1.key()),
+            GameError::WrongPlayerAccount
+        );
+        require!(
+            game.player1 != Some(spectator) && game.player2 != Some(spectator),
+            GameError::SpectatorIsPlayer
+        );
+        require!(
+            !game.spectators.contains(&spectator),
+            GameError::AlreadySpectating
+        );
+        require!(
+            game.spectators.len() < MAX_SPECTATORS,
+            GameError::TooManySpectators
+        );
+
+        game.spectators.push(spectator);
+        ctx.accounts.sync_game_permission(ctx.bumps.game)?;
+        msg!("{} is now spectating game {}", spectator, ctx.accounts.game.game_id);
+        Ok(())
+    }
+
+    // Revoke a spectator's read access. Creator-only, same layers as
+    // `add_spectator`; the shrunk permission's rent flows back to the Game PDA.
+    pub fn remove_spectator(ctx: Context<ManageSpectators>, spectator: Pubkey) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(
+            game.player1 == Some(ctx.accounts.player1.key()),
+            GameError::WrongPlayerAccount
+        );
+        let position = game
+            .spectators
+            .iter()
+            .position(|s| *s == spectator)
+            .ok_or(GameError::NotSpectating)?;
+        game.spectators.remove(position);
+
+        ctx.accounts.sync_game_permission(ctx.bumps.game)?;
+        msg!("{} stopped spectating game {}", spectator, ctx.accounts.game.game_id);
+        Ok(())
+    }
+
+    // Put an unjoined game into the matchmaking lobby for its stake tier
+    // (stake + stake mint), creating the lobby on first use. Base layer; only
+    // the creator can list. Expired entries are dropped first to make room,
+    // and a game whose entry expired can be listed again.
+    pub fn list_game(ctx: Context<ListGame>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(
+            game.player1 == Some(ctx.accounts.player1.key()),
+            GameError::WrongPlayerAccount
+        );
         require!(game.player2.is_none(), GameError::GameFull);
+        require!(!game.paid, GameError::GameSettled);
 
-        // Player 2 matches Player 1's wager into the vault.
-        if game.stake > 0 {
-            transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.key(),
-                    Transfer {
-                        from: ctx.accounts.player.to_account_info(),
-                        to: ctx.accounts.vault.to_account_info(),
-                    },
-                ),
-                game.stake,
-            )?;
-        }
+        let now = Clock::get()?.unix_timestamp;
+        let lobby = &mut ctx.accounts.lobby;
+        lobby.stake = game.stake;
+        lobby.stake_mint = game.stake_mint;
+        lobby.prune_expired(now);
+        require!(!lobby.contains(game.game_id), GameError::GameListed);
+        require!(lobby.queue.len() < LOBBY_CAPACITY, GameError::LobbyFull);
+
+        lobby.queue.push(LobbyEntry {
+            game_id: game.game_id,
+            player1: ctx.accounts.player1.key(),
+            target_wins: game.target_wins,
+            variant: game.variant.clone(),
+            listed_ts: now,
+        });
+        game.listed = true;
 
-        game.player2 = Some(player);
+        msg!(
+            "Game {} listed in lobby ({} queued)",
+            game.game_id,
+            lobby.queue.len()
+        );
+        Ok(())
+    }
 
-        // Create PlayerChoice PDA for player 2
-        let player_choice = &mut ctx.accounts.player_choice;
-        player_choice.game_id = game_id;
-        player_choice.player = player;
-        player_choice.choice = None;
+    // Join the oldest live game in the lobby with the caller's `target_wins`.
+    // The client reads the lobby queue to pick `game_id` (its accounts must be
+    // passed up front); the program re-checks that it really is the oldest
+    // match, so nobody can jump the queue.
+    pub fn quick_join(
+        ctx: Context<QuickJoin>,
+        game_id: u64,
+        target_wins: u8,
+        variant: GameVariant,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let player = ctx.accounts.player.key();
+        let lobby = &mut ctx.accounts.lobby;
+        lobby.prune_expired(now);
+        let oldest = lobby
+            .oldest_open(target_wins.max(1), &variant, player)
+            .ok_or(GameError::NoOpenGame)?;
+        require!(oldest == game_id, GameError::NotOldestOpenGame);
+        lobby.remove(game_id);
+        ctx.accounts.game.listed = false;
+
+        let vault = if ctx.accounts.game.stake > 0 {
+            Some(StakeVault::new(
+                &ctx.accounts.game,
+                &ctx.accounts.vault,
+                ctx.accounts.vault_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &ctx.accounts.system_program,
+            )?)
+        } else {
+            None
+        };
+        seat_player2(
+            &mut ctx.accounts.game,
+            &mut ctx.accounts.player_choice,
+            &ctx.accounts.player,
+            ctx.accounts.player_token_account.as_ref(),
+            vault,
+            &ctx.accounts.system_program,
+        )
+    }
 
-        msg!("{} joined Game {} as player 2", player, game_id);
+    // Drop lobby entries older than `LOBBY_ENTRY_TTL_SECS`. Permissionless;
+    // the games themselves stay open — `join_game` and `list_game` treat a
+    // game whose entry is gone as unlisted, and creators can `cancel_game` for
+    // a refund.
+    pub fn prune_lobby(ctx: Context<PruneLobby>) -> Result<()> {
+        let removed = ctx.accounts.lobby.prune_expired(Clock::get()?.unix_timestamp);
+        msg!("Pruned {} expired lobby entries", removed);
         Ok(())
     }
 
@@ -155,6 +409,15 @@ pub mod anchor_rock_paper_scissor {
     pub fn make_choice(ctx: Context<MakeChoice>, _game_id: u64, choice: Choice) -> Result<()> {
         let player_choice = &mut ctx.accounts.player_choice;
         require!(player_choice.choice.is_none(), GameError::AlreadyChose);
+        // An abandoned match takes no more moves; `claim_pot` refunds it.
+        require!(!ctx.accounts.game.abandoned, GameError::MatchDecided);
+
+        // The move has to exist in the game's rule table.
+        let rules = ctx.accounts.game.variant.rules(ctx.accounts.rule_table.as_ref())?;
+        require!(
+            (choice.index() as usize) < rules.len(),
+            GameError::InvalidMove
+        );
 
         player_choice.choice = choice.into();
         msg!(
@@ -181,6 +444,13 @@ pub mod anchor_rock_paper_scissor {
         let ephemeral_vault = ctx.accounts.ephemeral_vault.to_account_info();
         let magic_program = ctx.accounts.magic_program.to_account_info();
 
+        // A round is decided once, either here or by `claim_forfeit`.
+        require!(
+            game.round_result == RoundResult::None,
+            GameError::RoundAlreadyRevealed
+        );
+        require!(!game.abandoned, GameError::MatchDecided);
+
         // 1️⃣ Clone choices into game
         game.player1_choice = player1_choice.choice.clone().into();
         game.player2_choice = player2_choice.choice.clone().into();
@@ -199,24 +469,23 @@ pub mod anchor_rock_paper_scissor {
             .clone()
             .ok_or(GameError::MissingChoice)?;
 
-        // 4️⃣ Determine this round's winner based on choices, and tally the
-        // match score. A tied round counts for neither side and is replayed.
-        game.round_result = match (choice1, choice2) {
-            (Choice::Rock, Choice::Scissors)
-            | (Choice::Paper, Choice::Rock)
-            | (Choice::Scissors, Choice::Paper) => {
+        // 4️⃣ Determine this round's winner from the game's rule table, and
+        // tally the match score. A tied round counts for neither side and is
+        // replayed.
+        let rules = game.variant.rules(ctx.accounts.rule_table.as_ref())?;
+        game.round_result = match resolve_round(rules, &choice1, &choice2) {
+            RoundOutcome::Player1 => {
                 game.player1_wins += 1;
                 RoundResult::Winner(player1)
             }
-
-            (Choice::Rock, Choice::Paper)
-            | (Choice::Paper, Choice::Scissors)
-            | (Choice::Scissors, Choice::Rock) => {
+            RoundOutcome::Player2 => {
                 game.player2_wins += 1;
                 RoundResult::Winner(player2)
             }
-
-            _ => RoundResult::Tie,
+            RoundOutcome::Tie => {
+                game.tied_rounds = game.tied_rounds.saturating_add(1);
+                RoundResult::Tie
+            }
         };
         msg!(
             "Round {} result: {:?} — score {} : {}",
@@ -324,17 +593,41 @@ pub mod anchor_rock_paper_scissor {
 
         // 3️⃣ Advance the match, or start a fresh one:
         //   - match still going → next round, keep the score.
-        //   - match decided → a brand-new match (rematch). Only for free games;
-        //     a staked match must be settled + claimed first (so the pot can't
-        //     be replayed for).
+        //   - match decided → a brand-new match (rematch). Free games restart
+        //     right away; a staked match must either be settled + claimed, or
+        //     have had its pot paid out by the `rematch` action (so the pot
+        //     can't be replayed for).
         if game.is_match_decided() {
-            require!(game.stake == 0, GameError::MustClaimFirst);
+            if game.stake > 0 {
+                require!(game.rematch_ready, GameError::MustClaimFirst);
+                let escrow = ctx
+                    .accounts
+                    .rematch_escrow
+                    .as_ref()
+                    .ok_or(GameError::MissingRematchEscrow)?;
+                require!(
+                    escrow.settled_match == game.match_number,
+                    GameError::RematchNotSettled
+                );
+                game.rematch_ready = false;
+                game.match_number += 1;
+            }
+            // A bracket game's result has to stay put until it's reported.
+            require!(game.tournament.is_none(), GameError::MustClaimFirst);
+            // The stats PDAs live on the base layer, so a free match's result
+            // is parked on the game until `claim_pot` records it. Staked
+            // matches were already recorded by `settle_rematch`.
+            if game.stake == 0 {
+                game.defer_match_result()?;
+            }
             game.player1_wins = 0;
             game.player2_wins = 0;
+            game.tied_rounds = 0;
             game.round = 1;
         } else {
             game.round += 1;
         }
+        game.start_round_clock(Clock::get()?.unix_timestamp);
 
         // 4️⃣ Clear the round state
         game.player1_choice = None;
@@ -362,7 +655,7 @@ pub mod anchor_rock_paper_scissor {
             permission_program: permission_program.clone(),
             authority: game.to_account_info(),
             authority_is_signer: false,
-            args: private_args(vec![member(player1), member(player2)]),
+            args: private_args(game.permission_members()),
         }
         .invoke_signed(&[&[GAME_SEED, &game.game_id.to_le_bytes(), &[ctx.bumps.game]]])?;
 
@@ -411,6 +704,60 @@ pub mod anchor_rock_paper_scissor {
         Ok(())
     }
 
+    // Settle a stalled round on the ER. Once `round_deadline_ts` has passed and
+    // exactly one player has moved, that player takes the MATCH — a player who
+    // stops moving can't hold the pot hostage round after round. If neither
+    // moved, the match is abandoned instead and `claim_pot` refunds both
+    // stakes. Anyone can trigger it; the result only ever names a recorded
+    // player. The choices stay private (nothing to compare), and
+    // `undelegate_all` + `claim_pot` settle the match as usual.
+    pub fn claim_forfeit(ctx: Context<ClaimForfeit>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(
+            game.round_result == RoundResult::None,
+            GameError::RoundAlreadyRevealed
+        );
+        require!(!game.is_match_decided(), GameError::MatchDecided);
+        let player1 = game.player1.ok_or(GameError::MissingOpponent)?;
+        let player2 = game.player2.ok_or(GameError::MissingOpponent)?;
+        require!(
+            game.is_round_expired(Clock::get()?.unix_timestamp),
+            GameError::RoundNotExpired
+        );
+
+        let player1_moved = ctx.accounts.player1_choice.choice.is_some();
+        let player2_moved = ctx.accounts.player2_choice.choice.is_some();
+        let winner = match (player1_moved, player2_moved) {
+            (true, false) => {
+                game.player1_wins = game.target_wins;
+                player1
+            }
+            (false, true) => {
+                game.player2_wins = game.target_wins;
+                player2
+            }
+            (true, true) => return err!(GameError::BothPlayersMoved),
+            (false, false) => {
+                game.abandoned = true;
+                msg!(
+                    "Round {} stalled by both players — match abandoned, stakes refundable",
+                    game.round
+                );
+                return Ok(());
+            }
+        };
+        game.round_result = RoundResult::Winner(winner);
+
+        msg!(
+            "Round {} forfeited — {} takes the match {} : {}",
+            game.round,
+            winner,
+            game.player1_wins,
+            game.player2_wins
+        );
+        Ok(())
+    }
+
     /// Commit + undelegate game + both player_choices in a single magic-intent
     /// bundle. Bring the whole game state back to the base layer at once and
     /// release all three PDAs from the ER.
@@ -445,8 +792,24 @@ pub mod anchor_rock_paper_scissor {
     // recorded on the game.
     pub fn claim_pot(ctx: Context<ClaimPot>) -> Result<()> {
         let game = &mut ctx.accounts.game;
-        let winner = game.match_winner().ok_or(GameError::MatchNotDecided)?;
+        let winner = game.match_winner();
+        require!(winner.is_some() || game.abandoned, GameError::MatchNotDecided);
         require!(!game.paid, GameError::AlreadyPaid);
+        // This match's pot went out through the `rematch` action; play on.
+        // If that action failed, the escrow still shows the match unsettled:
+        // claim the pot as usual and leave the deposits to `refund_rematch`.
+        if game.rematch_ready {
+            let escrow = ctx
+                .accounts
+                .rematch_escrow
+                .as_ref()
+                .ok_or(GameError::MissingRematchEscrow)?;
+            require!(
+                escrow.settled_match + 1 == game.match_number,
+                GameError::RematchPending
+            );
+            game.rematch_ready = false;
+        }
 
         let player1 = game.player1.ok_or(GameError::MissingOpponent)?;
         let player2 = game.player2.ok_or(GameError::MissingOpponent)?;
@@ -461,6 +824,62 @@ pub mod anchor_rock_paper_scissor {
 
         game.paid = true;
 
+        // Record the results on both players' stats (and the leaderboard, if
+        // passed) exactly once — `paid` guards against a second claim. Free
+        // rematches restarted by `next_round` are recorded first, then this
+        // match; an abandoned match has no result to record.
+        let player1_stats = &mut ctx.accounts.player1_stats;
+        let player2_stats = &mut ctx.accounts.player2_stats;
+        player1_stats.ensure_initialized(player1);
+        player2_stats.ensure_initialized(player2);
+        for _ in 0..game.unrecorded_wins[0] {
+            record_result(game, player1_stats, player2_stats, 0);
+        }
+        for _ in 0..game.unrecorded_wins[1] {
+            record_result(game, player2_stats, player1_stats, 0);
+        }
+        player1_stats.ties = player1_stats.ties.saturating_add(game.unrecorded_ties);
+        player2_stats.ties = player2_stats.ties.saturating_add(game.unrecorded_ties);
+        game.unrecorded_wins = [0; 2];
+        game.unrecorded_ties = 0;
+        if let Some(winner) = winner {
+            let tied_rounds = game.tied_rounds as u32;
+            if winner == player1 {
+                record_result(game, player1_stats, player2_stats, tied_rounds);
+            } else {
+                record_result(game, player2_stats, player1_stats, tied_rounds);
+            }
+        }
+        if let Some(leaderboard) = ctx.accounts.leaderboard.as_mut() {
+            leaderboard.record(player1_stats);
+            leaderboard.record(player2_stats);
+        }
+
+        // An abandoned match has no result: each player gets their stake back,
+        // with no house fee.
+        let Some(winner) = winner else {
+            if game.stake > 0 {
+                let game_id_bytes = game.game_id.to_le_bytes();
+                let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &game_id_bytes, &[ctx.bumps.vault]]];
+                let vault = StakeVault::new(
+                    game,
+                    &ctx.accounts.vault,
+                    ctx.accounts.vault_token_account.as_ref(),
+                    ctx.accounts.token_program.as_ref(),
+                    &ctx.accounts.system_program,
+                )?;
+                for (player, token_account) in [
+                    (&ctx.accounts.player1, ctx.accounts.player1_token_account.as_ref()),
+                    (&ctx.accounts.player2, ctx.accounts.player2_token_account.as_ref()),
+                ] {
+                    let to = vault.account_of(player.to_account_info(), token_account)?;
+                    vault.pay(to, game.stake, signer_seeds)?;
+                }
+            }
+            msg!("Game {} abandoned, stakes refunded to both players", game.game_id);
+            return Ok(());
+        };
+
         // Nothing staked → nothing to pay out.
         if game.stake == 0 {
             return Ok(());
@@ -469,20 +888,50 @@ pub mod anchor_rock_paper_scissor {
         let game_id_bytes = game.game_id.to_le_bytes();
         let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &game_id_bytes, &[ctx.bumps.vault]]];
 
+        let vault = StakeVault::new(
+            game,
+            &ctx.accounts.vault,
+            ctx.accounts.vault_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            &ctx.accounts.system_program,
+        )?;
         let to = if winner == player1 {
-            ctx.accounts.player1.to_account_info()
+            vault.account_of(
+                ctx.accounts.player1.to_account_info(),
+                ctx.accounts.player1_token_account.as_ref(),
+            )?
         } else {
-            ctx.accounts.player2.to_account_info()
+            vault.account_of(
+                ctx.accounts.player2.to_account_info(),
+                ctx.accounts.player2_token_account.as_ref(),
+            )?
         };
         let pot = game.stake.checked_mul(2).ok_or(GameError::MathOverflow)?;
-        pay_from_vault(
-            &ctx.accounts.vault,
-            &to,
-            pot,
-            &ctx.accounts.system_program,
-            signer_seeds,
-        )?;
-        msg!("Paid pot of {} lamports to match winner {}", pot, winner);
+        let (fee, fee_recipient) = house_fee(&ctx.accounts.config, pot)?;
+        if fee > 0 {
+            let fee_recipient_wallet = ctx
+                .accounts
+                .fee_recipient
+                .as_ref()
+                .ok_or(GameError::WrongFeeRecipient)?;
+            require_keys_eq!(
+                fee_recipient_wallet.key(),
+                fee_recipient,
+                GameError::WrongFeeRecipient
+            );
+            let fee_to = vault.account_of(
+                fee_recipient_wallet.to_account_info(),
+                ctx.accounts.fee_recipient_token_account.as_ref(),
+            )?;
+            vault.pay(fee_to, fee, signer_seeds)?;
+        }
+        vault.pay(to, pot - fee, signer_seeds)?;
+        msg!(
+            "Paid pot of {} to match winner {} (house fee {})",
+            pot - fee,
+            winner,
+            fee
+        );
 
         Ok(())
     }
@@ -500,108 +949,466 @@ pub mod anchor_rock_paper_scissor {
         require!(game.player2.is_none(), GameError::CannotCancelStarted);
         require!(!game.paid, GameError::AlreadyPaid);
 
+        // A listed game leaves the lobby queue (no-op if it already expired
+        // and was pruned).
+        if game.listed {
+            let lobby = ctx.accounts.lobby.as_mut().ok_or(GameError::MissingLobby)?;
+            lobby.remove(game.game_id);
+            game.listed = false;
+        }
+
         game.paid = true;
 
         if game.stake > 0 {
             let game_id_bytes = game.game_id.to_le_bytes();
             let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &game_id_bytes, &[ctx.bumps.vault]]];
-            pay_from_vault(
+            let vault = StakeVault::new(
+                game,
                 &ctx.accounts.vault,
-                &ctx.accounts.player1.to_account_info(),
-                game.stake,
+                ctx.accounts.vault_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
                 &ctx.accounts.system_program,
-                signer_seeds,
             )?;
+            let to = vault.account_of(
+                ctx.accounts.player1.to_account_info(),
+                ctx.accounts.player1_token_account.as_ref(),
+            )?;
+            vault.pay(to, game.stake, signer_seeds)?;
         }
         msg!("Game {} cancelled, stake refunded to creator", game.game_id);
         Ok(())
     }
 
-    /// Delegate account to the delegation program based on account type
-    /// Set specific validator based on ER, see https://docs.magicblock.gg/pages/get-started/how-integrate-your-program/local-setup
-    pub fn delegate_pda(ctx: Context<DelegatePda>, account_type: AccountType) -> Result<()> {
-        let seed_data = derive_seeds_from_account_type(&account_type);
-        let seeds_refs: Vec<&[u8]> = seed_data.iter().map(|s| s.as_slice()).collect();
-
-        let validator = ctx.accounts.validator.as_ref().map(|v| v.key());
-        ctx.accounts.delegate_pda(
-            &ctx.accounts.payer,
-            &seeds_refs,
-            DelegateConfig {
-                validator,
-                ..Default::default()
-            },
-        )?;
+    // Create the global leaderboard. Anyone can pay for it, once; from then on
+    // `claim_pot` keeps it updated whenever it's passed in.
+    pub fn init_leaderboard(ctx: Context<InitLeaderboard>) -> Result<()> {
+        ctx.accounts.leaderboard.entries = Vec::new();
+        msg!("Leaderboard created (top {})", LEADERBOARD_SIZE);
         Ok(())
     }
 
-    /// Create an ephemeral permission for a delegated account directly on the ER.
-    /// The `permissioned_account` PDA is both `payer` and `permissioned_account` —
-    /// it covers its own rent from the lamports pre-funded at `create_game` /
-    /// `join_game` time, and signs the CPI via its seeds derived from `account_type`.
-    /// Idempotent: skips if the permission already exists. `members = Some(vec)` →
-    /// private with that member list; `members = None` → public.
-    pub fn init_permission(
-        ctx: Context<PermissionContextRps>,
-        account_type: AccountType,
-        members: Option<Vec<Member>>,
-    ) -> Result<()> {
-        if ctx.accounts.permission.lamports() > 0 {
-            msg!("Permission already exists, skipping creation");
-            return Ok(());
-        }
-
-        let seed_data = derive_seeds_from_account_type(&account_type);
-        let (_, bump) = Pubkey::find_program_address(
-            &seed_data.iter().map(|s| s.as_slice()).collect::<Vec<_>>(),
-            &crate::ID,
-        );
-        let mut seeds = seed_data.clone();
-        seeds.push(vec![bump]);
-        let seed_refs: Vec<&[u8]> = seeds.iter().map(|s| s.as_slice()).collect();
+    // Set the house fee taken from every pot (`claim_pot` and rematch
+    // settlements; `cancel_game` refunds are exempt) and where it goes. Only the
+    // program's upgrade authority can change it.
+    pub fn set_config(ctx: Context<SetConfig>, fee_bps: u16, fee_recipient: Pubkey) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, GameError::FeeTooHigh);
+        let config = &mut ctx.accounts.config;
+        config.fee_bps = fee_bps;
+        config.fee_recipient = fee_recipient;
+        msg!("House fee set to {} bps, paid to {}", fee_bps, fee_recipient);
+        Ok(())
+    }
 
-        let (is_private, member_list) = match members {
-            Some(m) => (true, m),
-            None => (false, vec![]),
+    // Staked rematch, step 1 (base layer, while the game is still delegated):
+    // each player deposits the next match's stake into the vault, recorded on
+    // the game's `RematchEscrow`. The delegated `Game` isn't owned by this
+    // program on the base layer, so its last committed state is read by hand —
+    // the stake and players never change after `join_game`.
+    pub fn fund_rematch(ctx: Context<FundRematch>, _game_id: u64) -> Result<()> {
+        let game = Game::try_deserialize(&mut &ctx.accounts.game.try_borrow_data()?[..])?;
+        let player = ctx.accounts.player.key();
+        require!(game.stake > 0, GameError::NothingStaked);
+        require!(game.stake_mint.is_none(), GameError::RematchNeedsLamports);
+
+        let escrow = &mut ctx.accounts.rematch_escrow;
+        escrow.game_id = game.game_id;
+        let funded = if game.player1 == Some(player) {
+            &mut escrow.player1_funded
+        } else if game.player2 == Some(player) {
+            &mut escrow.player2_funded
+        } else {
+            return err!(GameError::NotAPlayer);
         };
+        require!(!*funded, GameError::RematchAlreadyFunded);
+        *funded = true;
+        ctx.accounts.player_stats.ensure_initialized(player);
 
-        CreateEphemeralPermissionCpi {
-            payer: ctx.accounts.permissioned_account.to_account_info(),
-            permissioned_account: ctx.accounts.permissioned_account.to_account_info(),
-            permission: ctx.accounts.permission.to_account_info(),
-            vault: ctx.accounts.ephemeral_vault.to_account_info(),
-            magic_program: ctx.accounts.magic_program.to_account_info(),
-            permission_program: ctx.accounts.permission_program.to_account_info(),
-            args: EphemeralMembersArgs {
-                is_private,
-                members: member_list,
-            },
-        }
-        .invoke_signed(&[seed_refs.as_slice()])?;
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.key(),
+                Transfer {
+                    from: ctx.accounts.player.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                },
+            ),
+            game.stake,
+        )?;
+        msg!(
+            "{} funded a rematch of game {} with {} lamports",
+            player,
+            game.game_id,
+            game.stake
+        );
         Ok(())
     }
-}
-
-#[derive(Accounts)]
-#[instruction(game_id: u64)]
-pub struct CreateGame<'info> {
-    #[account(
-        init_if_needed,
-        payer = player1,
-        space = 8 + Game::LEN,
-        seeds = [GAME_SEED, &game_id.to_le_bytes()],
-        bump
-    )]
-    pub game: Account<'info, Game>,
 
-    #[account(
-        init_if_needed,
-        payer = player1,
-        space = 8 + PlayerChoice::LEN,
-        seeds = [PLAYER_CHOICE_SEED, &game_id.to_le_bytes(), player1.key().as_ref()],
-        bump
-    )]
-    pub player_choice: Account<'info, PlayerChoice>,
+    // Staked rematch, step 2 (ER): once the match is decided and both players
+    // have funded the next one, commit the game with a post-commit action that
+    // pays the finished match's pot on the base layer (`settle_rematch`). The
+    // PDAs stay delegated; `next_round` starts the new match once the
+    // settlement is visible on the escrow.
+    pub fn rematch(ctx: Context<Rematch>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let escrow = &ctx.accounts.rematch_escrow;
+        let winner = game.match_winner().ok_or(GameError::MatchNotDecided)?;
+        let payer = ctx.accounts.payer.key();
+        require!(
+            game.player1 == Some(payer) || game.player2 == Some(payer),
+            GameError::NotAPlayer
+        );
+        require!(game.stake > 0, GameError::NothingStaked);
+        require!(game.tournament.is_none(), GameError::MustClaimFirst);
+        require!(!game.rematch_ready, GameError::RematchPending);
+        // Every earlier match is settled, and both deposits are for this one.
+        require!(
+            escrow.settled_match + 1 == game.match_number,
+            GameError::RematchNotSettled
+        );
+        require!(
+            escrow.player1_funded && escrow.player2_funded,
+            GameError::RematchNotFunded
+        );
+        require_keys_eq!(
+            ctx.accounts.winner.key(),
+            winner,
+            GameError::WrongPlayerAccount
+        );
+        // A bad account would fail the action and strand the rematch.
+        for (stats, player) in [
+            (&ctx.accounts.player1_stats, game.player1),
+            (&ctx.accounts.player2_stats, game.player2),
+        ] {
+            let player = player.ok_or(GameError::MissingOpponent)?;
+            let (expected, _) =
+                Pubkey::find_program_address(&[PLAYER_STATS_SEED, player.as_ref()], &crate::ID);
+            require_keys_eq!(stats.key(), expected, GameError::WrongPlayerAccount);
+        }
+        let pot = game.stake.checked_mul(2).ok_or(GameError::MathOverflow)?;
+        let (fee, fee_recipient) = house_fee(&ctx.accounts.config, pot)?;
+        if fee > 0 {
+            require_keys_eq!(
+                ctx.accounts.fee_recipient.key(),
+                fee_recipient,
+                GameError::WrongFeeRecipient
+            );
+        }
+        game.rematch_ready = true;
+        game.exit(&crate::ID)?;
+
+        let action = CallHandler {
+            destination_program: crate::ID,
+            accounts: vec![
+                ShortAccountMeta {
+                    pubkey: escrow.key().to_bytes().into(),
+                    is_writable: true,
+                },
+                ShortAccountMeta {
+                    pubkey: game.key().to_bytes().into(),
+                    is_writable: false,
+                },
+                ShortAccountMeta {
+                    pubkey: ctx.accounts.vault.key().to_bytes().into(),
+                    is_writable: true,
+                },
+                ShortAccountMeta {
+                    pubkey: winner.to_bytes().into(),
+                    is_writable: true,
+                },
+                ShortAccountMeta {
+                    pubkey: ctx.accounts.config.key().to_bytes().into(),
+                    is_writable: false,
+                },
+                ShortAccountMeta {
+                    pubkey: ctx.accounts.fee_recipient.key().to_bytes().into(),
+                    is_writable: true,
+                },
+                ShortAccountMeta {
+                    pubkey: ctx.accounts.player1_stats.key().to_bytes().into(),
+                    is_writable: true,
+                },
+                ShortAccountMeta {
+                    pubkey: ctx.accounts.player2_stats.key().to_bytes().into(),
+                    is_writable: true,
+                },
+                ShortAccountMeta {
+                    pubkey: ctx.accounts.system_program.key().to_bytes().into(),
+                    is_writable: false,
+                },
+            ],
+            args: ActionArgs::new(anchor_lang::InstructionData::data(
+                &crate::instruction::SettleRematch {},
+            )),
+            // Signer that pays transaction fees for the action from its escrow PDA
+            escrow_authority: ctx.accounts.payer.to_account_info(),
+            compute_units: 200_000,
+        };
+
+        MagicIntentBundleBuilder::new(
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.magic_context.to_account_info(),
+            ctx.accounts.magic_program.to_account_info(),
+        )
+        .commit(&[game.to_account_info()])
+        .add_post_commit_actions([action])
+        .build_and_invoke()?;
+
+        msg!(
+            "Rematch of game {} scheduled; match {} pot goes to {}",
+            game.game_id,
+            game.match_number,
+            winner
+        );
+        Ok(())
+    }
+
+    // Staked rematch, step 3 (base layer, run by the `rematch` action): pay the
+    // finished match's pot to its winner, record the match on both players'
+    // stats, and consume both deposits, which now back the next match's pot.
+    pub fn settle_rematch(ctx: Context<SettleRematch>) -> Result<()> {
+        let game = Game::try_deserialize(&mut &ctx.accounts.game.try_borrow_data()?[..])?;
+        let escrow = &mut ctx.accounts.rematch_escrow;
+        require!(game.rematch_ready, GameError::RematchNotFunded);
+        require!(
+            escrow.settled_match + 1 == game.match_number,
+            GameError::RematchNotSettled
+        );
+        let winner = game.match_winner().ok_or(GameError::MatchNotDecided)?;
+        require_keys_eq!(
+            ctx.accounts.winner.key(),
+            winner,
+            GameError::WrongPlayerAccount
+        );
+
+        escrow.settled_match = game.match_number;
+        escrow.player1_funded = false;
+        escrow.player2_funded = false;
+
+        // Both stats exist: each player created theirs in `fund_rematch`. The
+        // leaderboard catches up on the game's next `claim_pot`.
+        let player1_stats = &mut ctx.accounts.player1_stats;
+        let player2_stats = &mut ctx.accounts.player2_stats;
+        require!(
+            game.player1 == Some(player1_stats.player)
+                && game.player2 == Some(player2_stats.player),
+            GameError::WrongPlayerAccount
+        );
+        let tied_rounds = game.tied_rounds as u32;
+        if game.player1 == Some(winner) {
+            record_result(&game, player1_stats, player2_stats, tied_rounds);
+        } else {
+            record_result(&game, player2_stats, player1_stats, tied_rounds);
+        }
+
+        let game_id_bytes = game.game_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &game_id_bytes, &[ctx.bumps.vault]]];
+        let pot = game.stake.checked_mul(2).ok_or(GameError::MathOverflow)?;
+        // Rematched pots pay the same house fee as `claim_pot`.
+        let (fee, fee_recipient) = house_fee(&ctx.accounts.config, pot)?;
+        if fee > 0 {
+            require_keys_eq!(
+                ctx.accounts.fee_recipient.key(),
+                fee_recipient,
+                GameError::WrongFeeRecipient
+            );
+            pay_from_vault(
+                &ctx.accounts.vault,
+                &ctx.accounts.fee_recipient.to_account_info(),
+                fee,
+                &ctx.accounts.system_program,
+                signer_seeds,
+            )?;
+        }
+        pay_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.winner.to_account_info(),
+            pot - fee,
+            &ctx.accounts.system_program,
+            signer_seeds,
+        )?;
+        msg!(
+            "Settled match {} of game {}: {} lamports to {} (house fee {})",
+            game.match_number,
+            game.game_id,
+            pot - fee,
+            winner,
+            fee
+        );
+        Ok(())
+    }
+
+    // Return rematch deposits that never turned into a match. Base layer, once
+    // the game is undelegated and its last match claimed (or cancelled).
+    pub fn refund_rematch(ctx: Context<RefundRematch>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        require!(game.paid, GameError::GameNotSettled);
+        require_keys_eq!(
+            ctx.accounts.player1.key(),
+            game.player1.ok_or(GameError::MissingOpponent)?,
+            GameError::WrongPlayerAccount
+        );
+        require_keys_eq!(
+            ctx.accounts.player2.key(),
+            game.player2.ok_or(GameError::MissingOpponent)?,
+            GameError::WrongPlayerAccount
+        );
+
+        let game_id_bytes = game.game_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &game_id_bytes, &[ctx.bumps.vault]]];
+        let escrow = &mut ctx.accounts.rematch_escrow;
+        let funded = [escrow.player1_funded, escrow.player2_funded];
+        escrow.player1_funded = false;
+        escrow.player2_funded = false;
+        for (funded, to) in funded
+            .into_iter()
+            .zip([&ctx.accounts.player1, &ctx.accounts.player2])
+        {
+            if funded {
+                pay_from_vault(
+                    &ctx.accounts.vault,
+                    &to.to_account_info(),
+                    game.stake,
+                    &ctx.accounts.system_program,
+                    signer_seeds,
+                )?;
+            }
+        }
+        msg!("Rematch deposits for game {} refunded", game.game_id);
+        Ok(())
+    }
+
+    // Tournaments — see `tournament.rs`. Create a 4/8/16-entrant bracket with an
+    // entry fee, best-of length and prize split (champion, runner-up,
+    // semi-finalists in bps).
+    pub fn create_tournament(
+        ctx: Context<CreateTournament>,
+        tournament_id: u64,
+        size: u8,
+        entry_fee: u64,
+        target_wins: u8,
+        prize_split_bps: [u16; 3],
+    ) -> Result<()> {
+        ctx.accounts.create(tournament_id, size, entry_fee, target_wins, prize_split_bps)
+    }
+
+    // Pay the entry fee and take the next bracket slot; the last entrant
+    // starts round 1.
+    pub fn register_entrant(ctx: Context<RegisterEntrant>) -> Result<()> {
+        ctx.accounts.register()
+    }
+
+    // Withdraw (with refund) while registration is still open.
+    pub fn leave_tournament(ctx: Context<LeaveTournament>) -> Result<()> {
+        ctx.accounts.leave(ctx.bumps.vault)
+    }
+
+    // Create the current round's bracket game `match_index` with both players
+    // seated. Permissionless; delegate and play it like any other game.
+    pub fn spawn_match(ctx: Context<SpawnMatch>, match_index: u8) -> Result<()> {
+        ctx.accounts.spawn(match_index)
+    }
+
+    // Advance the winner of an undelegated, decided bracket game (the higher
+    // seed if it was abandoned).
+    pub fn report_match(ctx: Context<ReportMatch>, match_index: u8) -> Result<()> {
+        ctx.accounts.report(match_index)
+    }
+
+    // Past the round deadline, advance the higher seed of a match that still
+    // hasn't been reported, so a stalled game can't lock the prize pool.
+    pub fn advance_stalled_match(ctx: Context<AdvanceStalledMatch>, match_index: u8) -> Result<()> {
+        ctx.accounts.advance(match_index)
+    }
+
+    // Split the prize pool once the final has been reported.
+    pub fn claim_prizes(ctx: Context<ClaimPrizes>) -> Result<()> {
+        ctx.accounts.pay(ctx.bumps.vault)
+    }
+
+    /// Delegate account to the delegation program based on account type
+    /// Set specific validator based on ER, see https://docs.magicblock.gg/pages/get-started/how-integrate-your-program/local-setup
+    pub fn delegate_pda(ctx: Context<DelegatePda>, account_type: AccountType) -> Result<()> {
+        let seed_data = derive_seeds_from_account_type(&account_type);
+        let seeds_refs: Vec<&[u8]> = seed_data.iter().map(|s| s.as_slice()).collect();
+
+        let validator = ctx.accounts.validator.as_ref().map(|v| v.key());
+        ctx.accounts.delegate_pda(
+            &ctx.accounts.payer,
+            &seeds_refs,
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Create an ephemeral permission for a delegated account directly on the ER.
+    /// The `permissioned_account` PDA is both `payer` and `permissioned_account` —
+    /// it covers its own rent from the lamports pre-funded at `create_game` /
+    /// `join_game` time, and signs the CPI via its seeds derived from `account_type`.
+    /// Idempotent: skips if the permission already exists. `members = Some(vec)` →
+    /// private with that member list; `members = None` → public.
+    pub fn init_permission(
+        ctx: Context<PermissionContextRps>,
+        account_type: AccountType,
+        members: Option<Vec<Member>>,
+    ) -> Result<()> {
+        if ctx.accounts.permission.lamports() > 0 {
+            msg!("Permission already exists, skipping creation");
+            return Ok(());
+        }
+
+        let seed_data = derive_seeds_from_account_type(&account_type);
+        let (_, bump) = Pubkey::find_program_address(
+            &seed_data.iter().map(|s| s.as_slice()).collect::<Vec<_>>(),
+            &crate::ID,
+        );
+        let mut seeds = seed_data.clone();
+        seeds.push(vec![bump]);
+        let seed_refs: Vec<&[u8]> = seeds.iter().map(|s| s.as_slice()).collect();
+
+        let (is_private, member_list) = match members {
+            Some(m) => (true, m),
+            None => (false, vec![]),
+        };
+
+        CreateEphemeralPermissionCpi {
+            payer: ctx.accounts.permissioned_account.to_account_info(),
+            permissioned_account: ctx.accounts.permissioned_account.to_account_info(),
+            permission: ctx.accounts.permission.to_account_info(),
+            vault: ctx.accounts.ephemeral_vault.to_account_info(),
+            magic_program: ctx.accounts.magic_program.to_account_info(),
+            permission_program: ctx.accounts.permission_program.to_account_info(),
+            args: EphemeralMembersArgs {
+                is_private,
+                members: member_list,
+            },
+        }
+        .invoke_signed(&[seed_refs.as_slice()])?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: u64)]
+pub struct CreateGame<'info> {
+    #[account(
+        init_if_needed,
+        payer = player1,
+        space = 8 + Game::LEN,
+        seeds = [GAME_SEED, &game_id.to_le_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    #[account(
+        init_if_needed,
+        payer = player1,
+        space = 8 + PlayerChoice::LEN,
+        seeds = [PLAYER_CHOICE_SEED, &game_id.to_le_bytes(), player1.key().as_ref()],
+        bump
+    )]
+    pub player_choice: Account<'info, PlayerChoice>,
 
     /// CHECK: SOL escrow PDA, system-owned, holds the pot. Validated by seeds.
     #[account(mut, seeds = [VAULT_SEED, &game_id.to_le_bytes()], bump)]
@@ -609,6 +1416,23 @@ pub struct CreateGame<'info> {
 
     #[account(mut)]
     pub player1: Signer<'info>,
+
+    /// Mint of the wager for token games; omit it for a lamport game.
+    pub stake_mint: Option<Account<'info, Mint>>,
+    /// Player 1's token account for the stake mint (token games only).
+    #[account(mut)]
+    pub player_token_account: Option<Account<'info, TokenAccount>>,
+    /// The vault PDA's associated token account for the stake mint, created
+    /// on first use (token games only).
+    #[account(
+        init_if_needed,
+        payer = player1,
+        associated_token::mint = stake_mint,
+        associated_token::authority = vault
+    )]
+    pub vault_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
     pub system_program: Program<'info, System>,
 }
 
@@ -635,8 +1459,28 @@ pub struct JoinGame<'info> {
     #[account(mut, seeds = [VAULT_SEED, &game_id.to_le_bytes()], bump)]
     pub vault: SystemAccount<'info>,
 
+    /// Required only if the game is listed, to check its entry has expired.
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &game.stake.to_le_bytes(), game.lobby_mint().as_ref()],
+        bump
+    )]
+    pub lobby: Option<Account<'info, Lobby>>,
+
     #[account(mut)]
     pub player: Signer<'info>,
+
+    /// Player 2's token account for the game's stake mint (token games only).
+    #[account(mut)]
+    pub player_token_account: Option<Account<'info, TokenAccount>>,
+    /// The vault PDA's associated token account for the stake mint (token games only).
+    #[account(
+        mut,
+        associated_token::mint = game.lobby_mint(),
+        associated_token::authority = vault
+    )]
+    pub vault_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
     pub system_program: Program<'info, System>,
 }
 
@@ -650,10 +1494,40 @@ pub struct MakeChoice<'info> {
     )]
     pub player_choice: Account<'info, PlayerChoice>,
 
+    #[account(seeds = [GAME_SEED, &game_id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    /// Required when the game plays a custom rule table.
+    pub rule_table: Option<Account<'info, RuleTable>>,
+
     #[account(mut)]
     pub player: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct SetVariant<'info> {
+    #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    /// Required when selecting a custom rule table.
+    pub rule_table: Option<Account<'info, RuleTable>>,
+    pub player1: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(table_id: u64)]
+pub struct CreateRuleTable<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RuleTable::LEN,
+        seeds = [RULE_TABLE_SEED, authority.key().as_ref(), &table_id.to_le_bytes()],
+        bump
+    )]
+    pub rule_table: Account<'info, RuleTable>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct RevealRound<'info> {
     #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
@@ -674,6 +1548,8 @@ pub struct RevealRound<'info> {
         bump
     )]
     pub player2_choice: Account<'info, PlayerChoice>,
+    /// Required when the game plays a custom rule table.
+    pub rule_table: Option<Account<'info, RuleTable>>,
     /// CHECK: Checked by the permission program
     #[account(mut)]
     pub permission_game: UncheckedAccount<'info>,
@@ -713,25 +1589,306 @@ pub struct NextRound<'info> {
     )]
     pub player1_choice: Account<'info, PlayerChoice>,
 
-    /// Player2's choice PDA (derived automatically)
+    /// Player2's choice PDA (derived automatically)
+    #[account(
+        mut,
+        seeds = [PLAYER_CHOICE_SEED, &game.game_id.to_le_bytes(), game.player2.unwrap().as_ref()],
+        bump
+    )]
+    pub player2_choice: Account<'info, PlayerChoice>,
+    /// Required to start a staked rematch (read-only clone on the ER).
+    #[account(seeds = [REMATCH_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub rematch_escrow: Option<Account<'info, RematchEscrow>>,
+    /// CHECK: Checked by the permission program
+    #[account(mut)]
+    pub permission_game: UncheckedAccount<'info>,
+    /// CHECK: Checked by the permission program
+    #[account(mut)]
+    pub permission1: UncheckedAccount<'info>,
+    /// CHECK: Checked by the permission program
+    #[account(mut)]
+    pub permission2: UncheckedAccount<'info>,
+    /// Must be one of the two players (checked in the handler)
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: PERMISSION PROGRAM
+    #[account(address = PERMISSION_PROGRAM_ID)]
+    pub permission_program: UncheckedAccount<'info>,
+    /// CHECK: verified by magic program
+    #[account(mut, address = EPHEMERAL_VAULT_ID)]
+    pub ephemeral_vault: UncheckedAccount<'info>,
+    /// CHECK: Magic Program
+    #[account(address = MAGIC_PROGRAM_ID)]
+    pub magic_program: UncheckedAccount<'info>,
+}
+
+/// Context for `claim_forfeit` — runs on the ER. Only reads whether each
+/// choice PDA holds a move; the choices themselves are never copied out.
+#[derive(Accounts)]
+pub struct ClaimForfeit<'info> {
+    #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    #[account(
+        seeds = [PLAYER_CHOICE_SEED, &game.game_id.to_le_bytes(), game.player1.unwrap().as_ref()],
+        bump
+    )]
+    pub player1_choice: Account<'info, PlayerChoice>,
+    #[account(
+        seeds = [PLAYER_CHOICE_SEED, &game.game_id.to_le_bytes(), game.player2.unwrap().as_ref()],
+        bump
+    )]
+    pub player2_choice: Account<'info, PlayerChoice>,
+    /// Anyone can trigger this
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+/// Context for `undelegate_all` — commits + undelegates game + both player_choice
+/// PDAs in a single magic-intent bundle. `#[commit]` auto-adds `magic_context` and
+/// `magic_program`. Player addresses are derived from the game's stored state.
+#[commit]
+#[derive(Accounts)]
+pub struct UndelegateAll<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    #[account(
+        mut,
+        seeds = [PLAYER_CHOICE_SEED, &game.game_id.to_le_bytes(), game.player1.unwrap().as_ref()],
+        bump,
+    )]
+    pub player1_choice: Account<'info, PlayerChoice>,
+    #[account(
+        mut,
+        seeds = [PLAYER_CHOICE_SEED, &game.game_id.to_le_bytes(), game.player2.unwrap().as_ref()],
+        bump,
+    )]
+    pub player2_choice: Account<'info, PlayerChoice>,
+}
+
+/// Context for `fund_rematch` — base layer, while the game is delegated.
+#[derive(Accounts)]
+#[instruction(game_id: u64)]
+pub struct FundRematch<'info> {
+    /// CHECK: the delegated Game PDA (owned by the delegation program while
+    /// delegated); validated by seeds and deserialized in the handler.
+    #[account(seeds = [GAME_SEED, &game_id.to_le_bytes()], bump)]
+    pub game: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = 8 + RematchEscrow::LEN,
+        seeds = [REMATCH_SEED, &game_id.to_le_bytes()],
+        bump
+    )]
+    pub rematch_escrow: Account<'info, RematchEscrow>,
+    /// CHECK: SOL escrow PDA, system-owned, holds the pot. Validated by seeds.
+    #[account(mut, seeds = [VAULT_SEED, &game_id.to_le_bytes()], bump)]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    /// Created here if needed, so `settle_rematch` can record the match.
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = 8 + PlayerStats::LEN,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for `rematch` — ER; commits the game with the `settle_rematch`
+/// post-commit action. The vault, winner and system program are only passed
+/// through to the action.
+#[commit]
+#[derive(Accounts)]
+pub struct Rematch<'info> {
+    /// Must be one of the two players; pays for the action from its escrow.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    #[account(seeds = [REMATCH_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub rematch_escrow: Account<'info, RematchEscrow>,
+    /// CHECK: SOL escrow PDA, written by the action on the base layer. Validated by seeds.
+    #[account(seeds = [VAULT_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: the finished match's winner, verified in the handler.
+    pub winner: UncheckedAccount<'info>,
+    /// CHECK: house-fee config PDA (may not exist yet); read by the action.
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: house-fee recipient, verified against the config when a fee is due.
+    pub fee_recipient: UncheckedAccount<'info>,
+    /// CHECK: the players' base-layer PlayerStats PDAs, written by the action;
+    /// verified in the handler.
+    pub player1_stats: UncheckedAccount<'info>,
+    /// CHECK: see `player1_stats`.
+    pub player2_stats: UncheckedAccount<'info>,
+    /// CHECK: Your program ID
+    pub program_id: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for `settle_rematch` — the post-commit action scheduled by
+/// `rematch`, executed on the base layer.
+#[action]
+#[derive(Accounts)]
+pub struct SettleRematch<'info> {
+    #[account(mut, seeds = [REMATCH_SEED, &rematch_escrow.game_id.to_le_bytes()], bump)]
+    pub rematch_escrow: Account<'info, RematchEscrow>,
+    /// CHECK: PDA owner depends on: 1) Delegated: Delegation Program; 2) Undelegated: Your program ID
+    #[account(seeds = [GAME_SEED, &rematch_escrow.game_id.to_le_bytes()], bump)]
+    pub game: UncheckedAccount<'info>,
+    /// CHECK: SOL escrow PDA, system-owned. Validated by seeds.
+    #[account(mut, seeds = [VAULT_SEED, &rematch_escrow.game_id.to_le_bytes()], bump)]
+    pub vault: SystemAccount<'info>,
+    /// CHECK: payout recipient, verified against the game's match winner.
+    #[account(mut)]
+    pub winner: UncheckedAccount<'info>,
+    /// CHECK: house-fee config PDA; no fee until it's initialized.
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: house-fee recipient, verified against the config when a fee is due.
+    #[account(mut)]
+    pub fee_recipient: UncheckedAccount<'info>,
+    /// Both players' stats, created by `fund_rematch`; checked against the
+    /// game's players in the handler.
+    #[account(mut, seeds = [PLAYER_STATS_SEED, player1_stats.player.as_ref()], bump)]
+    pub player1_stats: Account<'info, PlayerStats>,
+    #[account(mut, seeds = [PLAYER_STATS_SEED, player2_stats.player.as_ref()], bump)]
+    pub player2_stats: Account<'info, PlayerStats>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for `refund_rematch` — base layer, after the game is undelegated.
+#[derive(Accounts)]
+pub struct RefundRematch<'info> {
+    #[account(seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [REMATCH_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub rematch_escrow: Account<'info, RematchEscrow>,
+    /// CHECK: SOL escrow PDA, system-owned. Validated by seeds.
+    #[account(mut, seeds = [VAULT_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub vault: SystemAccount<'info>,
+    /// CHECK: refund recipient, verified against game.player1 in the handler.
+    #[account(mut)]
+    pub player1: UncheckedAccount<'info>,
+    /// CHECK: refund recipient, verified against game.player2 in the handler.
+    #[account(mut)]
+    pub player2: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for `claim_pot` — base layer, after the game is undelegated. The
+/// vault signs its own payout via seeds; both player accounts are passed so the
+/// winner (or both, on a tie) can be paid.
+#[derive(Accounts)]
+pub struct ClaimPot<'info> {
+    #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    /// CHECK: SOL escrow PDA, system-owned. Validated by seeds.
+    #[account(mut, seeds = [VAULT_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub vault: SystemAccount<'info>,
+    /// Only needed to claim a match whose `rematch` action never settled it.
+    #[account(seeds = [REMATCH_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub rematch_escrow: Option<Account<'info, RematchEscrow>>,
+    /// CHECK: payout recipient, verified against game.player1 in the handler.
+    #[account(mut)]
+    pub player1: UncheckedAccount<'info>,
+    /// CHECK: payout recipient, verified against game.player2 in the handler.
+    #[account(mut)]
+    pub player2: UncheckedAccount<'info>,
+    /// Anyone can trigger the payout.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Token games pay into the winner's token account instead of their wallet.
+    #[account(mut)]
+    pub player1_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub player2_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = game.lobby_mint(),
+        associated_token::authority = vault
+    )]
+    pub vault_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    /// Lifetime stats, created on each player's first settled match.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PlayerStats::LEN,
+        seeds = [PLAYER_STATS_SEED, player1.key().as_ref()],
+        bump
+    )]
+    pub player1_stats: Account<'info, PlayerStats>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PlayerStats::LEN,
+        seeds = [PLAYER_STATS_SEED, player2.key().as_ref()],
+        bump
+    )]
+    pub player2_stats: Account<'info, PlayerStats>,
+    /// Optional: pass it to keep the global top-N up to date.
+    #[account(mut, seeds = [LEADERBOARD_SEED], bump)]
+    pub leaderboard: Option<Account<'info, Leaderboard>>,
+    /// CHECK: house-fee config PDA; no fee until `set_config` has run. Pinned by
+    /// seeds so the fee can't be skipped.
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: house-fee recipient, verified against the config when a fee is due.
+    #[account(mut)]
+    pub fee_recipient: Option<UncheckedAccount<'info>>,
+    /// The fee recipient's token account, for token games.
+    #[account(mut)]
+    pub fee_recipient_token_account: Option<Account<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for `set_config` — gated on the program's upgrade authority.
+#[derive(Accounts)]
+pub struct SetConfig<'info> {
+    #[account(mut, constraint = authority.key() == program_data.upgrade_authority_address.ok_or(ProgramError::InvalidArgument)?)]
+    pub authority: Signer<'info>,
+    /// CHECK: Program data account to verify upgrade authority
+    #[account(seeds = [crate::ID.as_ref()], bump, seeds::program = bpf_loader_upgradeable::ID)]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(init_if_needed, payer = authority, space = 8 + Config::LEN, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitLeaderboard<'info> {
     #[account(
-        mut,
-        seeds = [PLAYER_CHOICE_SEED, &game.game_id.to_le_bytes(), game.player2.unwrap().as_ref()],
+        init,
+        payer = payer,
+        space = 8 + Leaderboard::LEN,
+        seeds = [LEADERBOARD_SEED],
         bump
     )]
-    pub player2_choice: Account<'info, PlayerChoice>,
-    /// CHECK: Checked by the permission program
-    #[account(mut)]
-    pub permission_game: UncheckedAccount<'info>,
-    /// CHECK: Checked by the permission program
+    pub leaderboard: Account<'info, Leaderboard>,
     #[account(mut)]
-    pub permission1: UncheckedAccount<'info>,
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for `add_spectator` / `remove_spectator`. The permission accounts
+/// are only used when the Game's ephemeral permission already exists (ER).
+#[derive(Accounts)]
+pub struct ManageSpectators<'info> {
+    #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    /// Only the creator manages spectators.
+    pub player1: Signer<'info>,
     /// CHECK: Checked by the permission program
     #[account(mut)]
-    pub permission2: UncheckedAccount<'info>,
-    /// Must be one of the two players (checked in the handler)
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    pub permission_game: UncheckedAccount<'info>,
     /// CHECK: PERMISSION PROGRAM
     #[account(address = PERMISSION_PROGRAM_ID)]
     pub permission_program: UncheckedAccount<'info>,
@@ -743,52 +1900,111 @@ pub struct NextRound<'info> {
     pub magic_program: UncheckedAccount<'info>,
 }
 
-/// Context for `undelegate_all` — commits + undelegates game + both player_choice
-/// PDAs in a single magic-intent bundle. `#[commit]` auto-adds `magic_context` and
-/// `magic_program`. Player addresses are derived from the game's stored state.
-#[commit]
+impl<'info> ManageSpectators<'info> {
+    /// Push the game's current member list to its permission if it exists and
+    /// is private (a round in progress). A revealed round's permission is
+    /// public until `next_round`, which applies the list itself.
+    fn sync_game_permission(&self, game_bump: u8) -> Result<()> {
+        let game = &self.game;
+        if self.permission_game.lamports() == 0 || game.round_result != RoundResult::None {
+            return Ok(());
+        }
+        // Persist the new list before the CPI reads the account.
+        game.exit(&crate::ID)?;
+        UpdateEphemeralPermissionCpi {
+            payer: game.to_account_info(),
+            permissioned_account: game.to_account_info(),
+            permission: self.permission_game.to_account_info(),
+            vault: self.ephemeral_vault.to_account_info(),
+            magic_program: self.magic_program.to_account_info(),
+            permission_program: self.permission_program.to_account_info(),
+            authority: game.to_account_info(),
+            authority_is_signer: false,
+            args: EphemeralMembersArgs {
+                is_private: true,
+                members: game.permission_members(),
+            },
+        }
+        .invoke_signed(&[&[GAME_SEED, &game.game_id.to_le_bytes(), &[game_bump]]])
+    }
+}
+
+/// Context for `list_game` — queues an unjoined game in its stake tier's lobby.
 #[derive(Accounts)]
-pub struct UndelegateAll<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
+pub struct ListGame<'info> {
     #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
     pub game: Account<'info, Game>,
+    #[account(
+        init_if_needed,
+        payer = player1,
+        space = 8 + Lobby::LEN,
+        seeds = [LOBBY_SEED, &game.stake.to_le_bytes(), game.lobby_mint().as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(mut)]
+    pub player1: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for `quick_join` — `join_game` plus the lobby the game is queued in.
+#[derive(Accounts)]
+#[instruction(game_id: u64)]
+pub struct QuickJoin<'info> {
     #[account(
         mut,
-        seeds = [PLAYER_CHOICE_SEED, &game.game_id.to_le_bytes(), game.player1.unwrap().as_ref()],
-        bump,
+        seeds = [GAME_SEED, &game_id.to_le_bytes()],
+        bump
     )]
-    pub player1_choice: Account<'info, PlayerChoice>,
+    pub game: Account<'info, Game>,
+
     #[account(
         mut,
-        seeds = [PLAYER_CHOICE_SEED, &game.game_id.to_le_bytes(), game.player2.unwrap().as_ref()],
-        bump,
+        seeds = [LOBBY_SEED, &game.stake.to_le_bytes(), game.lobby_mint().as_ref()],
+        bump
     )]
-    pub player2_choice: Account<'info, PlayerChoice>,
-}
+    pub lobby: Account<'info, Lobby>,
 
-/// Context for `claim_pot` — base layer, after the game is undelegated. The
-/// vault signs its own payout via seeds; both player accounts are passed so the
-/// winner (or both, on a tie) can be paid.
-#[derive(Accounts)]
-pub struct ClaimPot<'info> {
-    #[account(mut, seeds = [GAME_SEED, &game.game_id.to_le_bytes()], bump)]
-    pub game: Account<'info, Game>,
-    /// CHECK: SOL escrow PDA, system-owned. Validated by seeds.
-    #[account(mut, seeds = [VAULT_SEED, &game.game_id.to_le_bytes()], bump)]
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = 8 + PlayerChoice::LEN,
+        seeds = [PLAYER_CHOICE_SEED, &game_id.to_le_bytes(), player.key().as_ref()],
+        bump
+    )]
+    pub player_choice: Account<'info, PlayerChoice>,
+
+    /// CHECK: SOL escrow PDA, system-owned, holds the pot. Validated by seeds.
+    #[account(mut, seeds = [VAULT_SEED, &game_id.to_le_bytes()], bump)]
     pub vault: SystemAccount<'info>,
-    /// CHECK: payout recipient, verified against game.player1 in the handler.
-    #[account(mut)]
-    pub player1: UncheckedAccount<'info>,
-    /// CHECK: payout recipient, verified against game.player2 in the handler.
+
     #[account(mut)]
-    pub player2: UncheckedAccount<'info>,
-    /// Anyone can trigger the payout.
+    pub player: Signer<'info>,
+
+    /// Player 2's token account for the game's stake mint (token games only).
     #[account(mut)]
-    pub payer: Signer<'info>,
+    pub player_token_account: Option<Account<'info, TokenAccount>>,
+    /// The vault PDA's associated token account for the stake mint (token games only).
+    #[account(
+        mut,
+        associated_token::mint = game.lobby_mint(),
+        associated_token::authority = vault
+    )]
+    pub vault_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct PruneLobby<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &lobby.stake.to_le_bytes(), lobby.stake_mint.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+}
+
 /// Context for `cancel_game` — base layer refund of the creator's stake when
 /// no one joined.
 #[derive(Accounts)]
@@ -798,9 +2014,26 @@ pub struct CancelGame<'info> {
     /// CHECK: SOL escrow PDA, system-owned. Validated by seeds.
     #[account(mut, seeds = [VAULT_SEED, &game.game_id.to_le_bytes()], bump)]
     pub vault: SystemAccount<'info>,
+    /// Required only if the game is listed, so it can be taken off the queue.
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &game.stake.to_le_bytes(), game.lobby_mint().as_ref()],
+        bump
+    )]
+    pub lobby: Option<Account<'info, Lobby>>,
     /// Only the creator can cancel; refund goes back to them.
     #[account(mut)]
     pub player1: Signer<'info>,
+    /// Token games refund into the creator's token account.
+    #[account(mut)]
+    pub player1_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = game.lobby_mint(),
+        associated_token::authority = vault
+    )]
+    pub vault_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
     pub system_program: Program<'info, System>,
 }
 
@@ -850,25 +2083,97 @@ pub struct Game {
     pub player1_choice: Option<Choice>,
     pub player2_choice: Option<Choice>,
     pub round_result: RoundResult, // the CURRENT round's result
-    pub stake: u64,         // per-player wager in lamports (0 = free game)
+    pub stake: u64,         // per-player wager in lamports or stake-mint units (0 = free game)
+    pub stake_mint: Option<Pubkey>, // SPL mint of the wager (None = lamports)
     pub paid: bool,         // pot claimed / game cancelled
     pub target_wins: u8,    // round-wins needed to take the match (best-of-N)
     pub player1_wins: u8,   // match score
     pub player2_wins: u8,
     pub round: u8, // current round number, 1-based
+    pub round_deadline_ts: i64, // move deadline for the current round (0 = not started)
+    pub listed: bool,           // queued in its stake tier's matchmaking lobby
+    pub tied_rounds: u8,        // tied rounds replayed in the current match
+    pub tournament: Option<Pubkey>, // bracket this game belongs to, if any
+    pub variant: GameVariant,       // rule table the rounds are played under
+    pub match_number: u32,          // 1-based; bumped by each staked rematch
+    pub rematch_ready: bool,        // finished match's pot is paid by the rematch action
+    pub spectators: Vec<Pubkey>,    // read-only (TX_LOGS_FLAG) members of the Game permission
+    pub move_timeout_secs: u32,     // move window per round, from `create_game`
+    pub abandoned: bool,            // both players stalled; `claim_pot` refunds the stakes
+    pub unrecorded_wins: [u16; 2],  // free rematches won by player1/player2, not yet on their stats
+    pub unrecorded_ties: u32,       // tied rounds from those rematches
 }
 impl Game {
     pub const LEN: usize = 8                // game_id
         + (32 + 1) * 2                       // player1, player2
-        + (1 + 1) * 2                        // player1_choice, player2_choice
+        + (1 + 2) * 2                        // player1_choice, player2_choice
         + (1 + 32)                           // result (1 byte tag + 32 bytes pubkey for Winner variant)
         + 8                                  // stake
+        + (1 + 32)                           // stake_mint
         + 1                                  // paid
-        + 1 * 4; // target_wins, player1_wins, player2_wins, round
-
-    /// The match is over once a player reaches `target_wins` round-wins.
+        + 1 * 4                              // target_wins, player1_wins, player2_wins, round
+        + 8                                  // round_deadline_ts
+        + 1                                  // listed
+        + 1                                  // tied_rounds
+        + (1 + 32)                           // tournament
+        + (1 + 32)                           // variant
+        + 4                                  // match_number
+        + 1                                  // rematch_ready
+        + 4 + 32 * MAX_SPECTATORS            // spectators
+        + 4                                  // move_timeout_secs
+        + 1                                  // abandoned
+        + 2 * 2                              // unrecorded_wins
+        + 4; // unrecorded_ties
+
+    /// The match is over once a player reaches `target_wins` round-wins, or
+    /// once both players let a round expire.
     pub fn is_match_decided(&self) -> bool {
-        self.player1_wins >= self.target_wins || self.player2_wins >= self.target_wins
+        self.abandoned
+            || self.player1_wins >= self.target_wins
+            || self.player2_wins >= self.target_wins
+    }
+
+    /// Give both players `move_timeout_secs` from `now` to move this round.
+    pub fn start_round_clock(&mut self, now: i64) {
+        self.round_deadline_ts = now.saturating_add(self.move_timeout_secs as i64);
+    }
+
+    /// Park the decided match's result in `unrecorded_wins` / `unrecorded_ties`
+    /// before the score is reset for a free rematch.
+    pub fn defer_match_result(&mut self) -> Result<()> {
+        let winner = self.match_winner().ok_or(GameError::MatchNotDecided)?;
+        let slot = if self.player1 == Some(winner) { 0 } else { 1 };
+        self.unrecorded_wins[slot] = self.unrecorded_wins[slot].saturating_add(1);
+        self.unrecorded_ties = self.unrecorded_ties.saturating_add(self.tied_rounds as u32);
+        Ok(())
+    }
+
+    /// Whether the current round's move window has closed.
+    pub fn is_round_expired(&self, now: i64) -> bool {
+        self.round_deadline_ts > 0 && now >= self.round_deadline_ts
+    }
+
+    /// Members of the Game's private permission: both players with full
+    /// access, spectators with transaction-log read access only.
+    pub fn permission_members(&self) -> Vec<Member> {
+        let players = [self.player1, self.player2]
+            .into_iter()
+            .flatten()
+            .map(|pubkey| Member {
+                flags: AUTHORITY_FLAG | TX_LOGS_FLAG,
+                pubkey,
+            });
+        let spectators = self.spectators.iter().map(|pubkey| Member {
+            flags: TX_LOGS_FLAG,
+            pubkey: *pubkey,
+        });
+        players.chain(spectators).collect()
+    }
+
+    /// Mint component of this game's lobby seeds; lamport games use the
+    /// default pubkey.
+    pub fn lobby_mint(&self) -> Pubkey {
+        self.stake_mint.unwrap_or_default()
     }
 
     /// Pubkey of the match winner, or None if the match is still in progress.
@@ -883,6 +2188,140 @@ impl Game {
     }
 }
 
+/// Matchmaking queue for one stake tier (`stake` + `stake_mint`), oldest first.
+#[account]
+pub struct Lobby {
+    pub stake: u64,
+    pub stake_mint: Option<Pubkey>,
+    pub queue: Vec<LobbyEntry>,
+}
+impl Lobby {
+    pub const LEN: usize = 8 + (1 + 32) + 4 + LOBBY_CAPACITY * LobbyEntry::LEN;
+
+    /// Drop entries past `LOBBY_ENTRY_TTL_SECS`; returns how many were removed.
+    pub fn prune_expired(&mut self, now: i64) -> usize {
+        let before = self.queue.len();
+        self.queue.retain(|entry| !entry.is_expired(now));
+        before - self.queue.len()
+    }
+
+    /// Oldest queued game with `target_wins` and `variant` that `player`
+    /// didn't create.
+    pub fn oldest_open(
+        &self,
+        target_wins: u8,
+        variant: &GameVariant,
+        player: Pubkey,
+    ) -> Option<u64> {
+        self.queue
+            .iter()
+            .find(|entry| {
+                entry.target_wins == target_wins
+                    && entry.variant == *variant
+                    && entry.player1 != player
+            })
+            .map(|entry| entry.game_id)
+    }
+
+    /// Whether `game_id` is still queued.
+    pub fn contains(&self, game_id: u64) -> bool {
+        self.queue.iter().any(|entry| entry.game_id == game_id)
+    }
+
+    /// Remove `game_id` from the queue; returns whether it was there.
+    pub fn remove(&mut self, game_id: u64) -> bool {
+        let before = self.queue.len();
+        self.queue.retain(|entry| entry.game_id != game_id);
+        before != self.queue.len()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct LobbyEntry {
+    pub game_id: u64,
+    pub player1: Pubkey,
+    pub target_wins: u8,
+    pub variant: GameVariant,
+    pub listed_ts: i64,
+}
+impl LobbyEntry {
+    pub const LEN: usize = 8 + 32 + 1 + (1 + 32) + 8;
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.listed_ts.saturating_add(LOBBY_ENTRY_TTL_SECS)
+    }
+}
+
+/// A player's lifetime record, updated by `claim_pot` and `settle_rematch`.
+#[account]
+pub struct PlayerStats {
+    pub player: Pubkey,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,          // tied rounds across all matches (matches never tie)
+    pub total_wagered: u64, // sum of this player's stakes, in each game's asset units
+    pub rating: u32,        // Elo-style, starts at INITIAL_RATING
+    pub last_game_id: u64,  // most recently settled match
+}
+impl PlayerStats {
+    pub const LEN: usize = 32 + 4 * 3 + 8 + 4 + 8;
+
+    /// Fill in a freshly created (zeroed) stats account.
+    pub fn ensure_initialized(&mut self, player: Pubkey) {
+        if self.player == Pubkey::default() {
+            self.player = player;
+            self.rating = INITIAL_RATING;
+        }
+    }
+
+    /// Apply one settled match of `game` with `tied_rounds` replayed rounds,
+    /// moving the rating by `rating_change`.
+    pub fn record_match(&mut self, game: &Game, won: bool, rating_change: u32, tied_rounds: u32) {
+        if won {
+            self.wins = self.wins.saturating_add(1);
+            self.rating = self.rating.saturating_add(rating_change);
+        } else {
+            self.losses = self.losses.saturating_add(1);
+            self.rating = self.rating.saturating_sub(rating_change);
+        }
+        self.ties = self.ties.saturating_add(tied_rounds);
+        self.total_wagered = self.total_wagered.saturating_add(game.stake);
+        self.last_game_id = game.game_id;
+    }
+}
+
+/// Global top-`LEADERBOARD_SIZE` players by rating.
+#[account]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+impl Leaderboard {
+    pub const LEN: usize = 4 + LEADERBOARD_SIZE * LeaderboardEntry::LEN;
+
+    /// Upsert `stats`, keeping the board sorted and capped at `LEADERBOARD_SIZE`.
+    pub fn record(&mut self, stats: &PlayerStats) {
+        self.entries.retain(|entry| entry.player != stats.player);
+        self.entries.push(LeaderboardEntry {
+            player: stats.player,
+            rating: stats.rating,
+            wins: stats.wins,
+        });
+        self.entries
+            .sort_by(|a, b| b.rating.cmp(&a.rating).then(b.wins.cmp(&a.wins)));
+        self.entries.truncate(LEADERBOARD_SIZE);
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct LeaderboardEntry {
+    pub player: Pubkey,
+    pub rating: u32,
+    pub wins: u32,
+}
+impl LeaderboardEntry {
+    pub const LEN: usize = 32 + 4 + 4;
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
 pub enum RoundResult {
     Winner(Pubkey),
@@ -897,7 +2336,7 @@ pub struct PlayerChoice {
     pub choice: Option<Choice>,
 }
 impl PlayerChoice {
-    pub const LEN: usize = 8 + 8 + 32 + 2;
+    pub const LEN: usize = 8 + 8 + 32 + 3;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
@@ -905,6 +2344,82 @@ pub enum Choice {
     Rock,
     Paper,
     Scissors,
+    Lizard,
+    Spock,
+    /// Move `n` of a custom rule table (the named moves are 0–4).
+    Move(u8),
+}
+impl Choice {
+    /// Row/column of this move in a rule table.
+    pub fn index(&self) -> u8 {
+        match self {
+            Choice::Rock => 0,
+            Choice::Paper => 1,
+            Choice::Scissors => 2,
+            Choice::Lizard => 3,
+            Choice::Spock => 4,
+            Choice::Move(n) => *n,
+        }
+    }
+}
+
+/// Which rule table a game's rounds are resolved with.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum GameVariant {
+    Classic,
+    Rpsls,
+    /// A `RuleTable` account.
+    Custom(Pubkey),
+}
+impl GameVariant {
+    /// The `beats` bitmasks for this variant. Custom variants need their
+    /// `RuleTable` passed in.
+    pub fn rules<'a>(&self, rule_table: Option<&'a Account<RuleTable>>) -> Result<&'a [u16]> {
+        match self {
+            GameVariant::Classic => Ok(&CLASSIC_RULES),
+            GameVariant::Rpsls => Ok(&RPSLS_RULES),
+            GameVariant::Custom(key) => {
+                let rule_table = rule_table.ok_or(GameError::MissingRuleTable)?;
+                require_keys_eq!(rule_table.key(), *key, GameError::WrongRuleTable);
+                Ok(&rule_table.beats)
+            }
+        }
+    }
+}
+
+/// Global house-fee settings, written by `set_config`.
+#[account]
+pub struct Config {
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+}
+impl Config {
+    pub const LEN: usize = 2 + 32;
+}
+
+/// Base-layer record of staked-rematch deposits for one game. Deposits sit in
+/// the game's vault; `settled_match` is the last match whose pot the
+/// `settle_rematch` action paid out.
+#[account]
+pub struct RematchEscrow {
+    pub game_id: u64,
+    pub settled_match: u32,
+    pub player1_funded: bool,
+    pub player2_funded: bool,
+}
+impl RematchEscrow {
+    pub const LEN: usize = 8 + 4 + 1 + 1;
+}
+
+/// A custom N-move rule table; see `create_rule_table`.
+#[account]
+pub struct RuleTable {
+    pub authority: Pubkey,
+    pub table_id: u64,
+    pub beats: Vec<u16>, // beats[i] bit j => move i beats move j
+}
+impl RuleTable {
+    pub const LEN: usize = 32 + 8 + 4 + 2 * MAX_RULE_MOVES;
 }
 
 #[error_code]
@@ -937,6 +2452,92 @@ pub enum GameError {
     MatchNotDecided,
     #[msg("Settle and claim the pot before starting a new match.")]
     MustClaimFirst,
+    #[msg("The move deadline for this round has not passed.")]
+    RoundNotExpired,
+    #[msg("Both players moved; reveal the round instead.")]
+    BothPlayersMoved,
+    #[msg("This round has already been revealed.")]
+    RoundAlreadyRevealed,
+    #[msg("The match is already decided.")]
+    MatchDecided,
+    #[msg("Token games need the stake token accounts and the token program.")]
+    MissingTokenAccount,
+    #[msg("Token account mint does not match the game's stake mint.")]
+    StakeMintMismatch,
+    #[msg("Token account is not owned by the expected wallet.")]
+    WrongTokenAccount,
+    #[msg("This game is listed in the lobby; join it with quick_join.")]
+    GameListed,
+    #[msg("The lobby queue is full.")]
+    LobbyFull,
+    #[msg("No open game in the lobby matches this request.")]
+    NoOpenGame,
+    #[msg("Only the oldest matching game in the lobby can be joined.")]
+    NotOldestOpenGame,
+    #[msg("The game's lobby account is required.")]
+    MissingLobby,
+    #[msg("Tournaments take 4, 8 or 16 entrants.")]
+    InvalidBracketSize,
+    #[msg("Tournament id must fit in 47 bits.")]
+    InvalidTournamentId,
+    #[msg("Prize split must add up to 10000 bps.")]
+    InvalidPrizeSplit,
+    #[msg("Tournament registration is closed.")]
+    RegistrationClosed,
+    #[msg("Already registered for this tournament.")]
+    AlreadyRegistered,
+    #[msg("Not registered for this tournament.")]
+    NotRegistered,
+    #[msg("The tournament is not in progress.")]
+    TournamentNotInProgress,
+    #[msg("The tournament has not finished yet.")]
+    TournamentNotCompleted,
+    #[msg("No such match in the current round.")]
+    InvalidMatchIndex,
+    #[msg("This match result was already reported.")]
+    MatchAlreadyReported,
+    #[msg("This game is not part of the tournament.")]
+    NotATournamentGame,
+    #[msg("That move is not part of this game's rule table.")]
+    InvalidMove,
+    #[msg("Rule tables need 3-16 moves and exactly one winner per pair.")]
+    InvalidRuleTable,
+    #[msg("This game plays a custom rule table; pass it in.")]
+    MissingRuleTable,
+    #[msg("Rule table does not match the game's variant.")]
+    WrongRuleTable,
+    #[msg("Only staked games need a funded rematch.")]
+    NothingStaked,
+    #[msg("Staked rematches are only supported for lamport games.")]
+    RematchNeedsLamports,
+    #[msg("You already funded this rematch.")]
+    RematchAlreadyFunded,
+    #[msg("Both players must fund the rematch first.")]
+    RematchNotFunded,
+    #[msg("The previous match's pot has not been settled yet.")]
+    RematchNotSettled,
+    #[msg("A rematch is pending; start it with next_round.")]
+    RematchPending,
+    #[msg("The rematch escrow account is required.")]
+    MissingRematchEscrow,
+    #[msg("The game has not been settled yet.")]
+    GameNotSettled,
+    #[msg("Players can't be added as spectators.")]
+    SpectatorIsPlayer,
+    #[msg("Already spectating this game.")]
+    AlreadySpectating,
+    #[msg("Not a spectator of this game.")]
+    NotSpectating,
+    #[msg("This game has no room for more spectators.")]
+    TooManySpectators,
+    #[msg("House fee exceeds the maximum.")]
+    FeeTooHigh,
+    #[msg("Fee recipient does not match the config.")]
+    WrongFeeRecipient,
+    #[msg("Game ids with the top bit set are reserved for tournament brackets.")]
+    ReservedGameId,
+    #[msg("The bracket round deadline has not passed.")]
+    BracketRoundNotExpired,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
@@ -945,6 +2546,153 @@ pub enum AccountType {
     PlayerChoice { game_id: u64, player: Pubkey },
 }
 
+/// House fee on `pot` and who receives it. The config PDA is passed unchecked
+/// so pots still settle (fee-free) before `set_config` has ever run.
+fn house_fee(config: &AccountInfo, pot: u64) -> Result<(u64, Pubkey)> {
+    if config.owner != &crate::ID || config.data_is_empty() {
+        return Ok((0, Pubkey::default()));
+    }
+    let config = Config::try_deserialize(&mut &config.try_borrow_data()?[..])?;
+    let fee = (pot as u128 * config.fee_bps as u128 / 10_000) as u64;
+    Ok((fee, config.fee_recipient))
+}
+
+/// Which side took a round, before it's mapped onto the game's players.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoundOutcome {
+    Player1,
+    Player2,
+    Tie,
+}
+
+/// Decide one round from the two moves under `rules`. Pure, so `reveal_round`
+/// and the tests share exactly the same resolution. Identical moves, and any
+/// pair where neither move beats the other, tie.
+pub fn resolve_round(rules: &[u16], choice1: &Choice, choice2: &Choice) -> RoundOutcome {
+    if beats(rules, choice1, choice2) {
+        RoundOutcome::Player1
+    } else if beats(rules, choice2, choice1) {
+        RoundOutcome::Player2
+    } else {
+        RoundOutcome::Tie
+    }
+}
+
+/// Whether `a` beats `b` under `rules` (see `CLASSIC_RULES` for the layout).
+/// Moves outside the table never win.
+fn beats(rules: &[u16], a: &Choice, b: &Choice) -> bool {
+    let (a, b) = (a.index() as usize, b.index() as usize);
+    b < rules.len() && rules.get(a).is_some_and(|row| row & (1 << b) != 0)
+}
+
+/// A rule table needs 3..=`MAX_RULE_MOVES` moves, no move beating itself or a
+/// move outside the table, and exactly one winner for every pair of moves.
+fn validate_rules(rules: &[u16]) -> Result<()> {
+    let moves = rules.len();
+    require!(
+        (3..=MAX_RULE_MOVES).contains(&moves),
+        GameError::InvalidRuleTable
+    );
+    for (i, row) in rules.iter().enumerate() {
+        require!(row & (1 << i) == 0, GameError::InvalidRuleTable);
+        require!(
+            moves == MAX_RULE_MOVES || row >> moves == 0,
+            GameError::InvalidRuleTable
+        );
+        for (j, other) in rules.iter().enumerate().skip(i + 1) {
+            let i_beats_j = row & (1 << j) != 0;
+            let j_beats_i = other & (1 << i) != 0;
+            require!(i_beats_j != j_beats_i, GameError::InvalidRuleTable);
+        }
+    }
+    Ok(())
+}
+
+/// 10^(k/16) scaled by 1000, for k = 0..=16 — i.e. 10^(d/400) in 25-point
+/// rating steps up to the 400-point cap.
+const POW10_STEPS: [u32; 17] = [
+    1000, 1155, 1334, 1540, 1778, 2054, 2371, 2738, 3162, 3652, 4217, 4870, 5623, 6494, 7499,
+    8660, 10000,
+];
+
+/// Rating points the winner takes from the loser. Integer Elo: the winner's
+/// expected score is 1 / (1 + 10^((loser - winner) / 400)), with the gap
+/// capped at 400 and rounded to 25-point steps. Always moves at least 1.
+fn elo_delta(winner_rating: u32, loser_rating: u32) -> u32 {
+    let gap = (loser_rating as i64 - winner_rating as i64).clamp(-400, 400);
+    let step = POW10_STEPS[((gap.unsigned_abs() + 12) / 25) as usize];
+    // 10^(gap/400) scaled by 1000
+    let odds = if gap >= 0 { step } else { 1_000_000 / step };
+    // Winner's expected score, per mille
+    let expected = 1_000_000 / (1000 + odds);
+    (ELO_K * (1000 - expected) / 1000).max(1)
+}
+
+/// Record one finished match of `game` on both players' stats, moving
+/// `elo_delta` rating points from the loser to the winner.
+fn record_result(
+    game: &Game,
+    winner_stats: &mut PlayerStats,
+    loser_stats: &mut PlayerStats,
+    tied_rounds: u32,
+) {
+    let rating_change = elo_delta(winner_stats.rating, loser_stats.rating);
+    winner_stats.record_match(game, true, rating_change, tied_rounds);
+    loser_stats.record_match(game, false, rating_change, tied_rounds);
+    msg!(
+        "Rating {} +{} / {} -{}",
+        winner_stats.player,
+        rating_change,
+        loser_stats.player,
+        rating_change
+    );
+}
+
+/// Seat `player` as Player 2: pre-fund their PlayerChoice PDA with rent for its
+/// ephemeral permission, take their matching wager, and start the first round.
+/// Shared by `join_game` and `quick_join`; `vault` is None for free games.
+fn seat_player2<'info>(
+    game: &mut Account<'info, Game>,
+    player_choice: &mut Account<'info, PlayerChoice>,
+    player: &Signer<'info>,
+    player_token_account: Option<&Account<'info, TokenAccount>>,
+    vault: Option<StakeVault<'_, 'info>>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    transfer(
+        CpiContext::new(
+            system_program.key(),
+            Transfer {
+                from: player.to_account_info(),
+                to: player_choice.to_account_info(),
+            },
+        ),
+        ephemeral_rollups_sdk::ephemeral_accounts::rent(EphemeralPermission::size_of(1) as u32),
+    )?;
+
+    let player_key = player.key();
+    require!(!game.paid, GameError::GameSettled);
+    require!(game.player1 != Some(player_key), GameError::CannotJoinOwnGame);
+    require!(game.player2.is_none(), GameError::GameFull);
+
+    // Player 2 matches Player 1's wager into the vault, in the game's asset.
+    if let Some(vault) = vault {
+        let from = vault.account_of(player.to_account_info(), player_token_account)?;
+        vault.take(from, player.to_account_info(), game.stake)?;
+    }
+
+    game.player2 = Some(player_key);
+    game.start_round_clock(Clock::get()?.unix_timestamp);
+
+    // Create PlayerChoice PDA for player 2
+    player_choice.game_id = game.game_id;
+    player_choice.player = player_key;
+    player_choice.choice = None;
+
+    msg!("{} joined Game {} as player 2", player_key, game.game_id);
+    Ok(())
+}
+
 /// Transfer `amount` lamports out of the system-owned vault PDA, which signs
 /// for itself via `signer_seeds`.
 fn pay_from_vault<'info>(
@@ -967,6 +2715,119 @@ fn pay_from_vault<'info>(
     )
 }
 
+// Where a game's pot sits: lamports in the vault PDA itself, or — for games
+// created with a stake mint — the vault PDA's associated token account. Each
+// context pins that ATA with `associated_token` constraints, so all that's
+// left to pick here is which of the two the game uses.
+struct StakeVault<'a, 'info> {
+    vault: &'a SystemAccount<'info>,
+    tokens: Option<(&'a Account<'info, TokenAccount>, &'a Program<'info, Token>)>,
+    system_program: &'a Program<'info, System>,
+}
+
+impl<'a, 'info> StakeVault<'a, 'info> {
+    fn new(
+        game: &Game,
+        vault: &'a SystemAccount<'info>,
+        vault_token_account: Option<&'a Account<'info, TokenAccount>>,
+        token_program: Option<&'a Program<'info, Token>>,
+        system_program: &'a Program<'info, System>,
+    ) -> Result<Self> {
+        let tokens = match game.stake_mint {
+            Some(_) => Some((
+                vault_token_account.ok_or(GameError::MissingTokenAccount)?,
+                token_program.ok_or(GameError::MissingTokenAccount)?,
+            )),
+            None => None,
+        };
+        Ok(StakeVault {
+            vault,
+            tokens,
+            system_program,
+        })
+    }
+
+    // A player's side of a transfer: their wallet in a lamport game, or their
+    // own stake-mint token account in a token game.
+    fn account_of(
+        &self,
+        wallet: AccountInfo<'info>,
+        token_account: Option<&Account<'info, TokenAccount>>,
+    ) -> Result<AccountInfo<'info>> {
+        let Some((vault_token_account, _)) = self.tokens else {
+            return Ok(wallet);
+        };
+        let token_account = token_account.ok_or(GameError::MissingTokenAccount)?;
+        require_keys_eq!(
+            token_account.mint,
+            vault_token_account.mint,
+            GameError::StakeMintMismatch
+        );
+        require_keys_eq!(
+            token_account.owner,
+            wallet.key(),
+            GameError::WrongTokenAccount
+        );
+        Ok(token_account.to_account_info())
+    }
+
+    // Move a player's wager into the pot, signed by the player.
+    fn take(
+        &self,
+        from: AccountInfo<'info>,
+        player: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        match self.tokens {
+            None => transfer(
+                CpiContext::new(
+                    self.system_program.key(),
+                    Transfer {
+                        from,
+                        to: self.vault.to_account_info(),
+                    },
+                ),
+                amount,
+            ),
+            Some((vault_token_account, token_program)) => token::transfer(
+                CpiContext::new(
+                    token_program.key(),
+                    TokenTransfer {
+                        from,
+                        to: vault_token_account.to_account_info(),
+                        authority: player,
+                    },
+                ),
+                amount,
+            ),
+        }
+    }
+
+    // Pay `amount` out of the pot; the vault PDA signs via `signer_seeds`.
+    fn pay(
+        &self,
+        to: AccountInfo<'info>,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        match self.tokens {
+            None => pay_from_vault(self.vault, &to, amount, self.system_program, signer_seeds),
+            Some((vault_token_account, token_program)) => token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.key(),
+                    TokenTransfer {
+                        from: vault_token_account.to_account_info(),
+                        to,
+                        authority: self.vault.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                amount,
+            ),
+        }
+    }
+}
+
 fn derive_seeds_from_account_type(account_type: &AccountType) -> Vec<Vec<u8>> {
     match account_type {
         AccountType::Game { game_id } => {
@@ -0,0 +1,639 @@
+//! Single-elimination tournaments built out of ordinary bracket `Game`s.
+//!
+//! Entrants pay an entry fee into a tournament vault PDA. Once the bracket is
+//! full, `spawn_match` creates each round's `Game`s (free games, stake 0) with
+//! both players already seated, so they delegate and play exactly like a
+//! regular match. After a bracket game is undelegated, `report_match` reads
+//! `match_winner()` and advances the winner. A match that produces no winner
+//! (abandoned, or still unreported at the round deadline) advances the higher
+//! seed, so the bracket can't stall. When the final is reported,
+//! `claim_prizes` splits the vault between the champion, the runner-up and the
+//! semi-finalists.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use ephemeral_rollups_sdk::access_control::structs::EphemeralPermission;
+
+use crate::{
+    pay_from_vault, Game, GameError, GameVariant, PlayerChoice, RoundResult, GAME_SEED,
+    MAX_SPECTATORS, MOVE_TIMEOUT_SECS, PLAYER_CHOICE_SEED,
+};
+
+pub const TOURNAMENT_SEED: &[u8] = b"tournament";
+pub const TOURNAMENT_VAULT_SEED: &[u8] = b"tournament_vault";
+/// Largest supported bracket.
+pub const MAX_ENTRANTS: usize = 16;
+/// Prize split denominator: `prize_split_bps` must add up to this.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// Tag bit set on every bracket game id. `create_game` rejects ids carrying
+/// it, so a regular game can't squat a bracket match's `Game` PDA.
+pub const BRACKET_GAME_ID_FLAG: u64 = 1 << 63;
+/// Seconds each bracket round has before `advance_stalled_match` may push the
+/// higher seed of an unreported match through.
+pub const BRACKET_ROUND_TIMEOUT_SECS: i64 = 24 * 60 * 60;
+
+#[account]
+pub struct Tournament {
+    pub tournament_id: u64,
+    pub organizer: Pubkey,
+    pub size: u8,                  // 4, 8 or 16 entrants
+    pub entry_fee: u64,            // lamports per entrant
+    pub target_wins: u8,           // best-of length of every bracket game
+    pub prize_split_bps: [u16; 3], // champion, runner-up, semi-finalists (shared)
+    pub status: TournamentStatus,
+    pub round: u8,            // current bracket round, 1-based (0 while registering)
+    pub round_deadline_ts: i64, // after this, unreported matches advance the higher seed
+    pub players: Vec<Pubkey>, // entrants still alive, in bracket order
+    pub winners: Vec<Option<Pubkey>>, // reported winner of each current-round match
+    pub champion: Option<Pubkey>,
+    pub runner_up: Option<Pubkey>,
+    pub semifinalists: Vec<Pubkey>, // losers of the semi-finals
+}
+impl Tournament {
+    pub const LEN: usize = 8                // tournament_id
+        + 32                                 // organizer
+        + 1                                  // size
+        + 8                                  // entry_fee
+        + 1                                  // target_wins
+        + 2 * 3                              // prize_split_bps
+        + 1                                  // status
+        + 1                                  // round
+        + 8                                  // round_deadline_ts
+        + 4 + 32 * MAX_ENTRANTS              // players
+        + 4 + (1 + 32) * (MAX_ENTRANTS / 2)  // winners
+        + (1 + 32) * 2                       // champion, runner_up
+        + 4 + 32 * 2; // semifinalists
+
+    /// Game id of bracket match `match_index` in `round`. The tournament id
+    /// fills bits 16–62, so bracket games never collide with each other, and
+    /// `BRACKET_GAME_ID_FLAG` keeps them apart from `create_game` ids.
+    pub fn match_game_id(&self, round: u8, match_index: u8) -> u64 {
+        BRACKET_GAME_ID_FLAG
+            | (self.tournament_id << 16)
+            | ((round as u64) << 8)
+            | match_index as u64
+    }
+
+    /// The two entrants meeting in `match_index` of the current round. The
+    /// first is the higher seed (the earlier registration).
+    pub fn pairing(&self, match_index: u8) -> Result<(Pubkey, Pubkey)> {
+        require!(
+            (match_index as usize) < self.matches_in_round(),
+            GameError::InvalidMatchIndex
+        );
+        let i = match_index as usize * 2;
+        Ok((self.players[i], self.players[i + 1]))
+    }
+
+    pub fn matches_in_round(&self) -> usize {
+        self.players.len() / 2
+    }
+
+    /// Start bracket round `round` with the current `players`.
+    pub fn start_round(&mut self, round: u8, now: i64) {
+        self.round = round;
+        self.round_deadline_ts = now + BRACKET_ROUND_TIMEOUT_SECS;
+        self.winners = vec![None; self.matches_in_round()];
+    }
+
+    /// Record `winner` for `match_index` of the current round, and move on to
+    /// the next round (or finish) once every match is in.
+    pub fn advance(&mut self, match_index: u8, winner: Pubkey, now: i64) -> Result<()> {
+        let slot = self
+            .winners
+            .get(match_index as usize)
+            .ok_or(GameError::InvalidMatchIndex)?;
+        require!(slot.is_none(), GameError::MatchAlreadyReported);
+
+        let (player1, player2) = self.pairing(match_index)?;
+        let loser = if winner == player1 { player2 } else { player1 };
+        self.winners[match_index as usize] = Some(winner);
+
+        match self.players.len() {
+            2 => {
+                self.champion = Some(winner);
+                self.runner_up = Some(loser);
+            }
+            4 => self.semifinalists.push(loser),
+            _ => {}
+        }
+        msg!(
+            "Tournament {} round {} match {}: {} advances",
+            self.tournament_id,
+            self.round,
+            match_index,
+            winner
+        );
+
+        // Once every match of the round is in, the winners form the next round.
+        if self.winners.iter().all(Option::is_some) {
+            self.players = self.winners.iter().flatten().copied().collect();
+            if self.players.len() == 1 {
+                self.status = TournamentStatus::Completed;
+                msg!("Tournament {} won by {}", self.tournament_id, winner);
+            } else {
+                self.start_round(self.round + 1, now);
+                msg!("Tournament {} round {} ready", self.tournament_id, self.round);
+            }
+        }
+        Ok(())
+    }
+
+    /// Lamports held for prizes once the bracket is full.
+    pub fn prize_pool(&self) -> Result<u64> {
+        Ok(self
+            .entry_fee
+            .checked_mul(self.size as u64)
+            .ok_or(GameError::MathOverflow)?)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TournamentStatus {
+    Registering,
+    InProgress,
+    Completed,
+    Paid,
+}
+
+/// Context for `create_tournament` — the organizer sets the bracket size,
+/// entry fee, best-of length and prize split.
+#[derive(Accounts)]
+#[instruction(tournament_id: u64)]
+pub struct CreateTournament<'info> {
+    #[account(
+        init,
+        payer = organizer,
+        space = 8 + Tournament::LEN,
+        seeds = [TOURNAMENT_SEED, &tournament_id.to_le_bytes()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateTournament<'info> {
+    pub fn create(
+        &mut self,
+        tournament_id: u64,
+        size: u8,
+        entry_fee: u64,
+        target_wins: u8,
+        prize_split_bps: [u16; 3],
+    ) -> Result<()> {
+        require!(matches!(size, 4 | 8 | 16), GameError::InvalidBracketSize);
+        // Bracket game ids keep the low 16 bits for round + match index and
+        // the top bit for `BRACKET_GAME_ID_FLAG`.
+        require!(tournament_id >> 47 == 0, GameError::InvalidTournamentId);
+        let split_total: u64 = prize_split_bps.iter().map(|bps| *bps as u64).sum();
+        require!(split_total == BPS_DENOMINATOR, GameError::InvalidPrizeSplit);
+
+        let tournament = &mut self.tournament;
+        tournament.tournament_id = tournament_id;
+        tournament.organizer = self.organizer.key();
+        tournament.size = size;
+        tournament.entry_fee = entry_fee;
+        tournament.target_wins = target_wins.max(1);
+        tournament.prize_split_bps = prize_split_bps;
+        tournament.status = TournamentStatus::Registering;
+        tournament.round = 0;
+        tournament.round_deadline_ts = 0;
+        tournament.players = Vec::new();
+        tournament.winners = Vec::new();
+        tournament.champion = None;
+        tournament.runner_up = None;
+        tournament.semifinalists = Vec::new();
+
+        msg!(
+            "Tournament {} created: {} entrants, entry fee {} lamports",
+            tournament_id,
+            size,
+            entry_fee
+        );
+        Ok(())
+    }
+}
+
+/// Context for `register_entrant` — pays the entry fee into the tournament vault.
+#[derive(Accounts)]
+pub struct RegisterEntrant<'info> {
+    #[account(mut, seeds = [TOURNAMENT_SEED, &tournament.tournament_id.to_le_bytes()], bump)]
+    pub tournament: Account<'info, Tournament>,
+    /// CHECK: SOL escrow PDA, system-owned, holds the prize pool. Validated by seeds.
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_VAULT_SEED, &tournament.tournament_id.to_le_bytes()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub entrant: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RegisterEntrant<'info> {
+    pub fn register(&mut self) -> Result<()> {
+        let tournament = &mut self.tournament;
+        let entrant = self.entrant.key();
+        require!(
+            tournament.status == TournamentStatus::Registering,
+            GameError::RegistrationClosed
+        );
+        require!(
+            !tournament.players.contains(&entrant),
+            GameError::AlreadyRegistered
+        );
+
+        if tournament.entry_fee > 0 {
+            transfer(
+                CpiContext::new(
+                    self.system_program.key(),
+                    Transfer {
+                        from: self.entrant.to_account_info(),
+                        to: self.vault.to_account_info(),
+                    },
+                ),
+                tournament.entry_fee,
+            )?;
+        }
+        tournament.players.push(entrant);
+
+        // A full bracket starts round 1 in registration order.
+        if tournament.players.len() == tournament.size as usize {
+            tournament.status = TournamentStatus::InProgress;
+            tournament.start_round(1, Clock::get()?.unix_timestamp);
+            msg!(
+                "Tournament {} bracket full, round 1 ready",
+                tournament.tournament_id
+            );
+        }
+
+        msg!(
+            "{} registered for tournament {} ({}/{})",
+            entrant,
+            tournament.tournament_id,
+            tournament.players.len(),
+            tournament.size
+        );
+        Ok(())
+    }
+}
+
+/// Context for `leave_tournament` — refunds an entrant while registration is
+/// still open.
+#[derive(Accounts)]
+pub struct LeaveTournament<'info> {
+    #[account(mut, seeds = [TOURNAMENT_SEED, &tournament.tournament_id.to_le_bytes()], bump)]
+    pub tournament: Account<'info, Tournament>,
+    /// CHECK: SOL escrow PDA, system-owned. Validated by seeds.
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_VAULT_SEED, &tournament.tournament_id.to_le_bytes()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub entrant: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> LeaveTournament<'info> {
+    pub fn leave(&mut self, vault_bump: u8) -> Result<()> {
+        let tournament = &mut self.tournament;
+        let entrant = self.entrant.key();
+        require!(
+            tournament.status == TournamentStatus::Registering,
+            GameError::RegistrationClosed
+        );
+        let position = tournament
+            .players
+            .iter()
+            .position(|player| *player == entrant)
+            .ok_or(GameError::NotRegistered)?;
+        tournament.players.remove(position);
+
+        if tournament.entry_fee > 0 {
+            let tournament_id_bytes = tournament.tournament_id.to_le_bytes();
+            let signer_seeds: &[&[&[u8]]] =
+                &[&[TOURNAMENT_VAULT_SEED, &tournament_id_bytes, &[vault_bump]]];
+            pay_from_vault(
+                &self.vault,
+                &self.entrant.to_account_info(),
+                tournament.entry_fee,
+                &self.system_program,
+                signer_seeds,
+            )?;
+        }
+        msg!("{} left tournament {}", entrant, tournament.tournament_id);
+        Ok(())
+    }
+}
+
+/// Context for `spawn_match` — creates one bracket `Game` of the current round
+/// with both players seated, plus their PlayerChoice PDAs. Permissionless: the
+/// payer covers rent, including the ephemeral-permission rent that
+/// `create_game`/`join_game` would otherwise pre-fund.
+#[derive(Accounts)]
+#[instruction(match_index: u8)]
+pub struct SpawnMatch<'info> {
+    #[account(seeds = [TOURNAMENT_SEED, &tournament.tournament_id.to_le_bytes()], bump)]
+    pub tournament: Account<'info, Tournament>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Game::LEN,
+        seeds = [
+            GAME_SEED,
+            &tournament.match_game_id(tournament.round, match_index).to_le_bytes()
+        ],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PlayerChoice::LEN,
+        seeds = [
+            PLAYER_CHOICE_SEED,
+            &tournament.match_game_id(tournament.round, match_index).to_le_bytes(),
+            tournament.pairing(match_index).unwrap_or_default().0.as_ref()
+        ],
+        bump
+    )]
+    pub player1_choice: Account<'info, PlayerChoice>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PlayerChoice::LEN,
+        seeds = [
+            PLAYER_CHOICE_SEED,
+            &tournament.match_game_id(tournament.round, match_index).to_le_bytes(),
+            tournament.pairing(match_index).unwrap_or_default().1.as_ref()
+        ],
+        bump
+    )]
+    pub player2_choice: Account<'info, PlayerChoice>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SpawnMatch<'info> {
+    pub fn spawn(&mut self, match_index: u8) -> Result<()> {
+        let tournament = &self.tournament;
+        require!(
+            tournament.status == TournamentStatus::InProgress,
+            GameError::TournamentNotInProgress
+        );
+        // The seeds above fall back to default keys for an out-of-range index;
+        // this rejects it.
+        let (player1, player2) = tournament.pairing(match_index)?;
+        let game_id = tournament.match_game_id(tournament.round, match_index);
+
+        // Same permission rent pre-funding as create_game + join_game.
+        self.fund(
+            self.game.to_account_info(),
+            EphemeralPermission::size_of(2 + MAX_SPECTATORS),
+        )?;
+        self.fund(
+            self.player1_choice.to_account_info(),
+            EphemeralPermission::size_of(1),
+        )?;
+        self.fund(
+            self.player2_choice.to_account_info(),
+            EphemeralPermission::size_of(1),
+        )?;
+
+        let game = &mut self.game;
+        game.game_id = game_id;
+        game.player1 = Some(player1);
+        game.player2 = Some(player2);
+        game.player1_choice = None;
+        game.player2_choice = None;
+        game.round_result = RoundResult::None;
+        // Bracket games are free: the prize pool lives in the tournament vault.
+        game.stake = 0;
+        game.stake_mint = None;
+        game.paid = false;
+        game.target_wins = tournament.target_wins;
+        game.player1_wins = 0;
+        game.player2_wins = 0;
+        game.round = 1;
+        game.move_timeout_secs = MOVE_TIMEOUT_SECS;
+        game.abandoned = false;
+        game.unrecorded_wins = [0; 2];
+        game.unrecorded_ties = 0;
+        game.start_round_clock(Clock::get()?.unix_timestamp);
+        game.listed = false;
+        game.tied_rounds = 0;
+        game.tournament = Some(tournament.key());
+        game.variant = GameVariant::Classic;
+
+        for (player_choice, player) in [
+            (&mut self.player1_choice, player1),
+            (&mut self.player2_choice, player2),
+        ] {
+            player_choice.game_id = game_id;
+            player_choice.player = player;
+            player_choice.choice = None;
+        }
+
+        msg!(
+            "Tournament {} round {} match {}: {} vs {} (game {})",
+            tournament.tournament_id,
+            tournament.round,
+            match_index,
+            player1,
+            player2,
+            game_id
+        );
+        Ok(())
+    }
+
+    fn fund(&self, to: AccountInfo<'info>, permission_size: usize) -> Result<()> {
+        transfer(
+            CpiContext::new(
+                self.system_program.key(),
+                Transfer {
+                    from: self.payer.to_account_info(),
+                    to,
+                },
+            ),
+            ephemeral_rollups_sdk::ephemeral_accounts::rent(permission_size as u32),
+        )
+    }
+}
+
+/// Context for `report_match` — base layer, after the bracket game has been
+/// undelegated. Permissionless; the result is read straight off the `Game`.
+#[derive(Accounts)]
+#[instruction(match_index: u8)]
+pub struct ReportMatch<'info> {
+    #[account(mut, seeds = [TOURNAMENT_SEED, &tournament.tournament_id.to_le_bytes()], bump)]
+    pub tournament: Account<'info, Tournament>,
+    #[account(
+        seeds = [
+            GAME_SEED,
+            &tournament.match_game_id(tournament.round, match_index).to_le_bytes()
+        ],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+}
+
+impl<'info> ReportMatch<'info> {
+    pub fn report(&mut self, match_index: u8) -> Result<()> {
+        let tournament = &mut self.tournament;
+        let game = &self.game;
+        require!(
+            tournament.status == TournamentStatus::InProgress,
+            GameError::TournamentNotInProgress
+        );
+        require!(
+            game.tournament == Some(tournament.key()),
+            GameError::NotATournamentGame
+        );
+        let (higher_seed, _) = tournament.pairing(match_index)?;
+        require!(game.is_match_decided(), GameError::MatchNotDecided);
+
+        // An abandoned game has no winner; the higher seed goes through.
+        let winner = game.match_winner().unwrap_or(higher_seed);
+        tournament.advance(match_index, winner, Clock::get()?.unix_timestamp)
+    }
+}
+
+/// Context for `advance_stalled_match` — base layer, once the round deadline
+/// has passed. Permissionless; covers matches that were never spawned, never
+/// played, or are still delegated.
+#[derive(Accounts)]
+#[instruction(match_index: u8)]
+pub struct AdvanceStalledMatch<'info> {
+    #[account(mut, seeds = [TOURNAMENT_SEED, &tournament.tournament_id.to_le_bytes()], bump)]
+    pub tournament: Account<'info, Tournament>,
+    /// CHECK: the match's bracket `Game` PDA, validated by seeds. It may not
+    /// exist yet or may still be owned by the delegation program.
+    #[account(
+        seeds = [
+            GAME_SEED,
+            &tournament.match_game_id(tournament.round, match_index).to_le_bytes()
+        ],
+        bump
+    )]
+    pub game: UncheckedAccount<'info>,
+}
+
+impl<'info> AdvanceStalledMatch<'info> {
+    pub fn advance(&mut self, match_index: u8) -> Result<()> {
+        let tournament = &mut self.tournament;
+        require!(
+            tournament.status == TournamentStatus::InProgress,
+            GameError::TournamentNotInProgress
+        );
+        let (higher_seed, _) = tournament.pairing(match_index)?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= tournament.round_deadline_ts,
+            GameError::BracketRoundNotExpired
+        );
+
+        // A game back on the base layer with a result goes through
+        // `report_match`, so the deadline can't override its winner.
+        if self.game.owner == &crate::ID {
+            let game = Game::try_deserialize(&mut &self.game.try_borrow_data()?[..])?;
+            require!(!game.is_match_decided(), GameError::MatchDecided);
+        }
+        msg!(
+            "Tournament {} round {} match {} stalled",
+            tournament.tournament_id,
+            tournament.round,
+            match_index
+        );
+        tournament.advance(match_index, higher_seed, now)
+    }
+}
+
+/// Context for `claim_prizes` — base layer payout of the whole prize pool once
+/// the final is reported. Anyone can trigger it; every recipient is checked
+/// against the placings recorded on the tournament.
+#[derive(Accounts)]
+pub struct ClaimPrizes<'info> {
+    #[account(mut, seeds = [TOURNAMENT_SEED, &tournament.tournament_id.to_le_bytes()], bump)]
+    pub tournament: Account<'info, Tournament>,
+    /// CHECK: SOL escrow PDA, system-owned. Validated by seeds.
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_VAULT_SEED, &tournament.tournament_id.to_le_bytes()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    /// CHECK: payout recipient, verified against tournament.champion.
+    #[account(mut)]
+    pub champion: UncheckedAccount<'info>,
+    /// CHECK: payout recipient, verified against tournament.runner_up.
+    #[account(mut)]
+    pub runner_up: UncheckedAccount<'info>,
+    /// CHECK: payout recipient, verified against tournament.semifinalists[0].
+    #[account(mut)]
+    pub semifinalist1: UncheckedAccount<'info>,
+    /// CHECK: payout recipient, verified against tournament.semifinalists[1].
+    #[account(mut)]
+    pub semifinalist2: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClaimPrizes<'info> {
+    pub fn pay(&mut self, vault_bump: u8) -> Result<()> {
+        let tournament = &mut self.tournament;
+        require!(
+            tournament.status == TournamentStatus::Completed,
+            GameError::TournamentNotCompleted
+        );
+        require!(
+            tournament.champion == Some(self.champion.key())
+                && tournament.runner_up == Some(self.runner_up.key())
+                && tournament.semifinalists == [self.semifinalist1.key(), self.semifinalist2.key()],
+            GameError::WrongPlayerAccount
+        );
+        tournament.status = TournamentStatus::Paid;
+
+        let pool = tournament.prize_pool()?;
+        let share = |bps: u16| (pool as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        // The champion takes whatever the others don't: their own share plus
+        // any rounding dust, so the vault pays out in full.
+        let [_, runner_up_bps, semifinal_bps] = tournament.prize_split_bps;
+        let semifinal_each = share(semifinal_bps) / 2;
+        let runner_up_prize = share(runner_up_bps);
+        let champion_prize = pool - runner_up_prize - semifinal_each * 2;
+
+        let tournament_id_bytes = tournament.tournament_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[TOURNAMENT_VAULT_SEED, &tournament_id_bytes, &[vault_bump]]];
+        for (to, amount) in [
+            (&self.champion, champion_prize),
+            (&self.runner_up, runner_up_prize),
+            (&self.semifinalist1, semifinal_each),
+            (&self.semifinalist2, semifinal_each),
+        ] {
+            if amount > 0 {
+                pay_from_vault(
+                    &self.vault,
+                    &to.to_account_info(),
+                    amount,
+                    &self.system_program,
+                    signer_seeds,
+                )?;
+            }
+        }
+
+        msg!(
+            "Tournament {} paid: {} / {} / {} x2 lamports",
+            tournament.tournament_id,
+            champion_prize,
+            runner_up_prize,
+            semifinal_each
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,483 @@
+use anchor_lang::prelude::{Clock, Pubkey};
+use anchor_lang::solana_program::{instruction::Instruction, system_program};
+use anchor_rock_paper_scissor::{
+    accounts, instruction, Choice, Game, GameError, PlayerStats, RoundResult,
+};
+use ephemeral_rollups_sdk::consts::{
+    EPHEMERAL_VAULT_ID, MAGIC_CONTEXT_ID, MAGIC_PROGRAM_ID, PERMISSION_PROGRAM_ID,
+};
+use solana_keypair::Keypair;
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_signer::Signer;
+
+mod utils;
+
+#[tokio::test]
+async fn plays_staked_match_to_payout() {
+    let players = [Keypair::new(), Keypair::new()];
+    let mut context = utils::program_test(&[&players[0], &players[1]])
+        .start_with_context()
+        .await;
+    let game = utils::game_pda(utils::GAME_ID);
+    let vault = utils::vault_pda(utils::GAME_ID);
+
+    let create_ix = create_game_ix(&players, utils::STAKE, 2);
+    let join_ix = join_game_ix(&players);
+    utils::send(&mut context, &[create_ix], &[&players[0]])
+        .await
+        .unwrap();
+    utils::send(&mut context, &[join_ix], &[&players[1]])
+        .await
+        .unwrap();
+    assert_eq!(utils::balance(&mut context, vault).await, 2 * utils::STAKE);
+
+    play_round(&mut context, &players, Choice::Rock, Choice::Scissors).await;
+    let state: Game = utils::read(&mut context, game).await;
+    assert_eq!(state.round_result, RoundResult::Winner(players[0].pubkey()));
+    assert_eq!((state.player1_wins, state.player2_wins), (1, 0));
+
+    let next_round_ix = next_round_ix(&players, 1);
+    utils::send(&mut context, &[next_round_ix], &[&players[1]])
+        .await
+        .unwrap();
+    let state: Game = utils::read(&mut context, game).await;
+    assert_eq!(state.round, 2);
+    assert_eq!(state.round_result, RoundResult::None);
+    assert_eq!(state.player1_choice, None);
+
+    play_round(&mut context, &players, Choice::Paper, Choice::Paper).await;
+    let state: Game = utils::read(&mut context, game).await;
+    assert_eq!(state.round_result, RoundResult::Tie);
+    assert_eq!(state.tied_rounds, 1);
+
+    let next_round_ix = next_round_ix(&players, 0);
+    utils::send(&mut context, &[next_round_ix], &[&players[0]])
+        .await
+        .unwrap();
+    play_round(&mut context, &players, Choice::Scissors, Choice::Paper).await;
+    let state: Game = utils::read(&mut context, game).await;
+    assert!(state.is_match_decided());
+    assert_eq!(state.match_winner(), Some(players[0].pubkey()));
+
+    // A decided staked match can't be replayed before its pot is settled.
+    let next_round_ix = next_round_ix(&players, 1);
+    let result = utils::send(&mut context, &[next_round_ix], &[&players[1]]).await;
+    utils::assert_program_error(result, GameError::MustClaimFirst);
+
+    let undelegate_ix = undelegate_all_ix(&context, &players);
+    utils::send(&mut context, &[undelegate_ix], &[])
+        .await
+        .unwrap();
+
+    let winner_before = utils::balance(&mut context, players[0].pubkey()).await;
+    let claim_ix = claim_pot_ix(&context, &players, None);
+    utils::send(&mut context, &[claim_ix], &[]).await.unwrap();
+    assert_eq!(
+        utils::balance(&mut context, players[0].pubkey()).await,
+        winner_before + 2 * utils::STAKE
+    );
+    assert_eq!(utils::balance(&mut context, vault).await, 0);
+    let state: Game = utils::read(&mut context, game).await;
+    assert!(state.paid);
+
+    let winner_stats: PlayerStats =
+        utils::read(&mut context, utils::player_stats_pda(players[0].pubkey())).await;
+    let loser_stats: PlayerStats =
+        utils::read(&mut context, utils::player_stats_pda(players[1].pubkey())).await;
+    assert_eq!((winner_stats.wins, winner_stats.losses), (1, 0));
+    assert_eq!((loser_stats.wins, loser_stats.losses), (0, 1));
+    assert_eq!(winner_stats.ties, 1);
+
+    let replay_ix = claim_pot_ix(&context, &players, None);
+    let result = utils::send(&mut context, &[replay_ix], &[]).await;
+    utils::assert_program_error(result, GameError::AlreadyPaid);
+}
+
+#[tokio::test]
+async fn rejects_undelegate_mid_match() {
+    let players = [Keypair::new(), Keypair::new()];
+    let mut context = utils::program_test(&[&players[0], &players[1]])
+        .start_with_context()
+        .await;
+
+    let create_ix = create_game_ix(&players, utils::STAKE, 2);
+    let join_ix = join_game_ix(&players);
+    utils::send(&mut context, &[create_ix], &[&players[0]])
+        .await
+        .unwrap();
+    utils::send(&mut context, &[join_ix], &[&players[1]])
+        .await
+        .unwrap();
+    play_round(&mut context, &players, Choice::Rock, Choice::Paper).await;
+
+    let undelegate_ix = utils::ix(
+        accounts::UndelegateAll {
+            payer: context.payer.pubkey(),
+            game: utils::game_pda(utils::GAME_ID),
+            player1_choice: choice_pda(&players, 0),
+            player2_choice: choice_pda(&players, 1),
+            magic_context: MAGIC_CONTEXT_ID,
+            magic_program: MAGIC_PROGRAM_ID,
+        },
+        instruction::UndelegateAll {},
+    );
+    let result = utils::send(&mut context, &[undelegate_ix], &[]).await;
+    utils::assert_program_error(result, GameError::MatchNotDecided);
+}
+
+#[tokio::test]
+async fn refunds_unjoined_game() {
+    let players = [Keypair::new(), Keypair::new()];
+    let mut context = utils::program_test(&[&players[0], &players[1]])
+        .start_with_context()
+        .await;
+    let game = utils::game_pda(utils::GAME_ID);
+
+    let create_ix = create_game_ix(&players, utils::STAKE, 1);
+    utils::send(&mut context, &[create_ix], &[&players[0]])
+        .await
+        .unwrap();
+
+    let creator_before = utils::balance(&mut context, players[0].pubkey()).await;
+    let cancel_ix = cancel_game_ix(&players);
+    utils::send(&mut context, &[cancel_ix], &[&players[0]])
+        .await
+        .unwrap();
+    assert_eq!(
+        utils::balance(&mut context, players[0].pubkey()).await,
+        creator_before + utils::STAKE
+    );
+    let state: Game = utils::read(&mut context, game).await;
+    assert!(state.paid);
+}
+
+#[tokio::test]
+async fn rejects_cancel_after_join() {
+    let players = [Keypair::new(), Keypair::new()];
+    let mut context = utils::program_test(&[&players[0], &players[1]])
+        .start_with_context()
+        .await;
+    let vault = utils::vault_pda(utils::GAME_ID);
+
+    let create_ix = create_game_ix(&players, utils::STAKE, 1);
+    let join_ix = join_game_ix(&players);
+    utils::send(&mut context, &[create_ix], &[&players[0]])
+        .await
+        .unwrap();
+    utils::send(&mut context, &[join_ix], &[&players[1]])
+        .await
+        .unwrap();
+
+    let cancel_ix = cancel_game_ix(&players);
+    let result = utils::send(&mut context, &[cancel_ix], &[&players[0]]).await;
+    utils::assert_program_error(result, GameError::CannotCancelStarted);
+    assert_eq!(utils::balance(&mut context, vault).await, 2 * utils::STAKE);
+}
+
+#[tokio::test]
+async fn rejects_moves_in_abandoned_match() {
+    let players = [Keypair::new(), Keypair::new()];
+    let mut context = utils::program_test(&[&players[0], &players[1]])
+        .start_with_context()
+        .await;
+    let game = utils::game_pda(utils::GAME_ID);
+
+    let create_ix = create_game_ix(&players, utils::STAKE, 1);
+    let join_ix = join_game_ix(&players);
+    utils::send(&mut context, &[create_ix], &[&players[0]])
+        .await
+        .unwrap();
+    utils::send(&mut context, &[join_ix], &[&players[1]])
+        .await
+        .unwrap();
+
+    // Neither player moves before the round deadline.
+    let state: Game = utils::read(&mut context, game).await;
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = state.round_deadline_ts;
+    context.set_sysvar(&clock);
+    let forfeit_ix = utils::ix(
+        accounts::ClaimForfeit {
+            game,
+            player1_choice: choice_pda(&players, 0),
+            player2_choice: choice_pda(&players, 1),
+            payer: context.payer.pubkey(),
+        },
+        instruction::ClaimForfeit {},
+    );
+    utils::send(&mut context, &[forfeit_ix], &[]).await.unwrap();
+    let state: Game = utils::read(&mut context, game).await;
+    assert!(state.abandoned);
+
+    let choice_ix = utils::ix(
+        accounts::MakeChoice {
+            player_choice: choice_pda(&players, 0),
+            game,
+            rule_table: None,
+            player: players[0].pubkey(),
+        },
+        instruction::MakeChoice {
+            _game_id: utils::GAME_ID,
+            choice: Choice::Rock,
+        },
+    );
+    let result = utils::send(&mut context, &[choice_ix], &[&players[0]]).await;
+    utils::assert_program_error(result, GameError::MatchDecided);
+}
+
+#[tokio::test]
+async fn claims_pot_when_rematch_settlement_never_ran() {
+    let players = [Keypair::new(), Keypair::new()];
+    let mut context = utils::program_test(&[&players[0], &players[1]])
+        .start_with_context()
+        .await;
+    let game = utils::game_pda(utils::GAME_ID);
+    let vault = utils::vault_pda(utils::GAME_ID);
+    let rematch_escrow = utils::rematch_escrow_pda(utils::GAME_ID);
+
+    let create_ix = create_game_ix(&players, utils::STAKE, 1);
+    let join_ix = join_game_ix(&players);
+    utils::send(&mut context, &[create_ix], &[&players[0]])
+        .await
+        .unwrap();
+    utils::send(&mut context, &[join_ix], &[&players[1]])
+        .await
+        .unwrap();
+    play_round(&mut context, &players, Choice::Rock, Choice::Scissors).await;
+
+    for player in &players {
+        let fund_ix = utils::ix(
+            accounts::FundRematch {
+                game,
+                rematch_escrow,
+                vault,
+                player: player.pubkey(),
+                player_stats: utils::player_stats_pda(player.pubkey()),
+                system_program: system_program::ID,
+            },
+            instruction::FundRematch {
+                _game_id: utils::GAME_ID,
+            },
+        );
+        utils::send(&mut context, &[fund_ix], &[player])
+            .await
+            .unwrap();
+    }
+
+    // The stubbed magic program accepts the commit but never runs the
+    // `settle_rematch` action, as if it had failed on the base layer.
+    let rematch_ix = utils::ix(
+        accounts::Rematch {
+            payer: players[0].pubkey(),
+            game,
+            rematch_escrow,
+            vault,
+            winner: players[0].pubkey(),
+            config: utils::config_pda(),
+            fee_recipient: players[0].pubkey(),
+            player1_stats: utils::player_stats_pda(players[0].pubkey()),
+            player2_stats: utils::player_stats_pda(players[1].pubkey()),
+            program_id: anchor_rock_paper_scissor::ID,
+            system_program: system_program::ID,
+            magic_context: MAGIC_CONTEXT_ID,
+            magic_program: MAGIC_PROGRAM_ID,
+        },
+        instruction::Rematch {},
+    );
+    utils::send(&mut context, &[rematch_ix], &[&players[0]])
+        .await
+        .unwrap();
+    let state: Game = utils::read(&mut context, game).await;
+    assert!(state.rematch_ready);
+
+    let undelegate_ix = undelegate_all_ix(&context, &players);
+    utils::send(&mut context, &[undelegate_ix], &[])
+        .await
+        .unwrap();
+    let claim_ix = claim_pot_ix(&context, &players, None);
+    let result = utils::send(&mut context, &[claim_ix], &[]).await;
+    utils::assert_program_error(result, GameError::MissingRematchEscrow);
+
+    let winner_before = utils::balance(&mut context, players[0].pubkey()).await;
+    let claim_ix = claim_pot_ix(&context, &players, Some(rematch_escrow));
+    utils::send(&mut context, &[claim_ix], &[]).await.unwrap();
+    assert_eq!(
+        utils::balance(&mut context, players[0].pubkey()).await,
+        winner_before + 2 * utils::STAKE
+    );
+    // The rematch deposits stay in the vault for `refund_rematch`.
+    assert_eq!(utils::balance(&mut context, vault).await, 2 * utils::STAKE);
+    let state: Game = utils::read(&mut context, game).await;
+    assert!(state.paid);
+    assert!(!state.rematch_ready);
+}
+
+/// Both players move, then the round is revealed.
+async fn play_round(
+    context: &mut ProgramTestContext,
+    players: &[Keypair; 2],
+    choice1: Choice,
+    choice2: Choice,
+) {
+    for (player, choice) in [(0, choice1), (1, choice2)] {
+        let choice_ix = utils::ix(
+            accounts::MakeChoice {
+                player_choice: choice_pda(players, player),
+                game: utils::game_pda(utils::GAME_ID),
+                rule_table: None,
+                player: players[player].pubkey(),
+            },
+            instruction::MakeChoice {
+                _game_id: utils::GAME_ID,
+                choice,
+            },
+        );
+        utils::send(context, &[choice_ix], &[&players[player]])
+            .await
+            .unwrap();
+    }
+
+    let game = utils::game_pda(utils::GAME_ID);
+    let reveal_ix = utils::ix(
+        accounts::RevealRound {
+            game,
+            player1_choice: choice_pda(players, 0),
+            player2_choice: choice_pda(players, 1),
+            rule_table: None,
+            permission_game: utils::permission_pda(game),
+            permission1: utils::permission_pda(choice_pda(players, 0)),
+            permission2: utils::permission_pda(choice_pda(players, 1)),
+            payer: context.payer.pubkey(),
+            permission_program: PERMISSION_PROGRAM_ID,
+            ephemeral_vault: EPHEMERAL_VAULT_ID,
+            magic_program: MAGIC_PROGRAM_ID,
+        },
+        instruction::RevealRound {},
+    );
+    utils::send(context, &[reveal_ix], &[]).await.unwrap();
+}
+
+fn choice_pda(players: &[Keypair; 2], player: usize) -> Pubkey {
+    utils::player_choice_pda(utils::GAME_ID, players[player].pubkey())
+}
+
+fn create_game_ix(players: &[Keypair; 2], stake: u64, target_wins: u8) -> Instruction {
+    utils::ix(
+        accounts::CreateGame {
+            game: utils::game_pda(utils::GAME_ID),
+            player_choice: choice_pda(players, 0),
+            vault: utils::vault_pda(utils::GAME_ID),
+            player1: players[0].pubkey(),
+            stake_mint: None,
+            player_token_account: None,
+            vault_token_account: None,
+            token_program: None,
+            associated_token_program: None,
+            system_program: system_program::ID,
+        },
+        instruction::CreateGame {
+            game_id: utils::GAME_ID,
+            stake,
+            target_wins,
+            move_timeout_secs: 0,
+        },
+    )
+}
+
+fn join_game_ix(players: &[Keypair; 2]) -> Instruction {
+    utils::ix(
+        accounts::JoinGame {
+            game: utils::game_pda(utils::GAME_ID),
+            player_choice: choice_pda(players, 1),
+            vault: utils::vault_pda(utils::GAME_ID),
+            lobby: None,
+            player: players[1].pubkey(),
+            player_token_account: None,
+            vault_token_account: None,
+            token_program: None,
+            system_program: system_program::ID,
+        },
+        instruction::JoinGame {
+            _game_id: utils::GAME_ID,
+        },
+    )
+}
+
+fn next_round_ix(players: &[Keypair; 2], payer: usize) -> Instruction {
+    let game = utils::game_pda(utils::GAME_ID);
+    utils::ix(
+        accounts::NextRound {
+            game,
+            player1_choice: choice_pda(players, 0),
+            player2_choice: choice_pda(players, 1),
+            rematch_escrow: None,
+            permission_game: utils::permission_pda(game),
+            permission1: utils::permission_pda(choice_pda(players, 0)),
+            permission2: utils::permission_pda(choice_pda(players, 1)),
+            payer: players[payer].pubkey(),
+            permission_program: PERMISSION_PROGRAM_ID,
+            ephemeral_vault: EPHEMERAL_VAULT_ID,
+            magic_program: MAGIC_PROGRAM_ID,
+        },
+        instruction::NextRound {},
+    )
+}
+
+fn undelegate_all_ix(context: &ProgramTestContext, players: &[Keypair; 2]) -> Instruction {
+    utils::ix(
+        accounts::UndelegateAll {
+            payer: context.payer.pubkey(),
+            game: utils::game_pda(utils::GAME_ID),
+            player1_choice: choice_pda(players, 0),
+            player2_choice: choice_pda(players, 1),
+            magic_context: MAGIC_CONTEXT_ID,
+            magic_program: MAGIC_PROGRAM_ID,
+        },
+        instruction::UndelegateAll {},
+    )
+}
+
+fn claim_pot_ix(
+    context: &ProgramTestContext,
+    players: &[Keypair; 2],
+    rematch_escrow: Option<Pubkey>,
+) -> Instruction {
+    utils::ix(
+        accounts::ClaimPot {
+            game: utils::game_pda(utils::GAME_ID),
+            vault: utils::vault_pda(utils::GAME_ID),
+            rematch_escrow,
+            player1: players[0].pubkey(),
+            player2: players[1].pubkey(),
+            payer: context.payer.pubkey(),
+            player1_token_account: None,
+            player2_token_account: None,
+            vault_token_account: None,
+            token_program: None,
+            player1_stats: utils::player_stats_pda(players[0].pubkey()),
+            player2_stats: utils::player_stats_pda(players[1].pubkey()),
+            leaderboard: None,
+            config: utils::config_pda(),
+            fee_recipient: None,
+            fee_recipient_token_account: None,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimPot {},
+    )
+}
+
+fn cancel_game_ix(players: &[Keypair; 2]) -> Instruction {
+    utils::ix(
+        accounts::CancelGame {
+            game: utils::game_pda(utils::GAME_ID),
+            vault: utils::vault_pda(utils::GAME_ID),
+            lobby: None,
+            player1: players[0].pubkey(),
+            player1_token_account: None,
+            vault_token_account: None,
+            token_program: None,
+            system_program: system_program::ID,
+        },
+        instruction::CancelGame {},
+    )
+}
@@ -0,0 +1,206 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_rock_paper_scissor::{
+    resolve_round, Choice, Game, GameVariant, RoundOutcome, RoundResult, CLASSIC_RULES,
+    RPSLS_RULES,
+};
+
+const CLASSIC_MOVES: [Choice; 3] = [Choice::Rock, Choice::Paper, Choice::Scissors];
+const RPSLS_MOVES: [Choice; 5] = [
+    Choice::Rock,
+    Choice::Paper,
+    Choice::Scissors,
+    Choice::Lizard,
+    Choice::Spock,
+];
+
+/// Every (winner, loser) pair of Rock-Paper-Scissors-Lizard-Spock, written out independently
+/// of the `RPSLS_RULES` bitmasks.
+const RPSLS_WINS: [(Choice, Choice); 10] = [
+    (Choice::Rock, Choice::Scissors),
+    (Choice::Rock, Choice::Lizard),
+    (Choice::Paper, Choice::Rock),
+    (Choice::Paper, Choice::Spock),
+    (Choice::Scissors, Choice::Paper),
+    (Choice::Scissors, Choice::Lizard),
+    (Choice::Lizard, Choice::Paper),
+    (Choice::Lizard, Choice::Spock),
+    (Choice::Spock, Choice::Rock),
+    (Choice::Spock, Choice::Scissors),
+];
+
+#[test]
+fn resolves_every_classic_pair() {
+    use Choice::*;
+    use RoundOutcome::*;
+    let expected = [
+        (Rock, Rock, Tie),
+        (Rock, Paper, Player2),
+        (Rock, Scissors, Player1),
+        (Paper, Rock, Player1),
+        (Paper, Paper, Tie),
+        (Paper, Scissors, Player2),
+        (Scissors, Rock, Player2),
+        (Scissors, Paper, Player1),
+        (Scissors, Scissors, Tie),
+    ];
+    for (choice1, choice2, outcome) in expected {
+        assert_eq!(
+            resolve_round(&CLASSIC_RULES, &choice1, &choice2),
+            outcome,
+            "{:?} vs {:?}",
+            choice1,
+            choice2
+        );
+    }
+}
+
+#[test]
+fn resolves_every_rpsls_pair() {
+    for choice1 in &RPSLS_MOVES {
+        for choice2 in &RPSLS_MOVES {
+            let expected = if RPSLS_WINS.contains(&(choice1.clone(), choice2.clone())) {
+                RoundOutcome::Player1
+            } else if RPSLS_WINS.contains(&(choice2.clone(), choice1.clone())) {
+                RoundOutcome::Player2
+            } else {
+                assert_eq!(choice1, choice2, "every distinct pair has a winner");
+                RoundOutcome::Tie
+            };
+            assert_eq!(
+                resolve_round(&RPSLS_RULES, choice1, choice2),
+                expected,
+                "{:?} vs {:?}",
+                choice1,
+                choice2
+            );
+        }
+    }
+}
+
+#[test]
+fn rpsls_agrees_with_classic_on_classic_moves() {
+    for choice1 in &CLASSIC_MOVES {
+        for choice2 in &CLASSIC_MOVES {
+            assert_eq!(
+                resolve_round(&RPSLS_RULES, choice1, choice2),
+                resolve_round(&CLASSIC_RULES, choice1, choice2),
+            );
+        }
+    }
+}
+
+#[test]
+fn custom_moves_index_the_rule_table() {
+    assert_eq!(
+        resolve_round(&CLASSIC_RULES, &Choice::Move(0), &Choice::Scissors),
+        RoundOutcome::Player1
+    );
+    assert_eq!(
+        resolve_round(&CLASSIC_RULES, &Choice::Move(2), &Choice::Move(0)),
+        RoundOutcome::Player2
+    );
+}
+
+#[test]
+fn moves_outside_the_table_never_win() {
+    for choice in &CLASSIC_MOVES {
+        assert_eq!(
+            resolve_round(&CLASSIC_RULES, &Choice::Lizard, choice),
+            RoundOutcome::Tie
+        );
+        assert_eq!(
+            resolve_round(&CLASSIC_RULES, choice, &Choice::Spock),
+            RoundOutcome::Tie
+        );
+    }
+}
+
+#[test]
+fn match_is_decided_at_target_wins() {
+    let player1 = Pubkey::new_unique();
+    let player2 = Pubkey::new_unique();
+
+    let game = game_with_score(player1, player2, 3, 2, 2);
+    assert!(!game.is_match_decided());
+    assert_eq!(game.match_winner(), None);
+
+    let game = game_with_score(player1, player2, 3, 3, 2);
+    assert!(game.is_match_decided());
+    assert_eq!(game.match_winner(), Some(player1));
+
+    let game = game_with_score(player1, player2, 1, 0, 1);
+    assert!(game.is_match_decided());
+    assert_eq!(game.match_winner(), Some(player2));
+}
+
+#[test]
+fn abandoned_match_is_decided_without_a_winner() {
+    let mut game = game_with_score(Pubkey::new_unique(), Pubkey::new_unique(), 3, 1, 2);
+    game.abandoned = true;
+    assert!(game.is_match_decided());
+    assert_eq!(game.match_winner(), None);
+}
+
+#[test]
+fn round_clock_uses_the_game_move_timeout() {
+    let mut game = game_with_score(Pubkey::new_unique(), Pubkey::new_unique(), 1, 0, 0);
+    game.move_timeout_secs = 600;
+    game.start_round_clock(1_000);
+    assert_eq!(game.round_deadline_ts, 1_600);
+    assert!(!game.is_round_expired(1_599));
+    assert!(game.is_round_expired(1_600));
+}
+
+#[test]
+fn restarted_free_match_is_parked_for_claim_pot() {
+    let player1 = Pubkey::new_unique();
+    let player2 = Pubkey::new_unique();
+    let mut game = game_with_score(player1, player2, 2, 1, 2);
+    game.tied_rounds = 3;
+    game.defer_match_result().unwrap();
+    game.tied_rounds = 0;
+    game.player1_wins = 2;
+    game.player2_wins = 0;
+    game.defer_match_result().unwrap();
+    assert_eq!(game.unrecorded_wins, [1, 1]);
+    assert_eq!(game.unrecorded_ties, 3);
+
+    let mut game = game_with_score(player1, player2, 2, 1, 1);
+    assert!(game.defer_match_result().is_err());
+}
+
+fn game_with_score(
+    player1: Pubkey,
+    player2: Pubkey,
+    target_wins: u8,
+    player1_wins: u8,
+    player2_wins: u8,
+) -> Game {
+    Game {
+        game_id: 1,
+        player1: Some(player1),
+        player2: Some(player2),
+        player1_choice: None,
+        player2_choice: None,
+        round_result: RoundResult::None,
+        stake: 0,
+        stake_mint: None,
+        paid: false,
+        target_wins,
+        player1_wins,
+        player2_wins,
+        round: 1,
+        round_deadline_ts: 0,
+        listed: false,
+        tied_rounds: 0,
+        tournament: None,
+        variant: GameVariant::Classic,
+        match_number: 1,
+        rematch_ready: false,
+        spectators: Vec::new(),
+        move_timeout_secs: anchor_rock_paper_scissor::MOVE_TIMEOUT_SECS,
+        abandoned: false,
+        unrecorded_wins: [0; 2],
+        unrecorded_ties: 0,
+    }
+}
@@ -0,0 +1,160 @@
+#![allow(dead_code)]
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{Instruction, InstructionError},
+    system_program,
+};
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_rock_paper_scissor::{
+    GameError, CONFIG_SEED, GAME_SEED, PLAYER_CHOICE_SEED, PLAYER_STATS_SEED, REMATCH_SEED,
+    VAULT_SEED,
+};
+use ephemeral_rollups_sdk::access_control::structs::PERMISSION_SEED;
+use ephemeral_rollups_sdk::consts::{MAGIC_PROGRAM_ID, PERMISSION_PROGRAM_ID};
+use solana_account::Account;
+use solana_keypair::Keypair;
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+
+pub const GAME_ID: u64 = 1;
+pub const STAKE: u64 = 100_000_000;
+pub const PLAYER_LAMPORTS: u64 = 1_000_000_000;
+
+/// The program plus stubs for the magic and permission programs, whose effects only exist on
+/// the ER, and a wallet for each of `players`. Nothing is actually delegated: the ER-only
+/// instructions run directly against the program-owned PDAs.
+pub fn program_test(players: &[&Keypair]) -> ProgramTest {
+    let mut program_test = ProgramTest::new(
+        "anchor_rock_paper_scissor",
+        anchor_rock_paper_scissor::ID,
+        None,
+    );
+    program_test.prefer_bpf(false);
+    program_test.add_program("magic_program", MAGIC_PROGRAM_ID, processor!(accept_stub));
+    program_test.add_program(
+        "magicblock_permission_program",
+        PERMISSION_PROGRAM_ID,
+        processor!(accept_stub),
+    );
+    for player in players {
+        program_test.add_account(
+            player.pubkey(),
+            Account {
+                lamports: PLAYER_LAMPORTS,
+                data: Vec::new(),
+                owner: system_program::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+    program_test
+}
+
+pub fn ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: anchor_rock_paper_scissor::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Sends `ixs` paid by the context payer and co-signed by `signers`.
+pub async fn send(
+    context: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransactionError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(
+        ixs,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+pub async fn read<T: AccountDeserialize>(context: &mut ProgramTestContext, address: Pubkey) -> T {
+    let account = context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .expect("account must exist");
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub async fn balance(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+    context.banks_client.get_balance(address).await.unwrap()
+}
+
+pub fn assert_program_error(result: Result<(), TransactionError>, expected: GameError) {
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(0, InstructionError::Custom(expected.into())),
+    );
+}
+
+pub fn game_pda(game_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[GAME_SEED, &game_id.to_le_bytes()],
+        &anchor_rock_paper_scissor::ID,
+    )
+    .0
+}
+
+pub fn vault_pda(game_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[VAULT_SEED, &game_id.to_le_bytes()],
+        &anchor_rock_paper_scissor::ID,
+    )
+    .0
+}
+
+pub fn player_choice_pda(game_id: u64, player: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[PLAYER_CHOICE_SEED, &game_id.to_le_bytes(), player.as_ref()],
+        &anchor_rock_paper_scissor::ID,
+    )
+    .0
+}
+
+pub fn player_stats_pda(player: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[PLAYER_STATS_SEED, player.as_ref()],
+        &anchor_rock_paper_scissor::ID,
+    )
+    .0
+}
+
+pub fn rematch_escrow_pda(game_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[REMATCH_SEED, &game_id.to_le_bytes()],
+        &anchor_rock_paper_scissor::ID,
+    )
+    .0
+}
+
+pub fn config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED], &anchor_rock_paper_scissor::ID).0
+}
+
+pub fn permission_pda(account: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[PERMISSION_SEED, account.as_ref()], &PERMISSION_PROGRAM_ID).0
+}
+
+fn accept_stub(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
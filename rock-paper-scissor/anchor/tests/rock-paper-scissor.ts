@@ -205,7 +205,7 @@ describe("anchor-rock-paper-scissor", () => {
     // rent. We delegate p1_choice now, but the game stays on base until player 2
     // joins — join_game needs the game to still be owned by our program.
     const createGameIx = await program.methods
-      .createGame(gameId, STAKE, TARGET_WINS)
+      .createGame(gameId, STAKE, TARGET_WINS, 0)
       .accountsPartial({
         game: gamePda,
         playerChoice: player1ChoicePda,
@@ -30,4 +30,32 @@ pub enum ErrorCode {
     DuplicateBid,
     #[msg("not all bid accounts are closed")]
     UnclosedBids,
+    #[msg("bid accounts must be tallied in bidder index order")]
+    BidOutOfOrder,
+    #[msg("proposed price does not clear the tallied demand")]
+    ClearingPriceMismatch,
+    #[msg("lot allocation already claimed")]
+    LotAlreadyClaimed,
+    #[msg("anti-sniping window and extension count must be greater than zero")]
+    InvalidExtensionConfig,
+    #[msg("reveal window must be greater than zero")]
+    InvalidRevealWindow,
+    #[msg("bid terms do not match the auction bid privacy mode")]
+    BidPrivacyMismatch,
+    #[msg("reveal window has closed")]
+    RevealClosed,
+    #[msg("revealed bid does not match its commitment")]
+    CommitmentMismatch,
+    #[msg("bid deposit does not cover the revealed bid")]
+    UndercollateralizedBid,
+    #[msg("unrevealed bids are slashed, not refunded")]
+    UnrevealedBid,
+    #[msg("NFT lots must sell a single unit in a single-unit auction")]
+    InvalidNftLot,
+    #[msg("missing token account for the bid currency")]
+    MissingPaymentAccount,
+    #[msg("SOL deposit does not cover the bid")]
+    InsufficientSolDeposit,
+    #[msg("SOL deposit still backs an open bid")]
+    SolDepositLocked,
 }
@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{AuctionKind, AuctionStatus, BidCurrency, BidPrivacy, LotKind};
+
+#[event]
+pub struct AuctionInitialized {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub auctioneer: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub bid_currency: BidCurrency,
+    pub lot_kind: LotKind,
+    pub lot_amount: u64,
+    pub deadline_ts: i64,
+    pub kind: AuctionKind,
+    pub bid_privacy: BidPrivacy,
+}
+
+/// Emitted without the bidder or the amount, so it is safe to publish while bids are private.
+#[event]
+pub struct BidPlaced {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub bidder_index: u16,
+    pub bid_count: u16,
+    pub deadline_ts: i64,
+}
+
+/// Like `BidPlaced`, carries neither the bidder nor the new amount.
+#[event]
+pub struct BidUpdated {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub bidder_index: u16,
+    pub deadline_ts: i64,
+}
+
+/// `bidder_index` is the freed slot, which now holds the bid that had the last index.
+#[event]
+pub struct BidWithdrawn {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub bidder_index: u16,
+    pub bid_count: u16,
+}
+
+#[event]
+pub struct BidRevealed {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub bidder: Pubkey,
+    pub unit_price: u64,
+    pub quantity: u64,
+}
+
+/// Emitted by every `end_auction` call; `status` stays `Tallying` until all bids are counted.
+#[event]
+pub struct AuctionTallied {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub status: AuctionStatus,
+    pub tallied: u16,
+    pub bid_count: u16,
+    pub highest_bid: u64,
+    pub highest_bidder: Pubkey,
+    pub second_bid: u64,
+    pub clearing_price: u64,
+}
+
+#[event]
+pub struct AuctionCancelled {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub bid_count: u16,
+}
+
+#[event]
+pub struct WinningBidSettled {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub winner: Pubkey,
+    pub payment: u64,
+    pub refund: u64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub bidder: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BidSlashed {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub bidder: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LotFinalized {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub winner: Pubkey,
+    pub quantity: u64,
+    pub status: AuctionStatus,
+}
+
+#[event]
+pub struct UnsoldLotReclaimed {
+    pub auction: Pubkey,
+    pub auction_id: u64,
+    pub amount: u64,
+    pub status: AuctionStatus,
+}
+
+#[event]
+pub struct SolDepositReleased {
+    pub auction: Pubkey,
+    pub bidder: Pubkey,
+    pub seller_amount: u64,
+    pub refund: u64,
+}
@@ -20,16 +20,28 @@ use ephemeral_rollups_sdk::{
     ephem::MagicIntentBundleBuilder,
 };
 
-mod error;
-mod state;
+pub mod error;
+pub mod events;
+pub mod state;
 
 use error::ErrorCode;
-use state::{Auction, AuctionStatus, Bid, MAX_BIDDERS};
+use events::{
+    AuctionCancelled, AuctionInitialized, AuctionTallied, BidPlaced, BidRevealed, BidSlashed,
+    BidUpdated, BidWithdrawn, LotFinalized, RefundClaimed, SolDepositReleased, UnsoldLotReclaimed,
+    WinningBidSettled,
+};
+use state::{
+    AntiSnipingConfig, Auction, AuctionKind, AuctionStatus, AuctionTally, Bid, BidCurrency,
+    BidPrivacy, BidTerms, LotAllocation, LotKind, SolDeposit, MAX_LOT_WINNERS,
+    SPONSORED_PERMISSION_MEMBERS,
+};
 
 declare_id!("F4vB5Ki7ZWnkht1shp2TCHG7GszLxRZ6pbizGNQecmor");
 
 pub const AUCTION_SEED: &[u8] = b"auction";
 pub const BID_SEED: &[u8] = b"bid";
+pub const BID_VAULT_SEED: &[u8] = b"bid_vault";
+pub const SOL_DEPOSIT_SEED: &[u8] = b"sol_deposit";
 pub const EPHEMERAL_SPL_TOKEN_PROGRAM_ID: Pubkey =
     pubkey!("SPLxh1LVZzEkX99H6rqYizhytLWPZVV296zyYDPagv2");
 pub const DELEGATION_PROGRAM_ID: Pubkey = pubkey!("DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh");
@@ -41,49 +53,41 @@ const DELEGATE_EPHEMERAL_ATA_DISCRIMINATOR: u8 = 4;
 pub mod sealed_auction {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_auction(
         ctx: Context<InitializeAuction>,
         auction_id: u64,
         lot_amount: u64,
         deadline_ts: i64,
         sponsor_lamports: u64,
+        kind: AuctionKind,
+        anti_sniping: Option<AntiSnipingConfig>,
+        bid_privacy: BidPrivacy,
     ) -> Result<()> {
-        require!(lot_amount > 0, ErrorCode::InvalidAmount);
-        require!(
-            deadline_ts > Clock::get()?.unix_timestamp,
-            ErrorCode::DeadlineInPast
-        );
-
-        transfer_lamports(
-            CpiContext::new(
-                ctx.accounts.system_program.key(),
-                LamportsTransfer {
-                    from: ctx.accounts.auctioneer.to_account_info(),
-                    to: ctx.accounts.auction.to_account_info(),
-                },
-            ),
-            ephemeral_rollups_sdk::ephemeral_accounts::rent(EphemeralPermission::size_of(
-                MAX_BIDDERS + 1,
-            ) as u32)
-            .checked_add(sponsor_lamports)
-            .ok_or(ErrorCode::InvalidAmount)?,
-        )?;
-
         let auction_key = ctx.accounts.auction.key();
-        let auction = &mut ctx.accounts.auction;
-        auction.auctioneer = ctx.accounts.auctioneer.key();
-        auction.auction_id = auction_id;
-        auction.token_a_mint = ctx.accounts.token_a_mint.key();
-        auction.token_b_mint = ctx.accounts.token_b_mint.key();
-        auction.lot_amount = lot_amount;
-        auction.deadline_ts = deadline_ts;
-        auction.bid_count = 0;
-        auction.closed_bid_count = 0;
-        auction.highest_bid = 0;
-        auction.highest_bidder = Pubkey::default();
-        auction.status = AuctionStatus::Open;
-        auction.lot_claimed = false;
-        auction.bump = ctx.bumps.auction;
+        init_auction_state(
+            &mut ctx.accounts.auction,
+            AuctionSetup {
+                auctioneer: ctx.accounts.auctioneer.key(),
+                auction_id,
+                token_a_mint: &ctx.accounts.token_a_mint,
+                token_b_mint: ctx.accounts.token_b_mint.key(),
+                bid_currency: BidCurrency::Token,
+                bid_vault_bump: 0,
+                lot_amount,
+                deadline_ts,
+                kind,
+                anti_sniping,
+                bid_privacy,
+                bump: ctx.bumps.auction,
+            },
+        )?;
+        fund_auction_sponsor(
+            &ctx.accounts.auctioneer,
+            ctx.accounts.auction.to_account_info(),
+            &ctx.accounts.system_program,
+            sponsor_lamports,
+        )?;
 
         token::transfer(
             CpiContext::new(
@@ -118,6 +122,7 @@ pub mod sealed_auction {
             validator,
         )?;
 
+        emit_auction_initialized(auction_key, &ctx.accounts.auction);
         msg!(
             "Initialized auction {} with Token A lot {} held on L1",
             auction_key,
@@ -126,6 +131,80 @@ pub mod sealed_auction {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_sol_auction(
+        ctx: Context<InitializeSolAuction>,
+        auction_id: u64,
+        lot_amount: u64,
+        deadline_ts: i64,
+        sponsor_lamports: u64,
+        kind: AuctionKind,
+        anti_sniping: Option<AntiSnipingConfig>,
+        bid_privacy: BidPrivacy,
+    ) -> Result<()> {
+        let auction_key = ctx.accounts.auction.key();
+        init_auction_state(
+            &mut ctx.accounts.auction,
+            AuctionSetup {
+                auctioneer: ctx.accounts.auctioneer.key(),
+                auction_id,
+                token_a_mint: &ctx.accounts.token_a_mint,
+                token_b_mint: Pubkey::default(),
+                bid_currency: BidCurrency::Lamports,
+                bid_vault_bump: ctx.bumps.bid_vault,
+                lot_amount,
+                deadline_ts,
+                kind,
+                anti_sniping,
+                bid_privacy,
+                bump: ctx.bumps.auction,
+            },
+        )?;
+        fund_auction_sponsor(
+            &ctx.accounts.auctioneer,
+            ctx.accounts.auction.to_account_info(),
+            &ctx.accounts.system_program,
+            sponsor_lamports,
+        )?;
+
+        // The vault only pays out what deposits put in, so seed it with rent to keep it alive.
+        let vault_rent = Rent::get()?
+            .minimum_balance(0)
+            .saturating_sub(ctx.accounts.bid_vault.lamports());
+        if vault_rent > 0 {
+            transfer_lamports(
+                CpiContext::new(
+                    ctx.accounts.system_program.key(),
+                    LamportsTransfer {
+                        from: ctx.accounts.auctioneer.to_account_info(),
+                        to: ctx.accounts.bid_vault.to_account_info(),
+                    },
+                ),
+                vault_rent,
+            )?;
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.key(),
+                SplTransfer {
+                    from: ctx.accounts.seller_token_a_account.to_account_info(),
+                    to: ctx.accounts.auction_token_a_account.to_account_info(),
+                    authority: ctx.accounts.auctioneer.to_account_info(),
+                },
+            ),
+            lot_amount,
+        )?;
+
+        emit_auction_initialized(auction_key, &ctx.accounts.auction);
+        msg!(
+            "Initialized SOL auction {} with Token A lot {} held on L1",
+            auction_key,
+            lot_amount
+        );
+        Ok(())
+    }
+
     pub fn delegate_auction(ctx: Context<DelegateAuction>, auction_id: u64) -> Result<()> {
         let validator = ctx.accounts.validator.as_ref().map(|v| v.key());
         let auctioneer = ctx.accounts.auctioneer.key();
@@ -141,6 +220,56 @@ pub mod sealed_auction {
         Ok(())
     }
 
+    /// Moves lamports into the L1 bid vault as collateral for the bidder's SOL bids.
+    ///
+    /// Runs on L1 while the bidder's `SolDeposit` is not delegated. The deposit is public, so it
+    /// should cover the largest bid the bidder might place.
+    pub fn deposit_sol(ctx: Context<DepositSol>, auction_id: u64, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        transfer_lamports(
+            CpiContext::new(
+                ctx.accounts.system_program.key(),
+                LamportsTransfer {
+                    from: ctx.accounts.bidder.to_account_info(),
+                    to: ctx.accounts.bid_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let deposit = &mut ctx.accounts.sol_deposit;
+        deposit.auction = ctx.accounts.auction.key();
+        deposit.bidder = ctx.accounts.bidder.key();
+        deposit.amount = deposit
+            .amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::InvalidAmount)?;
+        deposit.bump = ctx.bumps.sol_deposit;
+        msg!(
+            "Deposited {} lamports for auction {} ({})",
+            amount,
+            deposit.auction,
+            auction_id
+        );
+        Ok(())
+    }
+
+    pub fn delegate_sol_deposit(ctx: Context<DelegateSolDeposit>, auction_id: u64) -> Result<()> {
+        let validator = ctx.accounts.validator.as_ref().map(|v| v.key());
+        let auction = ctx.accounts.auction.key();
+        let bidder = ctx.accounts.bidder.key();
+        msg!("Delegating SOL deposit for auction {}", auction_id);
+        ctx.accounts.delegate_sol_deposit(
+            &ctx.accounts.bidder,
+            &[SOL_DEPOSIT_SEED, auction.as_ref(), bidder.as_ref()],
+            DelegateConfig {
+                validator,
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
     pub fn init_auction_permission(ctx: Context<AuctionPermission>, auction_id: u64) -> Result<()> {
         require_eq!(ctx.accounts.auction.auction_id, auction_id);
         if ctx.accounts.permission.lamports() > 0 {
@@ -174,53 +303,62 @@ pub mod sealed_auction {
         Ok(())
     }
 
-    pub fn place_bid(ctx: Context<PlaceBid>, auction_id: u64, amount: u64) -> Result<()> {
-        require!(amount > 0, ErrorCode::InvalidAmount);
+    pub fn place_bid(ctx: Context<PlaceBid>, auction_id: u64, terms: BidTerms) -> Result<()> {
         require_eq!(ctx.accounts.auction.auction_id, auction_id);
-        require!(
-            ctx.accounts.auction.status == AuctionStatus::Open,
-            ErrorCode::AuctionClosed
-        );
-        require!(
-            Clock::get()?.unix_timestamp < ctx.accounts.auction.deadline_ts,
-            ErrorCode::AuctionClosed
-        );
-        require!(
-            (ctx.accounts.auction.bid_count as usize) < MAX_BIDDERS,
-            ErrorCode::TooManyBidders
-        );
+        require_bidding_open(&ctx.accounts.auction)?;
+        let bidder_index = ctx.accounts.auction.bid_count;
+        let next_bid_count = bidder_index
+            .checked_add(1)
+            .ok_or(ErrorCode::TooManyBidders)?;
 
         let auction_key = ctx.accounts.auction.key();
-        let bidder = ctx.accounts.bidder.key();
+        let mut bid = Bid {
+            auction: auction_key,
+            bidder: ctx.accounts.bidder.key(),
+            amount: 0,
+            quantity: 0,
+            unit_price: 0,
+            bidder_index,
+            escrow: Pubkey::default(),
+            commitment: [0; 32],
+            revealed: false,
+            bump: ctx.bumps.bid,
+        };
+        apply_bid_terms(&ctx.accounts.auction, &mut bid, terms)?;
+        let amount = bid.amount;
 
         ctx.accounts.create_ephemeral_bid((8 + Bid::LEN) as u32)?;
 
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.key(),
-                SplTransfer {
-                    from: ctx.accounts.bidder_token_b_account.to_account_info(),
-                    to: ctx.accounts.auction_token_b_account.to_account_info(),
-                    authority: ctx.accounts.bidder.to_account_info(),
-                },
-            ),
+        let mut escrow = BidEscrow::new(
+            &ctx.accounts.auction,
+            ctx.accounts.auction_token_b_account.as_deref(),
+            ctx.accounts.sol_deposit.as_mut(),
+            &ctx.accounts.token_program,
+        )?;
+        bid.escrow = escrow.key();
+        escrow.collect(
+            ctx.accounts
+                .bidder_token_b_account
+                .as_ref()
+                .map(|account| account.to_account_info()),
+            ctx.accounts.bidder.to_account_info(),
+            amount,
             amount,
         )?;
 
-        let bidder_index = ctx.accounts.auction.bid_count;
-        let bid = Bid {
-            auction: auction_key,
-            bidder,
-            amount,
-            bidder_index,
-            escrow: ctx.accounts.auction_token_b_account.key(),
-            bump: ctx.bumps.bid,
-        };
         write_bid(&ctx.accounts.bid.to_account_info(), &bid)?;
 
-        ctx.accounts.auction.bid_count = bidder_index + 1;
+        ctx.accounts.auction.bid_count = next_bid_count;
+        extend_deadline(&mut ctx.accounts.auction)?;
 
-        msg!("Bid {} placed for auction {}", amount, auction_key);
+        emit!(BidPlaced {
+            auction: auction_key,
+            auction_id,
+            bidder_index,
+            bid_count: next_bid_count,
+            deadline_ts: ctx.accounts.auction.deadline_ts,
+        });
+        msg!("Bid {} placed for auction {}", bid.bidder_index, auction_key);
         Ok(())
     }
 
@@ -264,7 +402,7 @@ pub mod sealed_auction {
             magic_program: ctx.accounts.magic_program.to_account_info(),
             permission_program: ctx.accounts.permission_program.to_account_info(),
             args: EphemeralMembersArgs {
-                is_private: true,
+                is_private: ctx.accounts.auction.bid_privacy == BidPrivacy::Tee,
                 members: bid_members,
             },
         }
@@ -274,30 +412,235 @@ pub mod sealed_auction {
         Ok(())
     }
 
-    pub fn end_auction(ctx: Context<EndAuction>, auction_id: u64) -> Result<()> {
+    pub fn update_bid(ctx: Context<UpdateBid>, auction_id: u64, terms: BidTerms) -> Result<()> {
+        require_eq!(ctx.accounts.auction.auction_id, auction_id);
+        require_bidding_open(&ctx.accounts.auction)?;
+
+        let bid_info = ctx.accounts.bid.to_account_info();
+        let mut bid = read_bid(&bid_info)?;
+        let mut escrow = BidEscrow::new(
+            &ctx.accounts.auction,
+            ctx.accounts.auction_token_b_account.as_deref(),
+            ctx.accounts.sol_deposit.as_mut(),
+            &ctx.accounts.token_program,
+        )?;
+        require_keys_eq!(bid.escrow, escrow.key(), ErrorCode::InvalidBidEscrow);
+        let previous_amount = bid.amount;
+        apply_bid_terms(&ctx.accounts.auction, &mut bid, terms)?;
+        let amount = bid.amount;
+
+        let bidder_funds = ctx
+            .accounts
+            .bidder_token_b_account
+            .as_ref()
+            .map(|account| account.to_account_info());
+        if amount > previous_amount {
+            escrow.collect(
+                bidder_funds,
+                ctx.accounts.bidder.to_account_info(),
+                amount - previous_amount,
+                amount,
+            )?;
+        } else if amount < previous_amount {
+            escrow.refund(
+                &ctx.accounts.auction,
+                bidder_funds,
+                previous_amount - amount,
+            )?;
+        }
+
+        write_bid(&bid_info, &bid)?;
+        extend_deadline(&mut ctx.accounts.auction)?;
+
+        emit!(BidUpdated {
+            auction: bid.auction,
+            auction_id,
+            bidder_index: bid.bidder_index,
+            deadline_ts: ctx.accounts.auction.deadline_ts,
+        });
+        msg!("Bid {} updated for auction {}", bid_info.key(), bid.auction);
+        Ok(())
+    }
+
+    pub fn withdraw_bid(ctx: Context<WithdrawBid>, auction_id: u64) -> Result<()> {
+        require_eq!(ctx.accounts.auction.auction_id, auction_id);
+        require_bidding_open(&ctx.accounts.auction)?;
+
+        let auction = ctx.accounts.auction.key();
+        let bid_info = ctx.accounts.bid.to_account_info();
+        let bid = read_bid(&bid_info)?;
+        let mut escrow = BidEscrow::new(
+            &ctx.accounts.auction,
+            ctx.accounts.auction_token_b_account.as_deref(),
+            ctx.accounts.sol_deposit.as_mut(),
+            &ctx.accounts.token_program,
+        )?;
+        require_keys_eq!(bid.escrow, escrow.key(), ErrorCode::InvalidBidEscrow);
+        escrow.refund(
+            &ctx.accounts.auction,
+            ctx.accounts
+                .bidder_token_b_account
+                .as_ref()
+                .map(|account| account.to_account_info()),
+            bid.amount,
+        )?;
+        escrow.release();
+
+        // Keep bidder indexes dense for `end_auction` by moving the last bid into the freed slot.
+        let last_index = ctx
+            .accounts
+            .auction
+            .bid_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::MissingBid)?;
+        if bid.bidder_index != last_index {
+            let last_bid_info = ctx
+                .accounts
+                .last_bid
+                .as_ref()
+                .ok_or(ErrorCode::MissingBid)?
+                .to_account_info();
+            let mut moved_bid = read_bid(&last_bid_info)?;
+            require_keys_eq!(moved_bid.auction, auction, ErrorCode::InvalidBid);
+            require_eq!(moved_bid.bidder_index, last_index, ErrorCode::InvalidBid);
+            let expected_bid = Pubkey::find_program_address(
+                &[BID_SEED, auction.as_ref(), moved_bid.bidder.as_ref()],
+                ctx.program_id,
+            )
+            .0;
+            require_keys_eq!(last_bid_info.key(), expected_bid, ErrorCode::InvalidBid);
+            moved_bid.bidder_index = bid.bidder_index;
+            write_bid(&last_bid_info, &moved_bid)?;
+        }
+
+        let auction_id_bytes = ctx.accounts.auction.auction_id.to_le_bytes();
+        let auction_bump = [ctx.accounts.auction.bump];
+        let auction_signers: &[&[u8]] = &[
+            AUCTION_SEED,
+            ctx.accounts.auction.auctioneer.as_ref(),
+            &auction_id_bytes,
+            &auction_bump,
+        ];
+        let bid_bump = [bid.bump];
+        let bid_seeds: &[&[u8]] = &[
+            BID_SEED,
+            bid.auction.as_ref(),
+            bid.bidder.as_ref(),
+            &bid_bump,
+        ];
+        close_bid_permission(&CloseBidPermissionAccounts {
+            sponsor: ctx.accounts.auction.to_account_info(),
+            bid: ctx.accounts.bid.to_account_info(),
+            permission: ctx.accounts.bid_permission.to_account_info(),
+            ephemeral_vault: ctx.accounts.vault.to_account_info(),
+            magic_program: ctx.accounts.magic_program.to_account_info(),
+            permission_program: ctx.accounts.permission_program.to_account_info(),
+            sponsor_signers: auction_signers,
+            bid_signers: bid_seeds,
+        })?;
+        ctx.accounts.close_ephemeral_bid()?;
+        ctx.accounts.auction.bid_count = last_index;
+
+        emit!(BidWithdrawn {
+            auction,
+            auction_id,
+            bidder_index: bid.bidder_index,
+            bid_count: last_index,
+        });
+        msg!("Bid {} withdrawn from auction {}", bid_info.key(), auction);
+        Ok(())
+    }
+
+    pub fn reveal_bid(
+        ctx: Context<RevealBid>,
+        auction_id: u64,
+        unit_price: u64,
+        quantity: u64,
+        salt: [u8; 32],
+    ) -> Result<()> {
         require_eq!(ctx.accounts.auction.auction_id, auction_id);
         require!(
             ctx.accounts.auction.status == AuctionStatus::Open,
             ErrorCode::AuctionClosed
         );
+        let now = Clock::get()?.unix_timestamp;
         require!(
-            Clock::get()?.unix_timestamp >= ctx.accounts.auction.deadline_ts,
+            now >= ctx.accounts.auction.deadline_ts,
             ErrorCode::AuctionStillOpen
         );
+        require!(
+            now < ctx.accounts.auction.reveal_deadline_ts(),
+            ErrorCode::RevealClosed
+        );
+
+        let bid_info = ctx.accounts.bid.to_account_info();
+        let mut bid = read_bid(&bid_info)?;
+        require_keys_eq!(
+            bid.auction,
+            ctx.accounts.auction.key(),
+            ErrorCode::InvalidBid
+        );
+        require!(bid.is_unrevealed(), ErrorCode::InvalidBid);
+        require!(
+            Bid::commitment_for(&bid.auction, &bid.bidder, unit_price, quantity, &salt)
+                == bid.commitment,
+            ErrorCode::CommitmentMismatch
+        );
+        let value = bid_amount(&ctx.accounts.auction, unit_price, quantity)?;
+        require!(value <= bid.amount, ErrorCode::UndercollateralizedBid);
+
+        bid.unit_price = unit_price;
+        bid.quantity = quantity;
+        bid.revealed = true;
+        write_bid(&bid_info, &bid)?;
+
+        emit!(BidRevealed {
+            auction: bid.auction,
+            auction_id,
+            bidder: bid.bidder,
+            unit_price,
+            quantity,
+        });
+
+        msg!("Bid {} revealed at {}", bid_info.key(), value);
+        Ok(())
+    }
+
+    pub fn end_auction(
+        ctx: Context<EndAuction>,
+        auction_id: u64,
+        proposed_price: u64,
+    ) -> Result<()> {
+        require_eq!(ctx.accounts.auction.auction_id, auction_id);
+        match ctx.accounts.auction.status {
+            AuctionStatus::Open => require!(
+                Clock::get()?.unix_timestamp >= ctx.accounts.auction.reveal_deadline_ts(),
+                ErrorCode::AuctionStillOpen
+            ),
+            AuctionStatus::Tallying => {}
+            _ => return err!(ErrorCode::AuctionClosed),
+        }
 
         let auction_key = ctx.accounts.auction.key();
-        let bid_count = ctx.accounts.auction.bid_count as usize;
+        let bid_count = ctx.accounts.auction.bid_count;
+        let multi_unit = ctx.accounts.auction.kind.is_multi_unit();
+        let mut tally = ctx.accounts.auction.tally;
+        let mut allocations = ctx.accounts.auction.allocations.clone();
+        if multi_unit && (tally.next_index == 0 || tally.proposed_price != proposed_price) {
+            // A new clearing-price proposal restarts the uniform-price tally from the first bid.
+            tally = AuctionTally {
+                proposed_price,
+                ..Default::default()
+            };
+            allocations.clear();
+        }
         require!(
-            ctx.remaining_accounts.len() == bid_count,
+            tally.next_index == bid_count || !ctx.remaining_accounts.is_empty(),
             ErrorCode::MissingBid
         );
 
-        let mut highest_bid = 0;
-        let mut highest_bidder = Pubkey::default();
-        let mut highest_index = u8::MAX;
-
-        for index in 0..bid_count {
-            let bid_info = &ctx.remaining_accounts[index];
+        for bid_info in ctx.remaining_accounts {
+            require!(tally.next_index < bid_count, ErrorCode::InvalidBid);
             let bid = Account::<Bid>::try_from(bid_info)?;
             require_keys_eq!(bid.auction, auction_key, ErrorCode::InvalidBid);
             let expected_bid = Pubkey::find_program_address(
@@ -306,38 +649,134 @@ pub mod sealed_auction {
             )
             .0;
             require_keys_eq!(bid_info.key(), expected_bid, ErrorCode::InvalidBid);
+            // An index below the cursor was already tallied, so the bid was passed twice.
             require!(
-                (bid.bidder_index as usize) < bid_count,
-                ErrorCode::InvalidBid
+                bid.bidder_index >= tally.next_index,
+                ErrorCode::DuplicateBid
             );
+            require_eq!(bid.bidder_index, tally.next_index, ErrorCode::BidOutOfOrder);
 
-            for previous_index in 0..index {
-                let previous_info = &ctx.remaining_accounts[previous_index];
-                require_keys_neq!(bid_info.key(), previous_info.key(), ErrorCode::DuplicateBid);
-                let previous_bid = Account::<Bid>::try_from(previous_info)?;
-                require!(
-                    bid.bidder_index != previous_bid.bidder_index,
-                    ErrorCode::DuplicateBid
-                );
-            }
-
-            if bid.amount > highest_bid
-                || (bid.amount == highest_bid && bid.bidder_index < highest_index)
+            if bid.is_unrevealed() {
+                // Bids never revealed carry no terms; their deposit is taken by `slash_bid`.
+            } else if multi_unit
+                && bid.unit_price >= proposed_price
+                && allocations.len() == MAX_LOT_WINNERS
             {
-                highest_bid = bid.amount;
-                highest_bidder = bid.bidder;
-                highest_index = bid.bidder_index;
+                // The allocation table is full: later bids at or above the price lose in
+                // `bidder_index` order, add no demand and are refunded through `claim_refund`.
+            } else if multi_unit {
+                let demand = if bid.unit_price > proposed_price {
+                    &mut tally.demand_above
+                } else if bid.unit_price == proposed_price {
+                    &mut tally.demand_at
+                } else {
+                    &mut tally.demand_below
+                };
+                *demand = demand
+                    .checked_add(bid.quantity)
+                    .ok_or(ErrorCode::InvalidAmount)?;
+                if bid.unit_price >= proposed_price {
+                    allocations.push(LotAllocation {
+                        bidder: bid.bidder,
+                        quantity: bid.quantity,
+                        unit_price: bid.unit_price,
+                        filled: 0,
+                        claimed: false,
+                    });
+                }
+            } else if bid.value() > tally.highest_bid {
+                // Bids are visited in `bidder_index` order, so a strict comparison keeps the lower
+                // index on ties and the tied amount becomes the runner-up. `withdraw_bid` moves the
+                // last bid into a freed index, so this is not always placement order.
+                tally.second_bid = tally.highest_bid;
+                tally.highest_bid = bid.value();
+                tally.highest_bidder = bid.bidder;
+            } else if bid.value() > tally.second_bid {
+                tally.second_bid = bid.value();
             }
+            tally.next_index += 1;
         }
 
         let auction = &mut ctx.accounts.auction;
-        auction.highest_bid = highest_bid;
-        auction.highest_bidder = highest_bidder;
+        auction.tally = tally;
+        auction.allocations = allocations;
+        if tally.next_index < bid_count {
+            auction.status = AuctionStatus::Tallying;
+            emit_auction_tallied(auction_key, auction);
+            msg!(
+                "Tallied {} of {} bids for auction {}",
+                tally.next_index,
+                bid_count,
+                auction_key
+            );
+            return Ok(());
+        }
+
+        if multi_unit {
+            clear_uniform_price(auction)?;
+            auction.status = AuctionStatus::Ended;
+            emit_auction_tallied(auction_key, auction);
+            msg!(
+                "Ended auction {} at uniform price {} with {} winners",
+                auction_key,
+                auction.clearing_price,
+                auction.allocations.len()
+            );
+            return Ok(());
+        }
+
+        auction.second_bid = tally.second_bid;
+        let clearing_price = auction
+            .kind
+            .clearing_price(tally.highest_bid, tally.second_bid);
+        match clearing_price {
+            Some(clearing_price) => {
+                auction.highest_bid = tally.highest_bid;
+                auction.highest_bidder = tally.highest_bidder;
+                auction.clearing_price = clearing_price;
+            }
+            None => {
+                // An unmet reserve leaves the lot unsold and every bid refundable.
+                auction.highest_bid = 0;
+                auction.highest_bidder = Pubkey::default();
+                auction.clearing_price = 0;
+            }
+        }
         auction.status = AuctionStatus::Ended;
+        emit_auction_tallied(auction_key, auction);
+        msg!(
+            "Ended auction {} with winner {} at {}",
+            auction_key,
+            auction.highest_bidder,
+            auction.clearing_price
+        );
+        Ok(())
+    }
+
+    pub fn cancel_auction(ctx: Context<CancelAuction>, auction_id: u64) -> Result<()> {
+        require_eq!(ctx.accounts.auction.auction_id, auction_id);
+        // The seller cannot back out of a recorded result, but an auction that is never tallied to
+        // one must not strand the bid deposits.
+        require!(
+            matches!(
+                ctx.accounts.auction.status,
+                AuctionStatus::Open | AuctionStatus::Tallying
+            ),
+            ErrorCode::AuctionClosed
+        );
+
+        let auction_key = ctx.accounts.auction.key();
+        let auction = &mut ctx.accounts.auction;
+        auction.status = AuctionStatus::Cancelled;
+        emit!(AuctionCancelled {
+            auction: auction_key,
+            auction_id,
+            bid_count: auction.bid_count,
+        });
         msg!(
-            "Ended auction {} with winner {}",
+            "Cancelled auction {} with {} bids to refund",
             auction_key,
-            highest_bidder
+            auction.bid_count
         );
         Ok(())
     }
@@ -345,7 +784,7 @@ pub mod sealed_auction {
     pub fn undelegate_auction(ctx: Context<UndelegateAuction>, auction_id: u64) -> Result<()> {
         require_eq!(ctx.accounts.auction.auction_id, auction_id);
         require!(
-            ctx.accounts.auction.status == AuctionStatus::Ended,
+            ctx.accounts.auction.is_concluded(),
             ErrorCode::AuctionNotEnded
         );
         require_eq!(
@@ -363,15 +802,90 @@ pub mod sealed_auction {
         Ok(())
     }
 
+    /// Returns a bidder's `SolDeposit` to L1 once no open bid is locking it.
+    pub fn undelegate_sol_deposit(
+        ctx: Context<UndelegateSolDeposit>,
+        auction_id: u64,
+    ) -> Result<()> {
+        require_eq!(ctx.accounts.auction.auction_id, auction_id);
+        require!(
+            !ctx.accounts.sol_deposit.locked,
+            ErrorCode::SolDepositLocked
+        );
+        MagicIntentBundleBuilder::new(
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.magic_context.to_account_info(),
+            ctx.accounts.magic_program.to_account_info(),
+        )
+        .commit_and_undelegate(&[ctx.accounts.sol_deposit.to_account_info()])
+        .build_and_invoke()?;
+        Ok(())
+    }
+
+    /// Pays out a SOL deposit from the L1 bid vault after the auction concludes: the seller's
+    /// booked share to the auctioneer and the rest back to the bidder.
+    pub fn release_sol_deposit(ctx: Context<ReleaseSolDeposit>, auction_id: u64) -> Result<()> {
+        require_eq!(ctx.accounts.auction.auction_id, auction_id);
+        require!(
+            ctx.accounts.auction.is_concluded(),
+            ErrorCode::AuctionNotEnded
+        );
+        let deposit = &ctx.accounts.sol_deposit;
+        require!(!deposit.locked, ErrorCode::SolDepositLocked);
+        let seller_amount = deposit.owed_to_seller;
+        let refund = deposit
+            .amount
+            .checked_sub(seller_amount)
+            .ok_or(ErrorCode::InvalidAmount)?;
+
+        let auction_key = ctx.accounts.auction.key();
+        let vault_bump = [ctx.accounts.auction.bid_vault_bump];
+        let vault_signers: &[&[u8]] = &[BID_VAULT_SEED, auction_key.as_ref(), &vault_bump];
+        for (to, amount) in [
+            (ctx.accounts.seller.to_account_info(), seller_amount),
+            (ctx.accounts.bidder.to_account_info(), refund),
+        ] {
+            if amount > 0 {
+                transfer_lamports(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.system_program.key(),
+                        LamportsTransfer {
+                            from: ctx.accounts.bid_vault.to_account_info(),
+                            to,
+                        },
+                        &[vault_signers],
+                    ),
+                    amount,
+                )?;
+            }
+        }
+
+        emit!(SolDepositReleased {
+            auction: auction_key,
+            bidder: ctx.accounts.bidder.key(),
+            seller_amount,
+            refund,
+        });
+        Ok(())
+    }
+
     pub fn finalize(ctx: Context<Finalize>, auction_id: u64) -> Result<()> {
         require_eq!(ctx.accounts.auction.auction_id, auction_id);
         require!(
             ctx.accounts.auction.status == AuctionStatus::Ended,
             ErrorCode::AuctionNotEnded
         );
+        require!(ctx.accounts.auction.has_winner(), ErrorCode::MissingBid);
+        let winner = ctx.accounts.winner.key();
+        let quantity = ctx.accounts.auction.filled_quantity(&winner);
+        require!(quantity > 0, ErrorCode::InvalidBid);
         require!(
-            ctx.accounts.auction.highest_bidder != Pubkey::default(),
-            ErrorCode::MissingBid
+            ctx.accounts
+                .auction
+                .allocation(&winner)
+                .filter(|allocation| allocation.claimed)
+                .is_none(),
+            ErrorCode::LotAlreadyClaimed
         );
 
         let auction_id_bytes = ctx.accounts.auction.auction_id.to_le_bytes();
@@ -392,25 +906,37 @@ pub mod sealed_auction {
                 },
                 &[auction_seeds],
             ),
-            ctx.accounts.auction.lot_amount,
+            quantity,
         )?;
 
         let auction = &mut ctx.accounts.auction;
-        auction.status = AuctionStatus::Settled;
-        auction.lot_claimed = true;
+        if let Some(allocation) = auction
+            .allocations
+            .iter_mut()
+            .find(|allocation| allocation.bidder == winner)
+        {
+            allocation.claimed = true;
+        }
+        release_lot(auction, quantity)?;
+
+        emit!(LotFinalized {
+            auction: auction.key(),
+            auction_id,
+            winner,
+            quantity,
+            status: auction.status,
+        });
         Ok(())
     }
 
     pub fn reclaim_unsold_lot(ctx: Context<ReclaimUnsoldLot>, auction_id: u64) -> Result<()> {
         require_eq!(ctx.accounts.auction.auction_id, auction_id);
         require!(
-            ctx.accounts.auction.status == AuctionStatus::Ended,
+            ctx.accounts.auction.is_concluded(),
             ErrorCode::AuctionNotEnded
         );
-        require!(
-            ctx.accounts.auction.highest_bidder == Pubkey::default(),
-            ErrorCode::InvalidBid
-        );
+        let unsold = ctx.accounts.auction.unsold_lot();
+        require!(unsold > 0, ErrorCode::InvalidBid);
 
         let auction_id_bytes = ctx.accounts.auction.auction_id.to_le_bytes();
         let auction_bump = [ctx.accounts.auction.bump];
@@ -430,12 +956,17 @@ pub mod sealed_auction {
                 },
                 &[auction_seeds],
             ),
-            ctx.accounts.auction.lot_amount,
+            unsold,
         )?;
 
-        let auction = &mut ctx.accounts.auction;
-        auction.status = AuctionStatus::Settled;
-        auction.lot_claimed = true;
+        release_lot(&mut ctx.accounts.auction, unsold)?;
+
+        emit!(UnsoldLotReclaimed {
+            auction: ctx.accounts.auction.key(),
+            auction_id,
+            amount: unsold,
+            status: ctx.accounts.auction.status,
+        });
         Ok(())
     }
 
@@ -444,27 +975,39 @@ pub mod sealed_auction {
             ctx.accounts.auction.status == AuctionStatus::Ended,
             ErrorCode::AuctionNotEnded
         );
-        require!(
-            ctx.accounts.auction.highest_bidder != Pubkey::default(),
-            ErrorCode::MissingBid
-        );
+        require!(ctx.accounts.auction.has_winner(), ErrorCode::MissingBid);
 
         let auction = ctx.accounts.auction.key();
-        let winner = ctx.accounts.auction.highest_bidder;
+        let winner = ctx.accounts.winner.key();
+        require!(
+            ctx.accounts.auction.is_winner(&winner),
+            ErrorCode::InvalidBid
+        );
         let winning_bid_info = ctx.accounts.winning_bid.to_account_info();
         let bid = read_bid(&winning_bid_info)?;
         require_keys_eq!(bid.auction, auction, ErrorCode::InvalidBid);
         require_keys_eq!(bid.bidder, winner, ErrorCode::InvalidBid);
-        require_eq!(
-            bid.amount,
-            ctx.accounts.auction.highest_bid,
-            ErrorCode::InvalidBid
-        );
-        require_keys_eq!(
-            bid.escrow,
-            ctx.accounts.auction_token_b_account.key(),
-            ErrorCode::InvalidBidEscrow
-        );
+        let payment = if ctx.accounts.auction.kind.is_multi_unit() {
+            ctx.accounts
+                .auction
+                .filled_quantity(&winner)
+                .checked_mul(ctx.accounts.auction.clearing_price)
+                .ok_or(ErrorCode::InvalidAmount)?
+        } else {
+            require_eq!(
+                bid.value(),
+                ctx.accounts.auction.highest_bid,
+                ErrorCode::InvalidBid
+            );
+            ctx.accounts.auction.clearing_price
+        };
+        let mut escrow = BidEscrow::new(
+            &ctx.accounts.auction,
+            ctx.accounts.auction_token_b_account.as_deref(),
+            ctx.accounts.sol_deposit.as_mut(),
+            &ctx.accounts.token_program,
+        )?;
+        require_keys_eq!(bid.escrow, escrow.key(), ErrorCode::InvalidBidEscrow);
 
         let auction_id_bytes = ctx.accounts.auction.auction_id.to_le_bytes();
         let auction_bump = [ctx.accounts.auction.bump];
@@ -476,18 +1019,30 @@ pub mod sealed_auction {
         ];
         let bid_bump = [bid.bump];
         let bid_seeds: &[&[u8]] = &[BID_SEED, auction.as_ref(), winner.as_ref(), &bid_bump];
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.key(),
-                SplTransfer {
-                    from: ctx.accounts.auction_token_b_account.to_account_info(),
-                    to: ctx.accounts.seller_token_b_account.to_account_info(),
-                    authority: ctx.accounts.auction.to_account_info(),
-                },
-                &[auction_signers],
-            ),
-            bid.amount,
+        // The winner gets back everything above what they pay, including any unfilled units.
+        let winner_refund = bid
+            .amount
+            .checked_sub(payment)
+            .ok_or(ErrorCode::InvalidBid)?;
+        escrow.pay_seller(
+            &ctx.accounts.auction,
+            ctx.accounts
+                .seller_token_b_account
+                .as_ref()
+                .map(|account| account.to_account_info()),
+            payment,
         )?;
+        if winner_refund > 0 {
+            escrow.refund(
+                &ctx.accounts.auction,
+                ctx.accounts
+                    .winner_token_b_account
+                    .as_ref()
+                    .map(|account| account.to_account_info()),
+                winner_refund,
+            )?;
+        }
+        escrow.release();
 
         close_bid_permission(&CloseBidPermissionAccounts {
             sponsor: ctx.accounts.auction.to_account_info(),
@@ -506,12 +1061,20 @@ pub mod sealed_auction {
             .closed_bid_count
             .checked_add(1)
             .ok_or(ErrorCode::TooManyBidders)?;
+
+        emit!(WinningBidSettled {
+            auction,
+            auction_id: ctx.accounts.auction.auction_id,
+            winner,
+            payment,
+            refund: winner_refund,
+        });
         Ok(())
     }
 
     pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
         require!(
-            ctx.accounts.auction.status == AuctionStatus::Ended,
+            ctx.accounts.auction.is_concluded(),
             ErrorCode::AuctionNotEnded
         );
         let auction = ctx.accounts.auction.key();
@@ -520,14 +1083,21 @@ pub mod sealed_auction {
         require_keys_eq!(bid.auction, auction, ErrorCode::InvalidBid);
         require_keys_eq!(bid.bidder, ctx.accounts.bidder.key(), ErrorCode::InvalidBid);
         require!(
-            bid.bidder != ctx.accounts.auction.highest_bidder,
+            !ctx.accounts.auction.is_winner(&bid.bidder),
             ErrorCode::WinnerCannotRefund
         );
-        require_keys_eq!(
-            bid.escrow,
-            ctx.accounts.auction_token_b_account.key(),
-            ErrorCode::InvalidBidEscrow
+        // Cancellation refunds every deposit, including commitments that were never revealed.
+        require!(
+            !bid.is_unrevealed() || ctx.accounts.auction.status == AuctionStatus::Cancelled,
+            ErrorCode::UnrevealedBid
         );
+        let mut escrow = BidEscrow::new(
+            &ctx.accounts.auction,
+            ctx.accounts.auction_token_b_account.as_deref(),
+            ctx.accounts.sol_deposit.as_mut(),
+            &ctx.accounts.token_program,
+        )?;
+        require_keys_eq!(bid.escrow, escrow.key(), ErrorCode::InvalidBidEscrow);
 
         let auction_id_bytes = ctx.accounts.auction.auction_id.to_le_bytes();
         let auction_bump = [ctx.accounts.auction.bump];
@@ -544,18 +1114,15 @@ pub mod sealed_auction {
             bid.bidder.as_ref(),
             &bid_bump,
         ];
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.key(),
-                SplTransfer {
-                    from: ctx.accounts.auction_token_b_account.to_account_info(),
-                    to: ctx.accounts.bidder_token_b_account.to_account_info(),
-                    authority: ctx.accounts.auction.to_account_info(),
-                },
-                &[auction_signers],
-            ),
+        escrow.refund(
+            &ctx.accounts.auction,
+            ctx.accounts
+                .bidder_token_b_account
+                .as_ref()
+                .map(|account| account.to_account_info()),
             bid.amount,
         )?;
+        escrow.release();
 
         close_bid_permission(&CloseBidPermissionAccounts {
             sponsor: ctx.accounts.auction.to_account_info(),
@@ -574,8 +1141,349 @@ pub mod sealed_auction {
             .closed_bid_count
             .checked_add(1)
             .ok_or(ErrorCode::TooManyBidders)?;
+
+        emit!(RefundClaimed {
+            auction,
+            auction_id: ctx.accounts.auction.auction_id,
+            bidder: bid.bidder,
+            amount: bid.amount,
+        });
         Ok(())
     }
+
+    pub fn slash_bid(ctx: Context<SlashBid>) -> Result<()> {
+        require!(
+            ctx.accounts.auction.status == AuctionStatus::Ended,
+            ErrorCode::AuctionNotEnded
+        );
+        let auction = ctx.accounts.auction.key();
+        let bid_info = ctx.accounts.bid.to_account_info();
+        let bid = read_bid(&bid_info)?;
+        require_keys_eq!(bid.auction, auction, ErrorCode::InvalidBid);
+        require_keys_eq!(bid.bidder, ctx.accounts.bidder.key(), ErrorCode::InvalidBid);
+        require!(bid.is_unrevealed(), ErrorCode::InvalidBid);
+        let mut escrow = BidEscrow::new(
+            &ctx.accounts.auction,
+            ctx.accounts.auction_token_b_account.as_deref(),
+            ctx.accounts.sol_deposit.as_mut(),
+            &ctx.accounts.token_program,
+        )?;
+        require_keys_eq!(bid.escrow, escrow.key(), ErrorCode::InvalidBidEscrow);
+
+        let auction_id_bytes = ctx.accounts.auction.auction_id.to_le_bytes();
+        let auction_bump = [ctx.accounts.auction.bump];
+        let auction_signers: &[&[u8]] = &[
+            AUCTION_SEED,
+            ctx.accounts.auction.auctioneer.as_ref(),
+            &auction_id_bytes,
+            &auction_bump,
+        ];
+        let bid_bump = [bid.bump];
+        let bid_seeds: &[&[u8]] = &[
+            BID_SEED,
+            bid.auction.as_ref(),
+            bid.bidder.as_ref(),
+            &bid_bump,
+        ];
+        // The whole deposit of a bid that was never revealed goes to the seller.
+        escrow.pay_seller(
+            &ctx.accounts.auction,
+            ctx.accounts
+                .seller_token_b_account
+                .as_ref()
+                .map(|account| account.to_account_info()),
+            bid.amount,
+        )?;
+        escrow.release();
+
+        close_bid_permission(&CloseBidPermissionAccounts {
+            sponsor: ctx.accounts.auction.to_account_info(),
+            bid: ctx.accounts.bid.to_account_info(),
+            permission: ctx.accounts.bid_permission.to_account_info(),
+            ephemeral_vault: ctx.accounts.vault.to_account_info(),
+            magic_program: ctx.accounts.magic_program.to_account_info(),
+            permission_program: ctx.accounts.permission_program.to_account_info(),
+            sponsor_signers: auction_signers,
+            bid_signers: bid_seeds,
+        })?;
+        ctx.accounts.close_ephemeral_bid()?;
+        ctx.accounts.auction.closed_bid_count = ctx
+            .accounts
+            .auction
+            .closed_bid_count
+            .checked_add(1)
+            .ok_or(ErrorCode::TooManyBidders)?;
+
+        emit!(BidSlashed {
+            auction,
+            auction_id: ctx.accounts.auction.auction_id,
+            bidder: bid.bidder,
+            amount: bid.amount,
+        });
+        msg!(
+            "Slashed unrevealed bid {} for {}",
+            bid_info.key(),
+            bid.amount
+        );
+        Ok(())
+    }
+}
+
+/// Auction parameters shared by `initialize_auction` and `initialize_sol_auction`.
+struct AuctionSetup<'a, 'info> {
+    auctioneer: Pubkey,
+    auction_id: u64,
+    token_a_mint: &'a Account<'info, Mint>,
+    token_b_mint: Pubkey,
+    bid_currency: BidCurrency,
+    bid_vault_bump: u8,
+    lot_amount: u64,
+    deadline_ts: i64,
+    kind: AuctionKind,
+    anti_sniping: Option<AntiSnipingConfig>,
+    bid_privacy: BidPrivacy,
+    bump: u8,
+}
+
+fn init_auction_state(auction: &mut Auction, setup: AuctionSetup<'_, '_>) -> Result<()> {
+    let AuctionSetup {
+        lot_amount,
+        kind,
+        anti_sniping,
+        bid_privacy,
+        deadline_ts,
+        ..
+    } = setup;
+    require!(lot_amount > 0, ErrorCode::InvalidAmount);
+    if let AuctionKind::ReservePrice { reserve_price } = kind {
+        require!(reserve_price > 0, ErrorCode::InvalidAmount);
+    }
+    if let Some(config) = anti_sniping {
+        require!(
+            config.extension_window_secs > 0 && config.max_extensions > 0,
+            ErrorCode::InvalidExtensionConfig
+        );
+    }
+    if let BidPrivacy::CommitReveal { reveal_window_secs } = bid_privacy {
+        require!(reveal_window_secs > 0, ErrorCode::InvalidRevealWindow);
+    }
+    require!(
+        deadline_ts > Clock::get()?.unix_timestamp,
+        ErrorCode::DeadlineInPast
+    );
+    let lot_kind = if setup.token_a_mint.decimals == 0 && setup.token_a_mint.supply == 1 {
+        LotKind::Nft
+    } else {
+        LotKind::Fungible
+    };
+    if lot_kind == LotKind::Nft {
+        require!(
+            lot_amount == 1 && !kind.is_multi_unit(),
+            ErrorCode::InvalidNftLot
+        );
+    }
+
+    auction.auctioneer = setup.auctioneer;
+    auction.auction_id = setup.auction_id;
+    auction.token_a_mint = setup.token_a_mint.key();
+    auction.token_b_mint = setup.token_b_mint;
+    auction.bid_currency = setup.bid_currency;
+    auction.bid_vault_bump = setup.bid_vault_bump;
+    auction.lot_kind = lot_kind;
+    auction.lot_amount = lot_amount;
+    auction.lot_remaining = lot_amount;
+    auction.deadline_ts = deadline_ts;
+    auction.extension_window_secs = anti_sniping.map_or(0, |c| c.extension_window_secs);
+    auction.max_extensions = anti_sniping.map_or(0, |c| c.max_extensions);
+    auction.extensions_used = 0;
+    auction.kind = kind;
+    auction.bid_privacy = bid_privacy;
+    auction.bid_count = 0;
+    auction.closed_bid_count = 0;
+    auction.highest_bid = 0;
+    auction.highest_bidder = Pubkey::default();
+    auction.second_bid = 0;
+    auction.clearing_price = 0;
+    auction.tally = AuctionTally::default();
+    auction.allocations = Vec::new();
+    auction.status = AuctionStatus::Open;
+    auction.lot_claimed = false;
+    auction.bump = setup.bump;
+    Ok(())
+}
+
+/// Preloads the auction PDA with bid permission rent plus `sponsor_lamports` for ephemeral bids.
+fn fund_auction_sponsor<'info>(
+    auctioneer: &Signer<'info>,
+    auction: AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    sponsor_lamports: u64,
+) -> Result<()> {
+    transfer_lamports(
+        CpiContext::new(
+            system_program.key(),
+            LamportsTransfer {
+                from: auctioneer.to_account_info(),
+                to: auction,
+            },
+        ),
+        ephemeral_rollups_sdk::ephemeral_accounts::rent(EphemeralPermission::size_of(
+            SPONSORED_PERMISSION_MEMBERS,
+        ) as u32)
+        .checked_add(sponsor_lamports)
+        .ok_or(ErrorCode::InvalidAmount)?,
+    )
+}
+
+/// Where an auction holds bid collateral: its Token B account, or the bidder's SOL deposit.
+///
+/// SOL never moves on the ER. The lamports stay in the L1 bid vault and SOL bids only lock,
+/// unlock, or book to the seller part of the bidder's `SolDeposit`, which `release_sol_deposit`
+/// pays out on L1.
+enum BidEscrow<'a, 'info> {
+    Token {
+        account: &'a Account<'info, TokenAccount>,
+        token_program: &'a Program<'info, Token>,
+    },
+    Lamports {
+        deposit: &'a mut Account<'info, SolDeposit>,
+    },
+}
+
+impl<'a, 'info> BidEscrow<'a, 'info> {
+    /// Picks the escrow matching the auction's `BidCurrency` from an instruction's optional accounts.
+    fn new(
+        auction: &Auction,
+        token_account: Option<&'a Account<'info, TokenAccount>>,
+        deposit: Option<&'a mut Account<'info, SolDeposit>>,
+        token_program: &'a Program<'info, Token>,
+    ) -> Result<Self> {
+        match auction.bid_currency {
+            BidCurrency::Token => Ok(BidEscrow::Token {
+                account: token_account.ok_or(ErrorCode::InvalidBidEscrow)?,
+                token_program,
+            }),
+            BidCurrency::Lamports => Ok(BidEscrow::Lamports {
+                deposit: deposit.ok_or(ErrorCode::InvalidBidEscrow)?,
+            }),
+        }
+    }
+
+    fn key(&self) -> Pubkey {
+        match self {
+            BidEscrow::Token { account, .. } => account.key(),
+            BidEscrow::Lamports { deposit } => deposit.key(),
+        }
+    }
+
+    /// Escrows `amount` more for a bid that now totals `bid_amount`: a Token B transfer from `from`
+    /// signed by the bidder, or a lock on their whole SOL deposit once it covers the bid.
+    fn collect(
+        &mut self,
+        from: Option<AccountInfo<'info>>,
+        bidder: AccountInfo<'info>,
+        amount: u64,
+        bid_amount: u64,
+    ) -> Result<()> {
+        match self {
+            BidEscrow::Token {
+                account,
+                token_program,
+            } => token::transfer(
+                CpiContext::new(
+                    token_program.key(),
+                    SplTransfer {
+                        from: from.ok_or_else(|| error!(ErrorCode::MissingPaymentAccount))?,
+                        to: account.to_account_info(),
+                        authority: bidder,
+                    },
+                ),
+                amount,
+            ),
+            BidEscrow::Lamports { deposit } => {
+                let available = deposit.amount.saturating_sub(deposit.owed_to_seller);
+                require!(bid_amount <= available, ErrorCode::InsufficientSolDeposit);
+                deposit.locked = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns `amount` of escrow to the bidder: a Token B transfer to `to` signed by the auction
+    /// PDA. SOL stays in the deposit, which `release_sol_deposit` refunds on L1.
+    fn refund(
+        &mut self,
+        auction: &Account<'info, Auction>,
+        to: Option<AccountInfo<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        match self {
+            BidEscrow::Token { .. } => self.transfer_tokens(auction, to, amount),
+            BidEscrow::Lamports { .. } => Ok(()),
+        }
+    }
+
+    /// Pays `amount` of escrow to the seller: a Token B transfer to `to`, or for SOL auctions a
+    /// booking of the lamports to the seller for `release_sol_deposit`.
+    fn pay_seller(
+        &mut self,
+        auction: &Account<'info, Auction>,
+        to: Option<AccountInfo<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        match self {
+            BidEscrow::Token { .. } => self.transfer_tokens(auction, to, amount),
+            BidEscrow::Lamports { deposit } => {
+                deposit.owed_to_seller = deposit
+                    .owed_to_seller
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::InvalidAmount)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Unlocks a SOL deposit once its bid is closed. Token B escrow has nothing left to release.
+    fn release(&mut self) {
+        if let BidEscrow::Lamports { deposit } = self {
+            deposit.locked = false;
+        }
+    }
+
+    fn transfer_tokens(
+        &self,
+        auction: &Account<'info, Auction>,
+        to: Option<AccountInfo<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let BidEscrow::Token {
+            account,
+            token_program,
+        } = self
+        else {
+            return err!(ErrorCode::InvalidBidEscrow);
+        };
+        let auction_id_bytes = auction.auction_id.to_le_bytes();
+        let auction_bump = [auction.bump];
+        let auction_signers: &[&[u8]] = &[
+            AUCTION_SEED,
+            auction.auctioneer.as_ref(),
+            &auction_id_bytes,
+            &auction_bump,
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.key(),
+                SplTransfer {
+                    from: account.to_account_info(),
+                    to: to.ok_or_else(|| error!(ErrorCode::MissingPaymentAccount))?,
+                    authority: auction.to_account_info(),
+                },
+                &[auction_signers],
+            ),
+            amount,
+        )
+    }
 }
 
 fn permission_member(pubkey: Pubkey) -> Member {
@@ -585,6 +1493,163 @@ fn permission_member(pubkey: Pubkey) -> Member {
     }
 }
 
+/// Verifies the proposed uniform clearing price against the tallied demand and fills allocations.
+///
+/// The proposal is valid when it is the price of some bid, demand strictly above it leaves part of
+/// the lot unsold, and either demand at the price exhausts the lot or no bid was priced below it.
+/// Bids above the price fill in full; bids at the price share the remaining units pro rata.
+fn clear_uniform_price(auction: &mut Auction) -> Result<()> {
+    let tally = auction.tally;
+    let supply = auction.lot_amount;
+    if tally.demand_above == 0 && tally.demand_at == 0 && tally.demand_below == 0 {
+        auction.clearing_price = 0;
+        return Ok(());
+    }
+
+    let demand_through_price = tally
+        .demand_above
+        .checked_add(tally.demand_at)
+        .ok_or(ErrorCode::InvalidAmount)?;
+    require!(
+        tally.demand_at > 0
+            && tally.demand_above < supply
+            && (demand_through_price >= supply || tally.demand_below == 0),
+        ErrorCode::ClearingPriceMismatch
+    );
+
+    let remaining = supply - tally.demand_above;
+    for allocation in auction.allocations.iter_mut() {
+        allocation.filled =
+            if allocation.unit_price > tally.proposed_price || demand_through_price <= supply {
+                allocation.quantity
+            } else {
+                (allocation.quantity as u128 * remaining as u128 / tally.demand_at as u128) as u64
+            };
+    }
+    auction.clearing_price = tally.proposed_price;
+    Ok(())
+}
+
+fn emit_auction_initialized(auction_key: Pubkey, auction: &Auction) {
+    emit!(AuctionInitialized {
+        auction: auction_key,
+        auction_id: auction.auction_id,
+        auctioneer: auction.auctioneer,
+        token_a_mint: auction.token_a_mint,
+        token_b_mint: auction.token_b_mint,
+        bid_currency: auction.bid_currency,
+        lot_kind: auction.lot_kind,
+        lot_amount: auction.lot_amount,
+        deadline_ts: auction.deadline_ts,
+        kind: auction.kind,
+        bid_privacy: auction.bid_privacy,
+    });
+}
+
+/// Publishes tally progress; winner fields stay zero until the auction reaches `Ended`.
+fn emit_auction_tallied(auction_key: Pubkey, auction: &Auction) {
+    emit!(AuctionTallied {
+        auction: auction_key,
+        auction_id: auction.auction_id,
+        status: auction.status,
+        tallied: auction.tally.next_index,
+        bid_count: auction.bid_count,
+        highest_bid: auction.highest_bid,
+        highest_bidder: auction.highest_bidder,
+        second_bid: auction.second_bid,
+        clearing_price: auction.clearing_price,
+    });
+}
+
+fn release_lot(auction: &mut Auction, amount: u64) -> Result<()> {
+    auction.lot_remaining = auction
+        .lot_remaining
+        .checked_sub(amount)
+        .ok_or(ErrorCode::InvalidAmount)?;
+    if auction.lot_remaining == 0 {
+        // A cancelled auction keeps its status once the lot is back with the seller.
+        if auction.status == AuctionStatus::Ended {
+            auction.status = AuctionStatus::Settled;
+        }
+        auction.lot_claimed = true;
+    }
+    Ok(())
+}
+
+fn require_bidding_open(auction: &Auction) -> Result<()> {
+    require!(
+        auction.status == AuctionStatus::Open,
+        ErrorCode::AuctionClosed
+    );
+    require!(
+        Clock::get()?.unix_timestamp < auction.deadline_ts,
+        ErrorCode::AuctionClosed
+    );
+    Ok(())
+}
+
+fn extend_deadline(auction: &mut Auction) -> Result<()> {
+    if auction.extend_deadline(Clock::get()?.unix_timestamp) {
+        msg!(
+            "Extended auction deadline to {} ({} of {})",
+            auction.deadline_ts,
+            auction.extensions_used,
+            auction.max_extensions
+        );
+    }
+    Ok(())
+}
+
+/// Returns `unit_price * quantity` after validating the bid terms against the auction kind.
+fn bid_amount(auction: &Auction, unit_price: u64, quantity: u64) -> Result<u64> {
+    require!(unit_price > 0 && quantity > 0, ErrorCode::InvalidAmount);
+    if auction.kind.is_multi_unit() {
+        require!(quantity <= auction.lot_amount, ErrorCode::InvalidAmount);
+    } else {
+        // Single-unit auctions sell the whole lot as one unit.
+        require_eq!(quantity, 1, ErrorCode::InvalidAmount);
+    }
+    let amount = unit_price
+        .checked_mul(quantity)
+        .ok_or(ErrorCode::InvalidAmount)?;
+    Ok(amount)
+}
+
+/// Writes `terms` into `bid` after checking they match the auction's `BidPrivacy`.
+///
+/// Committed bids escrow only their deposit and keep zero terms until `reveal_bid`.
+fn apply_bid_terms(auction: &Auction, bid: &mut Bid, terms: BidTerms) -> Result<()> {
+    match (auction.bid_privacy, terms) {
+        (
+            BidPrivacy::Tee,
+            BidTerms::Plain {
+                unit_price,
+                quantity,
+            },
+        ) => {
+            bid.amount = bid_amount(auction, unit_price, quantity)?;
+            bid.unit_price = unit_price;
+            bid.quantity = quantity;
+        }
+        (
+            BidPrivacy::CommitReveal { .. },
+            BidTerms::Committed {
+                commitment,
+                deposit,
+            },
+        ) => {
+            require!(deposit > 0, ErrorCode::InvalidAmount);
+            require!(commitment != [0; 32], ErrorCode::CommitmentMismatch);
+            bid.amount = deposit;
+            bid.commitment = commitment;
+            bid.unit_price = 0;
+            bid.quantity = 0;
+        }
+        _ => return err!(ErrorCode::BidPrivacyMismatch),
+    }
+    Ok(())
+}
+
 fn write_bid(account_info: &AccountInfo, bid: &Bid) -> Result<()> {
     let mut data = account_info.try_borrow_mut_data()?;
     bid.try_serialize(&mut &mut data[..])?;
@@ -807,6 +1872,41 @@ pub struct InitializeAuction<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct InitializeSolAuction<'info> {
+    #[account(mut)]
+    pub auctioneer: Signer<'info>,
+    pub token_a_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = auctioneer,
+        space = 8 + Auction::LEN,
+        seeds = [AUCTION_SEED, auctioneer.key().as_ref(), &auction_id.to_le_bytes()],
+        bump
+    )]
+    pub auction: Box<Account<'info, Auction>>,
+    #[account(
+        init,
+        payer = auctioneer,
+        associated_token::mint = token_a_mint,
+        associated_token::authority = auction
+    )]
+    pub auction_token_a_account: Account<'info, TokenAccount>,
+    /// SOL escrow PDA, system-owned, holds every lamport bid. Validated by seeds.
+    #[account(mut, seeds = [BID_VAULT_SEED, auction.key().as_ref()], bump)]
+    pub bid_vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        token::mint = token_a_mint,
+        token::authority = auctioneer
+    )]
+    pub seller_token_a_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
 #[ephemeral_accounts]
 #[derive(Accounts)]
 #[instruction(auction_id: u64)]
@@ -814,13 +1914,13 @@ pub struct PlaceBid<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     pub bidder: Signer<'info>,
-    pub token_b_mint: Account<'info, Mint>,
+    #[account(constraint = token_b_mint.key() == auction.token_b_mint @ ErrorCode::MintMismatch)]
+    pub token_b_mint: Option<Account<'info, Mint>>,
     #[account(
         mut,
         sponsor,
         seeds = [AUCTION_SEED, auction.auctioneer.as_ref(), &auction.auction_id.to_le_bytes()],
         constraint = auction.auctioneer == payer.key() @ ErrorCode::InvalidBid,
-        constraint = auction.token_b_mint == token_b_mint.key() @ ErrorCode::MintMismatch,
         bump = auction.bump
     )]
     pub auction: Account<'info, Auction>,
@@ -835,16 +1935,24 @@ pub struct PlaceBid<'info> {
     #[account(
         mut,
         constraint = bidder_token_b_account.owner == bidder.key() @ ErrorCode::InvalidTokenOwner,
-        constraint = bidder_token_b_account.mint == token_b_mint.key() @ ErrorCode::MintMismatch
+        constraint = bidder_token_b_account.mint == auction.token_b_mint @ ErrorCode::MintMismatch
     )]
-    pub bidder_token_b_account: Box<Account<'info, TokenAccount>>,
+    pub bidder_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
     #[account(
         mut,
-        token::mint = token_b_mint,
+        token::mint = auction.token_b_mint,
         token::authority = auction
     )]
-    pub auction_token_b_account: Box<Account<'info, TokenAccount>>,
+    pub auction_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    /// The bidder's delegated SOL deposit, which backs SOL bids.
+    #[account(
+        mut,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump = sol_deposit.bump
+    )]
+    pub sol_deposit: Option<Account<'info, SolDeposit>>,
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -877,6 +1985,123 @@ pub struct InitBidPermission<'info> {
     pub magic_program: UncheckedAccount<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct UpdateBid<'info> {
+    pub bidder: Signer<'info>,
+    #[account(constraint = token_b_mint.key() == auction.token_b_mint @ ErrorCode::MintMismatch)]
+    pub token_b_mint: Option<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [AUCTION_SEED, auction.auctioneer.as_ref(), &auction_id.to_le_bytes()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        seeds = [BID_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Ephemeral bid PDA; its contents are validated after deserialization.
+    pub bid: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = bidder_token_b_account.owner == bidder.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = bidder_token_b_account.mint == auction.token_b_mint @ ErrorCode::MintMismatch
+    )]
+    pub bidder_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::mint = auction.token_b_mint,
+        token::authority = auction
+    )]
+    pub auction_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    /// The bidder's delegated SOL deposit, which backs SOL bids.
+    #[account(
+        mut,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump = sol_deposit.bump
+    )]
+    pub sol_deposit: Option<Account<'info, SolDeposit>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[ephemeral_accounts]
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct WithdrawBid<'info> {
+    pub bidder: Signer<'info>,
+    #[account(
+        mut,
+        sponsor,
+        seeds = [AUCTION_SEED, auction.auctioneer.as_ref(), &auction_id.to_le_bytes()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    /// CHECK: Ephemeral bid PDA sponsored by the auction.
+    #[account(
+        mut,
+        eph,
+        seeds = [BID_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    pub bid: UncheckedAccount<'info>,
+    /// CHECK: Bid holding the highest bidder index; required unless `bid` is that bid.
+    #[account(mut)]
+    pub last_bid: Option<UncheckedAccount<'info>>,
+    #[account(constraint = token_b_mint.key() == auction.token_b_mint @ ErrorCode::MintMismatch)]
+    pub token_b_mint: Option<Account<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = auction.token_b_mint,
+        token::authority = auction
+    )]
+    pub auction_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::mint = auction.token_b_mint,
+        token::authority = bidder
+    )]
+    pub bidder_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    /// The bidder's delegated SOL deposit, which SOL refunds unlock.
+    #[account(
+        mut,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump = sol_deposit.bump
+    )]
+    pub sol_deposit: Option<Account<'info, SolDeposit>>,
+    #[account(mut)]
+    /// CHECK: Verified by the Permission Program.
+    pub bid_permission: UncheckedAccount<'info>,
+    #[account(address = PERMISSION_PROGRAM_ID)]
+    /// CHECK: Fixed Permission Program id.
+    pub permission_program: UncheckedAccount<'info>,
+    #[account(address = MAGIC_PROGRAM_ID)]
+    /// CHECK: Fixed Magic Program id.
+    pub magic_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct RevealBid<'info> {
+    pub bidder: Signer<'info>,
+    #[account(
+        seeds = [AUCTION_SEED, auction.auctioneer.as_ref(), &auction_id.to_le_bytes()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        seeds = [BID_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Ephemeral bid PDA; its contents are validated after deserialization.
+    pub bid: UncheckedAccount<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(auction_id: u64)]
 pub struct EndAuction<'info> {
@@ -891,6 +2116,19 @@ pub struct EndAuction<'info> {
     pub auction: Account<'info, Auction>,
 }
 
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct CancelAuction<'info> {
+    pub auctioneer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [AUCTION_SEED, auction.auctioneer.as_ref(), &auction_id.to_le_bytes()],
+        has_one = auctioneer,
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+}
+
 #[commit]
 #[derive(Accounts)]
 #[instruction(auction_id: u64)]
@@ -905,6 +2143,25 @@ pub struct UndelegateAuction<'info> {
     pub auction: Account<'info, Auction>,
 }
 
+#[commit]
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct UndelegateSolDeposit<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [AUCTION_SEED, auction.auctioneer.as_ref(), &auction_id.to_le_bytes()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), sol_deposit.bidder.as_ref()],
+        bump = sol_deposit.bump
+    )]
+    pub sol_deposit: Account<'info, SolDeposit>,
+}
+
 #[derive(Accounts)]
 #[instruction(auction_id: u64)]
 pub struct Finalize<'info> {
@@ -922,10 +2179,12 @@ pub struct Finalize<'info> {
         token::authority = auction
     )]
     pub auction_token_a_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Winner identity is verified against the auction result and token destination authority.
+    pub winner: UncheckedAccount<'info>,
     #[account(
         mut,
         token::mint = token_a_mint,
-        token::authority = auction.highest_bidder
+        token::authority = winner
     )]
     pub winner_token_a_account: Box<Account<'info, TokenAccount>>,
     pub token_program: Program<'info, Token>,
@@ -970,6 +2229,8 @@ pub struct SettleWinningBid<'info> {
         bump = auction.bump
     )]
     pub auction: Account<'info, Auction>,
+    /// CHECK: Winner identity is verified against the auction result and token destination authority.
+    pub winner: UncheckedAccount<'info>,
     /// CHECK: Ephemeral winning bid PDA sponsored by the auction.
     #[account(
         mut,
@@ -977,25 +2238,38 @@ pub struct SettleWinningBid<'info> {
         seeds = [
             BID_SEED,
             auction.key().as_ref(),
-            auction.highest_bidder.as_ref()
+            winner.key().as_ref()
         ],
         bump
     )]
     pub winning_bid: UncheckedAccount<'info>,
     #[account(constraint = token_b_mint.key() == auction.token_b_mint @ ErrorCode::MintMismatch)]
-    pub token_b_mint: Account<'info, Mint>,
+    pub token_b_mint: Option<Account<'info, Mint>>,
     #[account(
         mut,
-        token::mint = token_b_mint,
+        token::mint = auction.token_b_mint,
         token::authority = auction
     )]
-    pub auction_token_b_account: Account<'info, TokenAccount>,
+    pub auction_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
     #[account(
         mut,
-        token::mint = token_b_mint,
+        token::mint = auction.token_b_mint,
         token::authority = auction.auctioneer
     )]
-    pub seller_token_b_account: Account<'info, TokenAccount>,
+    pub seller_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::mint = auction.token_b_mint,
+        token::authority = winner
+    )]
+    pub winner_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    /// The winner's delegated SOL deposit, which books the payment to the seller.
+    #[account(
+        mut,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), winner.key().as_ref()],
+        bump = sol_deposit.bump
+    )]
+    pub sol_deposit: Option<Account<'info, SolDeposit>>,
     #[account(mut)]
     /// CHECK: Verified by the Permission Program.
     pub bid_permission: UncheckedAccount<'info>,
@@ -1006,6 +2280,7 @@ pub struct SettleWinningBid<'info> {
     /// CHECK: Fixed Magic Program id.
     pub magic_program: UncheckedAccount<'info>,
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[ephemeral_accounts]
@@ -1033,19 +2308,84 @@ pub struct ClaimRefund<'info> {
     )]
     pub bid: UncheckedAccount<'info>,
     #[account(constraint = token_b_mint.key() == auction.token_b_mint @ ErrorCode::MintMismatch)]
-    pub token_b_mint: Account<'info, Mint>,
+    pub token_b_mint: Option<Account<'info, Mint>>,
     #[account(
         mut,
-        token::mint = token_b_mint,
+        token::mint = auction.token_b_mint,
         token::authority = auction
     )]
-    pub auction_token_b_account: Account<'info, TokenAccount>,
+    pub auction_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
     #[account(
         mut,
-        token::mint = token_b_mint,
+        token::mint = auction.token_b_mint,
         token::authority = bidder
     )]
-    pub bidder_token_b_account: Account<'info, TokenAccount>,
+    pub bidder_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    /// The bidder's delegated SOL deposit, which SOL refunds unlock.
+    #[account(
+        mut,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump = sol_deposit.bump
+    )]
+    pub sol_deposit: Option<Account<'info, SolDeposit>>,
+    #[account(mut)]
+    /// CHECK: Verified by the Permission Program.
+    pub bid_permission: UncheckedAccount<'info>,
+    #[account(address = PERMISSION_PROGRAM_ID)]
+    /// CHECK: Fixed Permission Program id.
+    pub permission_program: UncheckedAccount<'info>,
+    #[account(address = MAGIC_PROGRAM_ID)]
+    /// CHECK: Fixed Magic Program id.
+    pub magic_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[ephemeral_accounts]
+#[derive(Accounts)]
+pub struct SlashBid<'info> {
+    /// CHECK: Bidder identity is verified against the Bid account.
+    pub bidder: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        sponsor,
+        seeds = [AUCTION_SEED, auction.auctioneer.as_ref(), &auction.auction_id.to_le_bytes()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    /// CHECK: Ephemeral bid PDA sponsored by the auction.
+    #[account(
+        mut,
+        eph,
+        seeds = [
+            BID_SEED,
+            auction.key().as_ref(),
+            bidder.key().as_ref()
+        ],
+        bump
+    )]
+    pub bid: UncheckedAccount<'info>,
+    #[account(constraint = token_b_mint.key() == auction.token_b_mint @ ErrorCode::MintMismatch)]
+    pub token_b_mint: Option<Account<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = auction.token_b_mint,
+        token::authority = auction
+    )]
+    pub auction_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::mint = auction.token_b_mint,
+        token::authority = auction.auctioneer
+    )]
+    pub seller_token_b_account: Option<Box<Account<'info, TokenAccount>>>,
+    /// The bidder's delegated SOL deposit, whose slashed lock is booked to the seller.
+    #[account(
+        mut,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump = sol_deposit.bump
+    )]
+    pub sol_deposit: Option<Account<'info, SolDeposit>>,
     #[account(mut)]
     /// CHECK: Verified by the Permission Program.
     pub bid_permission: UncheckedAccount<'info>,
@@ -1056,6 +2396,7 @@ pub struct ClaimRefund<'info> {
     /// CHECK: Fixed Magic Program id.
     pub magic_program: UncheckedAccount<'info>,
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[delegate]
@@ -1076,6 +2417,83 @@ pub struct DelegateAuction<'info> {
     pub validator: Option<UncheckedAccount<'info>>,
 }
 
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct DepositSol<'info> {
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    /// CHECK: Only used to derive the auction address.
+    pub auctioneer: UncheckedAccount<'info>,
+    #[account(seeds = [AUCTION_SEED, auctioneer.key().as_ref(), &auction_id.to_le_bytes()], bump)]
+    /// CHECK: Only the address is used; the auction is usually delegated.
+    pub auction: UncheckedAccount<'info>,
+    /// SOL escrow PDA, system-owned and never delegated. Validated by seeds.
+    #[account(mut, seeds = [BID_VAULT_SEED, auction.key().as_ref()], bump)]
+    pub bid_vault: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        space = 8 + SolDeposit::LEN,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    pub sol_deposit: Account<'info, SolDeposit>,
+    pub system_program: Program<'info, System>,
+}
+
+#[delegate]
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct DelegateSolDeposit<'info> {
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    /// CHECK: Only used to derive the auction address.
+    pub auctioneer: UncheckedAccount<'info>,
+    #[account(seeds = [AUCTION_SEED, auctioneer.key().as_ref(), &auction_id.to_le_bytes()], bump)]
+    /// CHECK: Only the address is used; the auction may already be delegated.
+    pub auction: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        del,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    /// CHECK: The bidder's SOL deposit record, delegated so ER bids can lock it.
+    pub sol_deposit: UncheckedAccount<'info>,
+    /// CHECK: Checked by the delegation program.
+    pub validator: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct ReleaseSolDeposit<'info> {
+    #[account(
+        seeds = [AUCTION_SEED, auction.auctioneer.as_ref(), &auction_id.to_le_bytes()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    /// CHECK: Receives the seller's share. Validated against the auction.
+    #[account(mut, address = auction.auctioneer)]
+    pub seller: UncheckedAccount<'info>,
+    /// CHECK: Receives the refund and the closed record's rent. Validated against the deposit.
+    #[account(mut, address = sol_deposit.bidder)]
+    pub bidder: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [BID_VAULT_SEED, auction.key().as_ref()],
+        bump = auction.bid_vault_bump
+    )]
+    pub bid_vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = bidder,
+        seeds = [SOL_DEPOSIT_SEED, auction.key().as_ref(), sol_deposit.bidder.as_ref()],
+        bump = sol_deposit.bump
+    )]
+    pub sol_deposit: Account<'info, SolDeposit>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 #[instruction(auction_id: u64)]
 pub struct AuctionPermission<'info> {
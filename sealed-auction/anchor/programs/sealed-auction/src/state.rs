@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
 
-pub const MAX_BIDDERS: usize = 5;
+/// Member capacity used to size the permission rent preloaded into the auction sponsor.
+pub const SPONSORED_PERMISSION_MEMBERS: usize = 6;
+/// Capacity of `Auction::allocations`, the most winning bids a uniform-price auction can clear.
+pub const MAX_LOT_WINNERS: usize = 8;
 
 #[account]
 pub struct Auction {
@@ -8,19 +11,273 @@ pub struct Auction {
     pub auction_id: u64,
     pub token_a_mint: Pubkey,
     pub token_b_mint: Pubkey,
+    pub bid_currency: BidCurrency,
+    pub bid_vault_bump: u8,
+    pub lot_kind: LotKind,
     pub lot_amount: u64,
+    pub lot_remaining: u64,
     pub deadline_ts: i64,
-    pub bid_count: u8,
-    pub closed_bid_count: u8,
+    pub extension_window_secs: u32,
+    pub max_extensions: u8,
+    pub extensions_used: u8,
+    pub kind: AuctionKind,
+    pub bid_privacy: BidPrivacy,
+    pub bid_count: u16,
+    pub closed_bid_count: u16,
     pub highest_bid: u64,
     pub highest_bidder: Pubkey,
+    pub second_bid: u64,
+    pub clearing_price: u64,
+    pub tally: AuctionTally,
+    pub allocations: Vec<LotAllocation>,
     pub status: AuctionStatus,
     pub lot_claimed: bool,
     pub bump: u8,
 }
 
 impl Auction {
-    pub const LEN: usize = 32 + 8 + 32 + 32 + 8 + 8 + 1 + 1 + 8 + 32 + 1 + 1 + 1;
+    pub const LEN: usize = 32
+        + 8
+        + 32
+        + 32
+        + 1
+        + 1
+        + 1
+        + 8
+        + 8
+        + 8
+        + 4
+        + 1
+        + 1
+        + AuctionKind::LEN
+        + BidPrivacy::LEN
+        + 2
+        + 2
+        + 8
+        + 32
+        + 8
+        + 8
+        + AuctionTally::LEN
+        + 4
+        + MAX_LOT_WINNERS * LotAllocation::LEN
+        + 1
+        + 1
+        + 1;
+
+    /// End of the reveal phase, which equals `deadline_ts` for TEE-private auctions.
+    pub fn reveal_deadline_ts(&self) -> i64 {
+        self.deadline_ts + i64::from(self.bid_privacy.reveal_window_secs())
+    }
+
+    /// Whether bidding and tallying are over, so bids can be closed and the lot released.
+    pub fn is_concluded(&self) -> bool {
+        matches!(self.status, AuctionStatus::Ended | AuctionStatus::Cancelled)
+    }
+
+    pub fn allocation(&self, bidder: &Pubkey) -> Option<&LotAllocation> {
+        self.allocations
+            .iter()
+            .find(|allocation| allocation.bidder == *bidder)
+    }
+
+    pub fn has_winner(&self) -> bool {
+        if self.kind.is_multi_unit() {
+            self.allocations
+                .iter()
+                .any(|allocation| allocation.filled > 0)
+        } else {
+            self.highest_bidder != Pubkey::default()
+        }
+    }
+
+    pub fn is_winner(&self, bidder: &Pubkey) -> bool {
+        if self.kind.is_multi_unit() {
+            self.allocation(bidder)
+                .is_some_and(|allocation| allocation.filled > 0)
+        } else {
+            *bidder == self.highest_bidder
+        }
+    }
+
+    /// Token A units awarded to `bidder`, which is the whole lot for single-unit auctions.
+    pub fn filled_quantity(&self, bidder: &Pubkey) -> u64 {
+        if self.kind.is_multi_unit() {
+            self.allocation(bidder)
+                .map_or(0, |allocation| allocation.filled)
+        } else if *bidder == self.highest_bidder {
+            self.lot_amount
+        } else {
+            0
+        }
+    }
+
+    /// Pushes the deadline forward when a bid lands inside the anti-sniping window.
+    ///
+    /// Returns `true` if the deadline moved.
+    pub fn extend_deadline(&mut self, now: i64) -> bool {
+        let window = i64::from(self.extension_window_secs);
+        if window == 0
+            || self.extensions_used >= self.max_extensions
+            || self.deadline_ts - now > window
+        {
+            return false;
+        }
+        self.deadline_ts = self.deadline_ts.max(now + window);
+        self.extensions_used += 1;
+        true
+    }
+
+    /// Token A still held for the seller after subtracting unclaimed winner allocations.
+    pub fn unsold_lot(&self) -> u64 {
+        let reserved: u64 = if self.kind.is_multi_unit() {
+            self.allocations
+                .iter()
+                .filter(|allocation| !allocation.claimed)
+                .map(|allocation| allocation.filled)
+                .sum()
+        } else if self.highest_bidder != Pubkey::default() && !self.lot_claimed {
+            self.lot_amount
+        } else {
+            0
+        };
+        self.lot_remaining.saturating_sub(reserved)
+    }
+}
+
+/// Asset bidders escrow and the seller receives.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BidCurrency {
+    /// Token B held in the auction-owned Token B account.
+    Token,
+    /// Native SOL held in the system-owned L1 bid vault PDA and tracked per bidder by `SolDeposit`.
+    Lamports,
+}
+
+/// Shape of the Token A lot, detected from its mint at initialization.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LotKind {
+    Fungible,
+    /// A zero-decimal mint with a supply of one, sold whole to a single winner.
+    Nft,
+}
+
+/// How bid amounts are kept hidden until the auction ends.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BidPrivacy {
+    /// Bid accounts are private PER accounts on a TEE-backed ER.
+    Tee,
+    /// Bids store a hash plus a deposit and are revealed within `reveal_window_secs` after the
+    /// deadline, so no TEE is required.
+    CommitReveal { reveal_window_secs: u32 },
+}
+
+impl BidPrivacy {
+    pub const LEN: usize = 1 + 4;
+
+    pub fn reveal_window_secs(&self) -> u32 {
+        match self {
+            BidPrivacy::CommitReveal { reveal_window_secs } => *reveal_window_secs,
+            BidPrivacy::Tee => 0,
+        }
+    }
+}
+
+/// Bid contents submitted by `place_bid` and `update_bid`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum BidTerms {
+    /// Escrows `unit_price * quantity` Token B for a TEE-private auction.
+    Plain { unit_price: u64, quantity: u64 },
+    /// Escrows `deposit` Token B behind a `Bid::commitment_for` hash for a commit-reveal auction.
+    Committed { commitment: [u8; 32], deposit: u64 },
+}
+
+/// Optional anti-sniping rule: bids placed within `extension_window_secs` of the deadline push it
+/// to `extension_window_secs` after the bid, at most `max_extensions` times.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct AntiSnipingConfig {
+    pub extension_window_secs: u32,
+    pub max_extensions: u8,
+}
+
+/// Cursor carried across `end_auction` calls while bids are tallied in chunks.
+///
+/// Bids must be supplied in `bidder_index` order starting at `next_index`, so every accepted bid is
+/// counted exactly once and the tally completes when `next_index` reaches `bid_count`. Uniform-price
+/// auctions also accumulate the demand above, at, and below `proposed_price` so the proposal can be
+/// verified as the clearing price once every bid has been seen.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct AuctionTally {
+    pub next_index: u16,
+    pub highest_bid: u64,
+    pub highest_bidder: Pubkey,
+    pub second_bid: u64,
+    pub proposed_price: u64,
+    pub demand_above: u64,
+    pub demand_at: u64,
+    pub demand_below: u64,
+}
+
+impl AuctionTally {
+    pub const LEN: usize = 2 + 8 + 32 + 8 + 8 + 8 + 8 + 8;
+}
+
+/// Token A units awarded to one bidder in a uniform-price auction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct LotAllocation {
+    pub bidder: Pubkey,
+    pub quantity: u64,
+    pub unit_price: u64,
+    pub filled: u64,
+    pub claimed: bool,
+}
+
+impl LotAllocation {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 1;
+}
+
+/// Pricing rule applied when `end_auction` turns the tallied bids into a clearing price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AuctionKind {
+    /// The winner pays their own bid.
+    FirstPrice,
+    /// The winner pays the runner-up bid (Vickrey).
+    SecondPrice,
+    /// The winner pays their own bid, but only if it meets the seller's reserve.
+    ReservePrice { reserve_price: u64 },
+    /// The lot is split across bids by unit price and every winner pays the same clearing price.
+    UniformPrice,
+}
+
+impl AuctionKind {
+    pub const LEN: usize = 1 + 8;
+
+    pub fn is_multi_unit(&self) -> bool {
+        matches!(self, AuctionKind::UniformPrice)
+    }
+
+    pub fn reserve_price(&self) -> u64 {
+        match self {
+            AuctionKind::ReservePrice { reserve_price } => *reserve_price,
+            _ => 0,
+        }
+    }
+
+    /// Returns the price the winner pays, or `None` when the lot goes unsold.
+    ///
+    /// A second-price winner with no runner-up pays the reserve if there is one, and otherwise
+    /// their own bid, so a lone bidder never takes the lot for free.
+    pub fn clearing_price(&self, highest_bid: u64, second_bid: u64) -> Option<u64> {
+        if highest_bid == 0 || highest_bid < self.reserve_price() {
+            return None;
+        }
+        match self {
+            AuctionKind::SecondPrice => match second_bid.max(self.reserve_price()) {
+                0 => Some(highest_bid),
+                runner_up => Some(runner_up),
+            },
+            _ => Some(highest_bid),
+        }
+    }
 }
 
 #[account]
@@ -28,18 +285,78 @@ pub struct Bid {
     pub auction: Pubkey,
     pub bidder: Pubkey,
     pub amount: u64,
-    pub bidder_index: u8,
+    pub quantity: u64,
+    pub unit_price: u64,
+    pub bidder_index: u16,
     pub escrow: Pubkey,
+    pub commitment: [u8; 32],
+    pub revealed: bool,
     pub bump: u8,
 }
 
 impl Bid {
-    pub const LEN: usize = 32 + 32 + 8 + 1 + 32 + 1;
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 2 + 32 + 32 + 1 + 1;
+
+    /// Hash a commit-reveal bidder submits in place of the bid terms.
+    ///
+    /// Binding the auction and bidder keeps a public commitment from being copied into another
+    /// bid and replayed with the revealed preimage.
+    pub fn commitment_for(
+        auction: &Pubkey,
+        bidder: &Pubkey,
+        unit_price: u64,
+        quantity: u64,
+        salt: &[u8; 32],
+    ) -> [u8; 32] {
+        solana_sha256_hasher::hashv(&[
+            auction.as_ref(),
+            bidder.as_ref(),
+            &unit_price.to_le_bytes(),
+            &quantity.to_le_bytes(),
+            salt,
+        ])
+        .to_bytes()
+    }
+
+    /// Total the bid offers, which can be below the escrowed `amount` for a revealed deposit.
+    pub fn value(&self) -> u64 {
+        self.unit_price.saturating_mul(self.quantity)
+    }
+
+    /// Whether this is a commit-reveal bid whose terms have not been revealed yet.
+    pub fn is_unrevealed(&self) -> bool {
+        self.commitment != [0; 32] && !self.revealed
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum AuctionStatus {
     Open,
+    Tallying,
     Ended,
     Settled,
+    Cancelled,
+}
+
+/// A bidder's SOL collateral for one lamport auction.
+///
+/// The lamports stay in the L1 bid vault, which is never delegated. Only this record moves to the
+/// ER, where bids lock collateral against it and settlement books the seller's share; the vault
+/// pays both sides out on L1 once the record and the auction are back.
+#[account]
+pub struct SolDeposit {
+    pub auction: Pubkey,
+    pub bidder: Pubkey,
+    /// Lamports the bidder has moved into the bid vault.
+    pub amount: u64,
+    /// Whether the bidder has an open bid. The whole deposit backs it, so the bid amount itself
+    /// only lives in the private bid account.
+    pub locked: bool,
+    /// Part of `amount` that belongs to the seller after settlement or slashing.
+    pub owed_to_seller: u64,
+    pub bump: u8,
+}
+
+impl SolDeposit {
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 8 + 1;
 }
@@ -0,0 +1,34 @@
+use sealed_auction::error::ErrorCode;
+use solana_program_test::tokio;
+
+mod utils;
+
+#[tokio::test]
+async fn rejects_refund_before_auction_ends() {
+    let mut fixture = utils::setup(1).await;
+    fixture.open_with_bids(&[100]).await;
+
+    let refund_ix = fixture.claim_refund_ix(0);
+    let result = fixture.process(&[refund_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::AuctionNotEnded);
+}
+
+#[tokio::test]
+async fn rejects_refund_for_winner() {
+    let mut fixture = utils::setup(2).await;
+    fixture.open_with_bids(&[100, 250]).await;
+
+    fixture.warp_to_deadline().await;
+    let end_ix = fixture.end_auction_ix(&[0, 1]);
+    fixture.process(&[end_ix], &[]).await.unwrap();
+
+    let refund_ix = fixture.claim_refund_ix(1);
+    let result = fixture.process(&[refund_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::WinnerCannotRefund);
+
+    let winner_token_b = fixture.bidder_token_b[1];
+    assert_eq!(
+        fixture.token_balance(winner_token_b).await,
+        utils::BIDDER_TOKEN_B - 250
+    );
+}
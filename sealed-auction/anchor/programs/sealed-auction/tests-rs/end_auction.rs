@@ -0,0 +1,168 @@
+use sealed_auction::error::ErrorCode;
+use sealed_auction::state::{AuctionKind, AuctionStatus, MAX_LOT_WINNERS};
+use solana_program_test::tokio;
+
+mod utils;
+
+#[tokio::test]
+async fn rejects_tally_before_deadline() {
+    let mut fixture = utils::setup(1).await;
+    fixture.open_with_bids(&[100]).await;
+
+    let end_ix = fixture.end_auction_ix(&[0]);
+    let result = fixture.process(&[end_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::AuctionStillOpen);
+}
+
+#[tokio::test]
+async fn rejects_tally_without_bid_accounts() {
+    let mut fixture = utils::setup(2).await;
+    fixture.open_with_bids(&[100, 250]).await;
+
+    fixture.warp_to_deadline().await;
+    let end_ix = fixture.end_auction_ix(&[]);
+    let result = fixture.process(&[end_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::MissingBid);
+    assert_eq!(fixture.auction_state().await.status, AuctionStatus::Open);
+}
+
+#[tokio::test]
+async fn rejects_duplicate_bid_accounts() {
+    let mut fixture = utils::setup(2).await;
+    fixture.open_with_bids(&[100, 250]).await;
+
+    fixture.warp_to_deadline().await;
+    let end_ix = fixture.end_auction_ix(&[0, 0]);
+    let result = fixture.process(&[end_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::DuplicateBid);
+
+    let end_ix = fixture.end_auction_ix(&[1, 0]);
+    let result = fixture.process(&[end_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::BidOutOfOrder);
+}
+
+#[tokio::test]
+async fn tallies_across_transactions() {
+    let mut fixture = utils::setup(2).await;
+    fixture.open_with_bids(&[250, 100]).await;
+
+    fixture.warp_to_deadline().await;
+    let first_chunk_ix = fixture.end_auction_ix(&[0]);
+    fixture.process(&[first_chunk_ix], &[]).await.unwrap();
+    let auction = fixture.auction_state().await;
+    assert_eq!(auction.status, AuctionStatus::Tallying);
+    assert_eq!(auction.tally.next_index, 1);
+
+    let replay_ix = fixture.end_auction_ix(&[0]);
+    let result = fixture.process(&[replay_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::DuplicateBid);
+
+    let last_chunk_ix = fixture.end_auction_ix(&[1]);
+    fixture.process(&[last_chunk_ix], &[]).await.unwrap();
+    let auction = fixture.auction_state().await;
+    assert_eq!(auction.status, AuctionStatus::Ended);
+    assert_eq!(auction.highest_bidder, fixture.bidder(0));
+    assert_eq!(auction.highest_bid, 250);
+}
+
+#[tokio::test]
+async fn second_price_with_single_bid_charges_the_bid() {
+    let mut fixture = utils::setup(1).await;
+    let init_ix = fixture.initialize_auction_ix(AuctionKind::SecondPrice);
+    fixture.process(&[init_ix], &[]).await.unwrap();
+    let place_bid_ix = fixture.place_bid_ix(0, 300);
+    fixture.process(&[place_bid_ix], &[0]).await.unwrap();
+
+    fixture.warp_to_deadline().await;
+    let end_ix = fixture.end_auction_ix(&[0]);
+    fixture.process(&[end_ix], &[]).await.unwrap();
+    let auction = fixture.auction_state().await;
+    assert_eq!(auction.status, AuctionStatus::Ended);
+    assert_eq!(auction.highest_bidder, fixture.bidder(0));
+    assert_eq!(auction.second_bid, 0);
+    assert_eq!(auction.clearing_price, 300);
+}
+
+#[tokio::test]
+async fn uniform_price_refunds_winners_past_the_allocation_table() {
+    let bidder_count = MAX_LOT_WINNERS + 2;
+    let mut fixture = utils::setup(bidder_count).await;
+    let init_ix = fixture.initialize_auction_ix(AuctionKind::UniformPrice);
+    fixture.process(&[init_ix], &[]).await.unwrap();
+    for bidder in 0..bidder_count {
+        let place_bid_ix = fixture.place_bid_ix(bidder, 100);
+        fixture.process(&[place_bid_ix], &[bidder]).await.unwrap();
+    }
+
+    fixture.warp_to_deadline().await;
+    let bidders: Vec<usize> = (0..bidder_count).collect();
+    let end_ix = fixture.end_auction_at_price_ix(&bidders, 100);
+    fixture.process(&[end_ix], &[]).await.unwrap();
+    let auction = fixture.auction_state().await;
+    assert_eq!(auction.status, AuctionStatus::Ended);
+    assert_eq!(auction.clearing_price, 100);
+    assert_eq!(auction.allocations.len(), MAX_LOT_WINNERS);
+    for bidder in 0..MAX_LOT_WINNERS {
+        assert_eq!(auction.filled_quantity(&fixture.bidder(bidder)), 1);
+    }
+
+    let late_bidder = bidder_count - 1;
+    assert!(!auction.is_winner(&fixture.bidder(late_bidder)));
+    let refund_ix = fixture.claim_refund_ix(late_bidder);
+    fixture.process(&[refund_ix], &[]).await.unwrap();
+    let late_token_b = fixture.bidder_token_b[late_bidder];
+    assert_eq!(
+        fixture.token_balance(late_token_b).await,
+        utils::BIDDER_TOKEN_B
+    );
+}
+
+#[tokio::test]
+async fn cancels_before_deadline() {
+    let mut fixture = utils::setup(1).await;
+    fixture.open_with_bids(&[100]).await;
+
+    let cancel_ix = fixture.cancel_auction_ix();
+    fixture.process(&[cancel_ix], &[]).await.unwrap();
+    assert_eq!(
+        fixture.auction_state().await.status,
+        AuctionStatus::Cancelled
+    );
+}
+
+#[tokio::test]
+async fn cancels_untallied_auction_after_deadline() {
+    let mut fixture = utils::setup(2).await;
+    fixture.open_with_bids(&[100, 250]).await;
+
+    fixture.warp_to_deadline().await;
+    let first_chunk_ix = fixture.end_auction_ix(&[0]);
+    fixture.process(&[first_chunk_ix], &[]).await.unwrap();
+    let cancel_ix = fixture.cancel_auction_ix();
+    fixture.process(&[cancel_ix], &[]).await.unwrap();
+    assert_eq!(
+        fixture.auction_state().await.status,
+        AuctionStatus::Cancelled
+    );
+
+    let refund_ix = fixture.claim_refund_ix(1);
+    fixture.process(&[refund_ix], &[]).await.unwrap();
+    let bidder_token_b = fixture.bidder_token_b[1];
+    assert_eq!(
+        fixture.token_balance(bidder_token_b).await,
+        utils::BIDDER_TOKEN_B
+    );
+}
+
+#[tokio::test]
+async fn rejects_cancel_after_end() {
+    let mut fixture = utils::setup(2).await;
+    fixture.open_with_bids(&[100, 250]).await;
+
+    fixture.warp_to_deadline().await;
+    let end_ix = fixture.end_auction_ix(&[0, 1]);
+    fixture.process(&[end_ix], &[]).await.unwrap();
+    let cancel_ix = fixture.cancel_auction_ix();
+    let result = fixture.process(&[cancel_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::AuctionClosed);
+}
@@ -0,0 +1,82 @@
+use sealed_auction::error::ErrorCode;
+use sealed_auction::state::AuctionStatus;
+use solana_program_test::tokio;
+
+mod utils;
+
+#[tokio::test]
+async fn settles_winner_refunds_loser_and_finalizes_lot() {
+    let mut fixture = utils::setup(2).await;
+    fixture.open_with_bids(&[100, 250]).await;
+
+    let auction = fixture.auction_state().await;
+    assert_eq!(auction.status, AuctionStatus::Open);
+    assert_eq!(auction.bid_count, 2);
+    let auction_token_a = fixture.auction_token_a();
+    let auction_token_b = fixture.auction_token_b();
+    assert_eq!(
+        fixture.token_balance(auction_token_a).await,
+        utils::LOT_AMOUNT
+    );
+    assert_eq!(fixture.token_balance(auction_token_b).await, 350);
+    let bid = fixture.bid_state(1).await;
+    assert_eq!(bid.bidder, fixture.bidder(1));
+    assert_eq!(bid.amount, 250);
+    assert_eq!(bid.bidder_index, 1);
+
+    fixture.warp_to_deadline().await;
+    let end_ix = fixture.end_auction_ix(&[0, 1]);
+    fixture.process(&[end_ix], &[]).await.unwrap();
+
+    let auction = fixture.auction_state().await;
+    assert_eq!(auction.status, AuctionStatus::Ended);
+    assert_eq!(auction.highest_bid, 250);
+    assert_eq!(auction.highest_bidder, fixture.bidder(1));
+    assert_eq!(auction.clearing_price, 250);
+
+    let settle_ix = fixture.settle_winning_bid_ix(1);
+    fixture.process(&[settle_ix], &[]).await.unwrap();
+    let seller_token_b = fixture.seller_token_b;
+    assert_eq!(fixture.token_balance(seller_token_b).await, 250);
+
+    let refund_ix = fixture.claim_refund_ix(0);
+    fixture.process(&[refund_ix], &[]).await.unwrap();
+    let loser_token_b = fixture.bidder_token_b[0];
+    assert_eq!(
+        fixture.token_balance(loser_token_b).await,
+        utils::BIDDER_TOKEN_B
+    );
+    assert_eq!(fixture.token_balance(auction_token_b).await, 0);
+    assert_eq!(fixture.auction_state().await.closed_bid_count, 2);
+
+    let finalize_ix = fixture.finalize_ix(1);
+    fixture.process(&[finalize_ix], &[]).await.unwrap();
+    let winner_token_a = fixture.bidder_token_a[1];
+    assert_eq!(
+        fixture.token_balance(winner_token_a).await,
+        utils::LOT_AMOUNT
+    );
+
+    let auction = fixture.auction_state().await;
+    assert_eq!(auction.status, AuctionStatus::Settled);
+    assert!(auction.lot_claimed);
+}
+
+#[tokio::test]
+async fn auction_without_bids_has_no_winner_to_settle() {
+    let mut fixture = utils::setup(1).await;
+    fixture.open_with_bids(&[]).await;
+
+    fixture.warp_to_deadline().await;
+    let end_ix = fixture.end_auction_ix(&[]);
+    fixture.process(&[end_ix], &[]).await.unwrap();
+    assert_eq!(fixture.auction_state().await.status, AuctionStatus::Ended);
+
+    let settle_ix = fixture.settle_winning_bid_ix(0);
+    let result = fixture.process(&[settle_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::MissingBid);
+
+    let finalize_ix = fixture.finalize_ix(0);
+    let result = fixture.process(&[finalize_ix], &[]).await;
+    utils::assert_program_error(result, ErrorCode::MissingBid);
+}
@@ -0,0 +1,544 @@
+#![allow(dead_code)]
+
+use anchor_lang::prelude::{Clock, Pubkey, Rent};
+use anchor_lang::solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    system_program,
+};
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token::spl_token::{
+    self,
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use ephemeral_rollups_sdk::access_control::structs::PERMISSION_SEED;
+use ephemeral_rollups_sdk::consts::{EPHEMERAL_VAULT_ID, MAGIC_PROGRAM_ID, PERMISSION_PROGRAM_ID};
+use sealed_auction::error::ErrorCode;
+use sealed_auction::state::{Auction, AuctionKind, Bid, BidPrivacy, BidTerms};
+use sealed_auction::{
+    AUCTION_SEED, BID_SEED, DELEGATION_PROGRAM_ID, EPHEMERAL_SPL_TOKEN_PROGRAM_ID,
+};
+use solana_account::Account;
+use solana_keypair::Keypair;
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+
+pub const AUCTION_ID: u64 = 1;
+pub const LOT_AMOUNT: u64 = 1_000;
+pub const BIDDER_TOKEN_B: u64 = 10_000;
+pub const AUCTION_DURATION_SECS: i64 = 60;
+
+/// One auction with funded bidders, ready to run against the stubbed ER programs.
+///
+/// The magic program cannot allocate ephemeral accounts in a program test, so every bidder's
+/// `Bid` PDA is preloaded as a zeroed, program-owned account, which is what `place_bid` sees on
+/// the ER after `create_ephemeral_bid`.
+pub struct Fixture {
+    pub context: ProgramTestContext,
+    pub auctioneer: Keypair,
+    pub bidders: Vec<Keypair>,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub seller_token_a: Pubkey,
+    pub seller_token_b: Pubkey,
+    pub bidder_token_a: Vec<Pubkey>,
+    pub bidder_token_b: Vec<Pubkey>,
+    pub auction: Pubkey,
+    pub deadline_ts: i64,
+}
+
+pub async fn setup(bidder_count: usize) -> Fixture {
+    let mut program_test = ProgramTest::new("sealed_auction", sealed_auction::ID, None);
+    program_test.prefer_bpf(false);
+    program_test.add_program("magic_program", MAGIC_PROGRAM_ID, processor!(accept_stub));
+    program_test.add_program(
+        "magicblock_permission_program",
+        PERMISSION_PROGRAM_ID,
+        processor!(accept_stub),
+    );
+    program_test.add_program(
+        "ephemeral_token_program",
+        EPHEMERAL_SPL_TOKEN_PROGRAM_ID,
+        processor!(accept_stub),
+    );
+
+    let auctioneer = Keypair::new();
+    add_wallet(&mut program_test, auctioneer.pubkey(), 10_000_000_000);
+    let bidders: Vec<Keypair> = (0..bidder_count).map(|_| Keypair::new()).collect();
+    for bidder in &bidders {
+        add_wallet(&mut program_test, bidder.pubkey(), 1_000_000_000);
+    }
+
+    let token_a_mint = Pubkey::new_unique();
+    let token_b_mint = Pubkey::new_unique();
+    add_mint(
+        &mut program_test,
+        token_a_mint,
+        auctioneer.pubkey(),
+        LOT_AMOUNT,
+    );
+    add_mint(
+        &mut program_test,
+        token_b_mint,
+        auctioneer.pubkey(),
+        BIDDER_TOKEN_B * bidder_count as u64,
+    );
+
+    let seller_token_a = Pubkey::new_unique();
+    let seller_token_b = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        seller_token_a,
+        token_a_mint,
+        auctioneer.pubkey(),
+        LOT_AMOUNT,
+    );
+    add_token_account(
+        &mut program_test,
+        seller_token_b,
+        token_b_mint,
+        auctioneer.pubkey(),
+        0,
+    );
+
+    let auction = auction_pda(auctioneer.pubkey(), AUCTION_ID);
+    let mut bidder_token_a = Vec::with_capacity(bidder_count);
+    let mut bidder_token_b = Vec::with_capacity(bidder_count);
+    for bidder in &bidders {
+        let token_a = Pubkey::new_unique();
+        let token_b = Pubkey::new_unique();
+        add_token_account(&mut program_test, token_a, token_a_mint, bidder.pubkey(), 0);
+        add_token_account(
+            &mut program_test,
+            token_b,
+            token_b_mint,
+            bidder.pubkey(),
+            BIDDER_TOKEN_B,
+        );
+        bidder_token_a.push(token_a);
+        bidder_token_b.push(token_b);
+
+        let bid_size = 8 + Bid::LEN;
+        program_test.add_account(
+            bid_pda(auction, bidder.pubkey()),
+            Account {
+                lamports: Rent::default().minimum_balance(bid_size),
+                data: vec![0; bid_size],
+                owner: sealed_auction::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    let mut context = program_test.start_with_context().await;
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+
+    Fixture {
+        context,
+        auctioneer,
+        bidders,
+        token_a_mint,
+        token_b_mint,
+        seller_token_a,
+        seller_token_b,
+        bidder_token_a,
+        bidder_token_b,
+        auction,
+        deadline_ts: clock.unix_timestamp + AUCTION_DURATION_SECS,
+    }
+}
+
+impl Fixture {
+    pub fn bidder(&self, index: usize) -> Pubkey {
+        self.bidders[index].pubkey()
+    }
+
+    pub fn bid(&self, index: usize) -> Pubkey {
+        bid_pda(self.auction, self.bidder(index))
+    }
+
+    pub fn auction_token_a(&self) -> Pubkey {
+        get_associated_token_address(&self.auction, &self.token_a_mint)
+    }
+
+    pub fn auction_token_b(&self) -> Pubkey {
+        get_associated_token_address(&self.auction, &self.token_b_mint)
+    }
+
+    pub fn initialize_auction_ix(&self, kind: AuctionKind) -> Instruction {
+        let ephemeral_ata = ephemeral_ata_pda(self.auction, self.token_b_mint);
+        Instruction {
+            program_id: sealed_auction::ID,
+            accounts: sealed_auction::accounts::InitializeAuction {
+                auctioneer: self.auctioneer.pubkey(),
+                token_a_mint: self.token_a_mint,
+                token_b_mint: self.token_b_mint,
+                auction: self.auction,
+                auction_token_a_account: self.auction_token_a(),
+                auction_token_b_account: self.auction_token_b(),
+                auction_token_b_ephemeral_ata: ephemeral_ata,
+                auction_token_b_eata_buffer: eata_buffer_pda(ephemeral_ata),
+                auction_token_b_eata_record: delegation_record_pda(ephemeral_ata),
+                auction_token_b_eata_metadata: delegation_metadata_pda(ephemeral_ata),
+                seller_token_a_account: self.seller_token_a,
+                ephemeral_token_program: EPHEMERAL_SPL_TOKEN_PROGRAM_ID,
+                delegation_program: DELEGATION_PROGRAM_ID,
+                validator: None,
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: sealed_auction::instruction::InitializeAuction {
+                auction_id: AUCTION_ID,
+                lot_amount: LOT_AMOUNT,
+                deadline_ts: self.deadline_ts,
+                sponsor_lamports: 0,
+                kind,
+                anti_sniping: None,
+                bid_privacy: BidPrivacy::Tee,
+            }
+            .data(),
+        }
+    }
+
+    pub fn place_bid_ix(&self, bidder: usize, unit_price: u64) -> Instruction {
+        Instruction {
+            program_id: sealed_auction::ID,
+            accounts: sealed_auction::accounts::PlaceBid {
+                payer: self.auctioneer.pubkey(),
+                bidder: self.bidder(bidder),
+                token_b_mint: Some(self.token_b_mint),
+                auction: self.auction,
+                bid: self.bid(bidder),
+                bidder_token_b_account: Some(self.bidder_token_b[bidder]),
+                auction_token_b_account: Some(self.auction_token_b()),
+                sol_deposit: None,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                vault: EPHEMERAL_VAULT_ID,
+                magic_program: MAGIC_PROGRAM_ID,
+            }
+            .to_account_metas(None),
+            data: sealed_auction::instruction::PlaceBid {
+                auction_id: AUCTION_ID,
+                terms: BidTerms::Plain {
+                    unit_price,
+                    quantity: 1,
+                },
+            }
+            .data(),
+        }
+    }
+
+    pub fn end_auction_ix(&self, bidders: &[usize]) -> Instruction {
+        self.end_auction_at_price_ix(bidders, 0)
+    }
+
+    /// Tallies `bidders` against a uniform-price clearing proposal.
+    pub fn end_auction_at_price_ix(&self, bidders: &[usize], proposed_price: u64) -> Instruction {
+        let mut accounts = sealed_auction::accounts::EndAuction {
+            auctioneer: self.auctioneer.pubkey(),
+            auction: self.auction,
+        }
+        .to_account_metas(None);
+        accounts.extend(
+            bidders
+                .iter()
+                .map(|bidder| AccountMeta::new(self.bid(*bidder), false)),
+        );
+        Instruction {
+            program_id: sealed_auction::ID,
+            accounts,
+            data: sealed_auction::instruction::EndAuction {
+                auction_id: AUCTION_ID,
+                proposed_price,
+            }
+            .data(),
+        }
+    }
+
+    pub fn cancel_auction_ix(&self) -> Instruction {
+        Instruction {
+            program_id: sealed_auction::ID,
+            accounts: sealed_auction::accounts::CancelAuction {
+                auctioneer: self.auctioneer.pubkey(),
+                auction: self.auction,
+            }
+            .to_account_metas(None),
+            data: sealed_auction::instruction::CancelAuction {
+                auction_id: AUCTION_ID,
+            }
+            .data(),
+        }
+    }
+
+    pub fn settle_winning_bid_ix(&self, winner: usize) -> Instruction {
+        let winning_bid = self.bid(winner);
+        Instruction {
+            program_id: sealed_auction::ID,
+            accounts: sealed_auction::accounts::SettleWinningBid {
+                crank: self.auctioneer.pubkey(),
+                auction: self.auction,
+                winner: self.bidder(winner),
+                winning_bid,
+                token_b_mint: Some(self.token_b_mint),
+                auction_token_b_account: Some(self.auction_token_b()),
+                seller_token_b_account: Some(self.seller_token_b),
+                winner_token_b_account: Some(self.bidder_token_b[winner]),
+                sol_deposit: None,
+                bid_permission: permission_pda(winning_bid),
+                permission_program: PERMISSION_PROGRAM_ID,
+                magic_program: MAGIC_PROGRAM_ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                vault: EPHEMERAL_VAULT_ID,
+            }
+            .to_account_metas(None),
+            data: sealed_auction::instruction::SettleWinningBid {}.data(),
+        }
+    }
+
+    pub fn claim_refund_ix(&self, bidder: usize) -> Instruction {
+        let bid = self.bid(bidder);
+        Instruction {
+            program_id: sealed_auction::ID,
+            accounts: sealed_auction::accounts::ClaimRefund {
+                bidder: self.bidder(bidder),
+                auction: self.auction,
+                bid,
+                token_b_mint: Some(self.token_b_mint),
+                auction_token_b_account: Some(self.auction_token_b()),
+                bidder_token_b_account: Some(self.bidder_token_b[bidder]),
+                sol_deposit: None,
+                bid_permission: permission_pda(bid),
+                permission_program: PERMISSION_PROGRAM_ID,
+                magic_program: MAGIC_PROGRAM_ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                vault: EPHEMERAL_VAULT_ID,
+            }
+            .to_account_metas(None),
+            data: sealed_auction::instruction::ClaimRefund {}.data(),
+        }
+    }
+
+    pub fn finalize_ix(&self, winner: usize) -> Instruction {
+        Instruction {
+            program_id: sealed_auction::ID,
+            accounts: sealed_auction::accounts::Finalize {
+                auction: self.auction,
+                token_a_mint: self.token_a_mint,
+                auction_token_a_account: self.auction_token_a(),
+                winner: self.bidder(winner),
+                winner_token_a_account: self.bidder_token_a[winner],
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: sealed_auction::instruction::Finalize {
+                auction_id: AUCTION_ID,
+            }
+            .data(),
+        }
+    }
+
+    /// Sends `ixs` paid by the auctioneer and co-signed by the listed bidders.
+    pub async fn process(
+        &mut self,
+        ixs: &[Instruction],
+        bidders: &[usize],
+    ) -> Result<(), TransactionError> {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut signers = vec![&self.auctioneer];
+        signers.extend(bidders.iter().map(|bidder| &self.bidders[*bidder]));
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&self.auctioneer.pubkey()),
+            &signers,
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(tx)
+            .await
+            .map_err(|err| err.unwrap())
+    }
+
+    /// Initializes the auction and places one single-unit bid per entry of `unit_prices`.
+    pub async fn open_with_bids(&mut self, unit_prices: &[u64]) {
+        let init_ix = self.initialize_auction_ix(AuctionKind::FirstPrice);
+        self.process(&[init_ix], &[]).await.unwrap();
+        for (bidder, unit_price) in unit_prices.iter().enumerate() {
+            let place_bid_ix = self.place_bid_ix(bidder, *unit_price);
+            self.process(&[place_bid_ix], &[bidder]).await.unwrap();
+        }
+    }
+
+    pub async fn warp_to_deadline(&mut self) {
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = self.deadline_ts;
+        self.context.set_sysvar(&clock);
+    }
+
+    pub async fn auction_state(&mut self) -> Auction {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.auction)
+            .await
+            .unwrap()
+            .expect("auction account must exist");
+        Auction::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn bid_state(&mut self, bidder: usize) -> Bid {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.bid(bidder))
+            .await
+            .unwrap()
+            .expect("bid account must exist");
+        Bid::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn token_balance(&mut self, token_account: Pubkey) -> u64 {
+        let account = self
+            .context
+            .banks_client
+            .get_account(token_account)
+            .await
+            .unwrap()
+            .expect("token account must exist");
+        TokenAccount::unpack(&account.data).unwrap().amount
+    }
+}
+
+pub fn assert_program_error(result: Result<(), TransactionError>, expected: ErrorCode) {
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::InstructionError(0, InstructionError::Custom(expected.into())),
+    );
+}
+
+pub fn auction_pda(auctioneer: Pubkey, auction_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[AUCTION_SEED, auctioneer.as_ref(), &auction_id.to_le_bytes()],
+        &sealed_auction::ID,
+    )
+    .0
+}
+
+pub fn bid_pda(auction: Pubkey, bidder: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[BID_SEED, auction.as_ref(), bidder.as_ref()],
+        &sealed_auction::ID,
+    )
+    .0
+}
+
+pub fn permission_pda(account: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[PERMISSION_SEED, account.as_ref()], &PERMISSION_PROGRAM_ID).0
+}
+
+pub fn ephemeral_ata_pda(owner: Pubkey, mint: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), mint.as_ref()],
+        &EPHEMERAL_SPL_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn eata_buffer_pda(ephemeral_ata: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"buffer", ephemeral_ata.as_ref()],
+        &EPHEMERAL_SPL_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn delegation_record_pda(account: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"delegation", account.as_ref()], &DELEGATION_PROGRAM_ID).0
+}
+
+pub fn delegation_metadata_pda(account: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"delegation-metadata", account.as_ref()],
+        &DELEGATION_PROGRAM_ID,
+    )
+    .0
+}
+
+fn add_wallet(program_test: &mut ProgramTest, address: Pubkey, lamports: u64) {
+    program_test.add_account(
+        address,
+        Account {
+            lamports,
+            data: Vec::new(),
+            owner: system_program::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+fn add_mint(program_test: &mut ProgramTest, address: Pubkey, authority: Pubkey, supply: u64) {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::Some(authority),
+        supply,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    add_token_program_account(program_test, address, data);
+}
+
+fn add_token_account(
+    program_test: &mut ProgramTest,
+    address: Pubkey,
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint,
+        owner,
+        amount,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    add_token_program_account(program_test, address, data);
+}
+
+fn add_token_program_account(program_test: &mut ProgramTest, address: Pubkey, data: Vec<u8>) {
+    program_test.add_account(
+        address,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// Stands in for the magic, permission and ephemeral SPL token programs, whose effects only
+/// exist on the ER.
+fn accept_stub(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
@@ -31,6 +31,7 @@ type IdlInstruction = {
 
 const idl = require("../target/idl/sealed_auction.json") as {
   instructions: IdlInstruction[];
+  events: { name: string }[];
   types: {
     name: string;
     type: {
@@ -40,6 +41,21 @@ const idl = require("../target/idl/sealed_auction.json") as {
   }[];
 };
 
+type AuctionKind =
+  | { firstPrice: Record<string, never> }
+  | { secondPrice: Record<string, never> }
+  | { reservePrice: { reservePrice: BN } }
+  | { uniformPrice: Record<string, never> };
+
+type AntiSnipingConfig = {
+  extensionWindowSecs: number;
+  maxExtensions: number;
+};
+
+type BidPrivacy =
+  | { tee: Record<string, never> }
+  | { commitReveal: { revealWindowSecs: number } };
+
 const AUCTION_SEED = Buffer.from("auction");
 const BID_SEED = Buffer.from("bid");
 const LOT_AMOUNT = new BN(100);
@@ -426,6 +442,9 @@ describe("sealed-auction", () => {
     fixture: BaseAuctionFixture,
     deadlineTs = fixture.deadlineTs,
     lotAmount = fixture.lotAmount,
+    kind: AuctionKind = { firstPrice: {} },
+    antiSniping: AntiSnipingConfig | null = null,
+    bidPrivacy: BidPrivacy = { tee: {} },
   ): Promise<void> {
     await program.methods
       .initializeAuction(
@@ -433,6 +452,9 @@ describe("sealed-auction", () => {
         lotAmount,
         deadlineTs,
         SPONSOR_LAMPORTS,
+        kind,
+        antiSniping,
+        bidPrivacy,
       )
       .accountsPartial({
         auctioneer: auctioneer.publicKey,
@@ -494,18 +516,28 @@ describe("sealed-auction", () => {
       .sort();
 
     expect(instructionNames).to.deep.equal([
+      "cancel_auction",
       "claim_refund",
       "delegate_auction",
+      "delegate_sol_deposit",
+      "deposit_sol",
       "end_auction",
       "finalize",
       "init_auction_permission",
       "init_bid_permission",
       "initialize_auction",
+      "initialize_sol_auction",
       "place_bid",
       "process_undelegation",
       "reclaim_unsold_lot",
+      "release_sol_deposit",
+      "reveal_bid",
       "settle_winning_bid",
+      "slash_bid",
       "undelegate_auction",
+      "undelegate_sol_deposit",
+      "update_bid",
+      "withdraw_bid",
     ]);
     expect(instructionNames).not.to.include("initialize_lot_escrow");
     expect(instructionNames).not.to.include("undelegate_lot_escrow");
@@ -515,6 +547,39 @@ describe("sealed-auction", () => {
     expect(instructionNames).not.to.include("close_winning_bid");
   });
 
+  it("emits typed events across the auction lifecycle", () => {
+    const eventNames = idl.events.map((event) => event.name).sort();
+    const bidPlacedFields =
+      idl.types
+        .find((typeDef) => typeDef.name === "BidPlaced")
+        ?.type.fields?.map((field) => field.name) ?? [];
+
+    expect(eventNames).to.deep.equal([
+      "AuctionCancelled",
+      "AuctionInitialized",
+      "AuctionTallied",
+      "BidPlaced",
+      "BidRevealed",
+      "BidSlashed",
+      "BidUpdated",
+      "BidWithdrawn",
+      "LotFinalized",
+      "RefundClaimed",
+      "SolDepositReleased",
+      "UnsoldLotReclaimed",
+      "WinningBidSettled",
+    ]);
+    expect(bidPlacedFields).to.include("auction_id");
+    expect(bidPlacedFields).not.to.include("bidder");
+    expect(bidPlacedFields).not.to.include("amount");
+    expect(bidPlacedFields).not.to.include("unit_price");
+    expect(
+      idl.types
+        .find((typeDef) => typeDef.name === "BidUpdated")
+        ?.type.fields?.map((field) => field.name),
+    ).not.to.include("amount");
+  });
+
   it("does not reintroduce Token A e-token custody accounts", () => {
     const byName = new Map(
       idl.instructions.map((instruction) => [instruction.name, instruction]),
@@ -592,11 +657,51 @@ describe("sealed-auction", () => {
     expect(
       initializeAuctionInstruction?.args.map((arg) => arg.name),
     ).to.include("sponsor_lamports");
+    expect(
+      initializeAuctionInstruction?.args.map((arg) => arg.name),
+    ).to.include("kind");
+    expect(
+      initializeAuctionInstruction?.args.map((arg) => arg.name),
+    ).to.include("anti_sniping");
+    expect(auctionFields).to.include("extension_window_secs");
+    expect(auctionFields).to.include("max_extensions");
+    expect(auctionFields).to.include("extensions_used");
+    expect(
+      initializeAuctionInstruction?.args.map((arg) => arg.name),
+    ).to.include("bid_privacy");
+    expect(auctionFields).to.include("bid_privacy");
+    expect(typeNames).to.include("BidTerms");
+    expect(auctionFields).to.include("bid_currency");
+    expect(auctionFields).to.include("lot_kind");
+    expect(typeNames).to.include("BidCurrency");
+    expect(typeNames).to.include("LotKind");
+    expect(
+      byName
+        .get("initialize_sol_auction")
+        ?.accounts.map((account) => account.name),
+    ).to.include("bid_vault");
+    expect(claimRefundAccounts).to.include("sol_deposit");
+    expect(claimRefundAccounts).not.to.include("bid_vault");
+    expect(settleWinningBidAccounts).to.include("sol_deposit");
+    expect(settleWinningBidAccounts).not.to.include("seller_wallet");
+    expect(
+      byName
+        .get("release_sol_deposit")
+        ?.accounts.map((account) => account.name),
+    ).to.include("bid_vault");
+    expect(auctionFields).to.include("second_bid");
+    expect(auctionFields).to.include("clearing_price");
+    expect(settleWinningBidAccounts).to.include("winner");
+    expect(settleWinningBidAccounts).to.include("winner_token_b_account");
+    expect(auctionFields).to.include("allocations");
+    expect(typeNames).to.include("LotAllocation");
     expect(auctionFields).to.include("bid_count");
     expect(auctionFields).to.include("closed_bid_count");
+    expect(auctionFields).to.include("tally");
     expect(auctionFields).not.to.include("bidders");
     expect(auctionFields).not.to.include("bidder_count");
     expect(typeNames).not.to.include("AuctionSponsor");
+    expect(typeNames).to.include("AuctionTally");
     expect(endAuctionAccounts).to.deep.equal(["auctioneer", "auction"]);
     expect(settleWinningBidAccounts).to.include("auction");
     expect(settleWinningBidAccounts).to.include("winning_bid");
@@ -648,6 +753,42 @@ describe("sealed-auction", () => {
       "InvalidAmount",
     );
 
+    const zeroReserveFixture = await createBaseAuctionFixture(60);
+    await expectAnchorError(
+      initializeAuction(
+        zeroReserveFixture,
+        zeroReserveFixture.deadlineTs,
+        zeroReserveFixture.lotAmount,
+        { reservePrice: { reservePrice: new BN(0) } },
+      ),
+      "InvalidAmount",
+    );
+
+    const zeroWindowFixture = await createBaseAuctionFixture(60);
+    await expectAnchorError(
+      initializeAuction(
+        zeroWindowFixture,
+        zeroWindowFixture.deadlineTs,
+        zeroWindowFixture.lotAmount,
+        { firstPrice: {} },
+        { extensionWindowSecs: 0, maxExtensions: 3 },
+      ),
+      "InvalidExtensionConfig",
+    );
+
+    const zeroRevealFixture = await createBaseAuctionFixture(60);
+    await expectAnchorError(
+      initializeAuction(
+        zeroRevealFixture,
+        zeroRevealFixture.deadlineTs,
+        zeroRevealFixture.lotAmount,
+        { firstPrice: {} },
+        null,
+        { commitReveal: { revealWindowSecs: 0 } },
+      ),
+      "InvalidRevealWindow",
+    );
+
     const expiredFixture = await createBaseAuctionFixture(-1);
     await expectAnchorError(
       initializeAuction(expiredFixture),
@@ -834,7 +975,9 @@ describe("sealed-auction", () => {
       ) as Program<SealedAuction>;
 
       const placeBidTx = await bidderErProgram.methods
-        .placeBid(fixture.auctionId, amount)
+        .placeBid(fixture.auctionId, {
+          plain: { unitPrice: amount, quantity: new BN(1) },
+        })
         .accountsPartial({
           payer: auctioneer.publicKey,
           bidder: bidder.publicKey,
@@ -887,7 +1030,7 @@ describe("sealed-auction", () => {
     await sleepUntilUnixTimestamp(privateErConnection, fixture.deadlineTs);
 
     const endAuctionTx = await privateErProgram.methods
-      .endAuction(fixture.auctionId)
+      .endAuction(fixture.auctionId, new BN(0))
       .accountsPartial({
         auctioneer: auctioneer.publicKey,
         auction: fixture.auction,
@@ -919,10 +1062,12 @@ describe("sealed-auction", () => {
       .accountsPartial({
         crank: auctioneer.publicKey,
         auction: fixture.auction,
+        winner: bidderTwo.publicKey,
         winningBid: liveBidTwo,
         tokenBMint: fixture.tokenB,
         auctionTokenBAccount: fixture.auctionTokenB,
         sellerTokenBAccount: sellerTokenB,
+        winnerTokenBAccount: bidderTwoTokenB,
         bidPermission: permissionPdaFromAccount(liveBidTwo),
         permissionProgram: PERMISSION_PROGRAM_ID,
         magicProgram: MAGIC_PROGRAM_ID,
@@ -1008,6 +1153,7 @@ describe("sealed-auction", () => {
         auction: fixture.auction,
         tokenAMint: fixture.tokenA,
         auctionTokenAAccount: fixture.auctionTokenA,
+        winner: bidderTwo.publicKey,
         winnerTokenAAccount: winnerTokenA,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
@@ -1034,7 +1180,7 @@ describe("sealed-auction", () => {
     await sleepUntilUnixTimestamp(provider.connection, fixture.deadlineTs);
 
     await program.methods
-      .endAuction(fixture.auctionId)
+      .endAuction(fixture.auctionId, new BN(0))
       .accountsPartial({
         auctioneer: auctioneer.publicKey,
         auction: fixture.auction,